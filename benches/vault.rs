@@ -0,0 +1,153 @@
+//! Benchmarks of the CPU heavy helpers on a synthetic vault
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use nextcloud_passwords_client::{
+    export::FolderTemplate,
+    folder::Folder,
+    password::{CreatePassword, Password},
+    tree::FolderTree,
+    Filter,
+};
+use serde_json::json;
+
+const ENTRIES: usize = 10_000;
+
+fn uuid(n: usize) -> String {
+    format!("00000000-0000-0000-0000-{:012x}", n)
+}
+
+fn passwords_json() -> String {
+    let passwords: Vec<_> = (0..ENTRIES)
+        .map(|i| {
+            json!({
+                "id": uuid(i + 1),
+                "label": format!("Password {}", i),
+                "username": format!("user{}", i),
+                "password": "correct horse battery staple",
+                "url": format!("https://service{}.{}.example/login", i, i % 7),
+                "notes": "Some notes\n".repeat(10),
+                "customFields": json!([
+                    {"label": "pin", "type": "secret", "value": "1234"},
+                    {"label": "ext:import", "type": "data", "value": i.to_string()},
+                ]).to_string(),
+                "status": 0,
+                "statusCode": "GOOD",
+                "hash": "f3bbbd66a63d4bf1747940578ec3d0103530e21d",
+                "folder": uuid(i % 100),
+                "revision": uuid(i + ENTRIES),
+                "share": null,
+                "shared": false,
+                "cseType": "none",
+                "cseKey": "",
+                "sseType": "SSEv1r2",
+                "client": "CLIENT::UNKNOWN",
+                "hidden": false,
+                "trashed": false,
+                "favorite": i % 10 == 0,
+                "editable": true,
+                "edited": 1_600_000_000,
+                "created": 1_600_000_000,
+                "updated": 1_600_000_000,
+            })
+        })
+        .collect();
+    serde_json::to_string(&passwords).unwrap()
+}
+
+fn folders() -> Vec<Folder> {
+    let folders: Vec<_> = (0..ENTRIES)
+        .map(|i| {
+            json!({
+                "id": uuid(i + 1),
+                "label": format!("Folder {}", i),
+                "parent": uuid(i / 10),
+                "created": 0,
+                "updated": 0,
+                "edited": 0,
+                "revision": uuid(i + ENTRIES),
+                "cseType": "none",
+                "cseKey": "",
+                "sseType": "SSEv1r1",
+                "client": "CLIENT::UNKNOWN",
+                "hidden": false,
+                "trashed": false,
+                "favorite": false,
+            })
+        })
+        .collect();
+    serde_json::from_value(json!(folders)).unwrap()
+}
+
+fn vault(c: &mut Criterion) {
+    let passwords = passwords_json();
+    let parse = || serde_json::from_str::<Vec<Password>>(&passwords).unwrap();
+    let filter = || Filter::url_host_matches("*.3.example").and(Filter::favorite().not());
+
+    c.bench_function("parse passwords", |b| {
+        b.iter(|| serde_json::from_str::<Vec<Password>>(black_box(&passwords)).unwrap())
+    });
+    c.bench_function("filter passwords", |b| {
+        let filter = filter();
+        b.iter_batched(parse, |list| filter.apply(list), BatchSize::LargeInput)
+    });
+    c.bench_function("filter passwords (async)", |b| {
+        let filter = filter();
+        b.iter_batched(
+            parse,
+            |list| futures::executor::block_on(filter.apply_async(list)),
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("external ids", |b| {
+        let list = parse();
+        b.iter(|| {
+            list.iter()
+                .filter(|password| password.external_id("import").is_some())
+                .count()
+        })
+    });
+
+    c.bench_function("build creates from borrowed records", |b| {
+        let list = parse();
+        b.iter(|| {
+            list.iter()
+                .map(|password| {
+                    let versioned = &password.versioned;
+                    let create =
+                        CreatePassword::new(versioned.label.as_str(), password.reveal().expose())
+                            .hash(versioned.hash.as_str())
+                            .username(versioned.username.as_str())
+                            .url(versioned.url.as_str())
+                            .notes(versioned.notes.as_str());
+                    serde_json::to_string(&create).unwrap()
+                })
+                .count()
+        })
+    });
+
+    c.bench_function("folder tree", |b| {
+        b.iter_batched(
+            folders,
+            |list| FolderTree::build(list).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("folder tree (async)", |b| {
+        b.iter_batched(
+            folders,
+            |list| futures::executor::block_on(FolderTree::build_async(list)).unwrap(),
+            BatchSize::LargeInput,
+        )
+    });
+
+    let folders = folders();
+    c.bench_function("folder template", |b| {
+        b.iter(|| FolderTemplate::from_folders(black_box(&folders)))
+    });
+    c.bench_function("folder template (async)", |b| {
+        b.iter(|| futures::executor::block_on(FolderTemplate::from_folders_async(&folders)))
+    });
+}
+
+criterion_group!(benches, vault);
+criterion_main!(benches);
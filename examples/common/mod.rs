@@ -0,0 +1,722 @@
+//! Helpers shared by the examples and the tests: reading the credentials from the environment,
+//! and a mock of the Nextcloud server used by `--mock`. The tests of the helpers use the
+//! [fake](nextcloud_passwords_client::testing::fake::FakeServer) server of the crate instead.
+#![allow(dead_code)]
+
+use nextcloud_passwords_client::testing::{
+    default_settings, listen, not_found, Handler, Request, Response,
+};
+use nextcloud_passwords_client::LoginDetails;
+use serde_json::{json, Map, Value};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use url::Url;
+
+/// Was `--mock` given on the command line
+pub fn mock_requested() -> bool {
+    std::env::args().any(|arg| arg == "--mock")
+}
+
+/// The command line arguments, without the program name and `--mock`
+pub fn args() -> Vec<String> {
+    std::env::args()
+        .skip(1)
+        .filter(|arg| arg != "--mock")
+        .collect()
+}
+
+/// The server to use: a mock server with `--mock`, else the `NC_SERVER` environment variable
+pub fn server() -> Url {
+    if mock_requested() {
+        return MockServer::start();
+    }
+    let server = std::env::var("NC_SERVER").expect("NC_SERVER is not set (or use --mock)");
+    let mut server = Url::parse(&server).expect("NC_SERVER is not a valid URL");
+    if !server.path().ends_with('/') {
+        server.set_path(&format!("{}/", server.path()));
+    }
+    server
+}
+
+/// The credentials from `NC_LOGIN` and `NC_APP_PASSWORD`, if both are set
+pub fn credentials() -> Option<(String, String)> {
+    match (std::env::var("NC_LOGIN"), std::env::var("NC_APP_PASSWORD")) {
+        (Ok(login), Ok(password)) => Some((login, password)),
+        _ => None,
+    }
+}
+
+/// Login details from the environment (see [server] and [credentials]), or for a new mock
+/// server with `--mock`
+pub fn login_details() -> LoginDetails {
+    if mock_requested() {
+        return mock_login_details();
+    }
+    let (login_name, app_password) =
+        credentials().expect("NC_LOGIN and NC_APP_PASSWORD must be set (or use --mock)");
+    LoginDetails {
+        server: server(),
+        login_name,
+        app_password,
+        api_base_override: None,
+        client_name: None,
+        master_password: std::env::var("NC_MASTER_PASSWORD").ok(),
+    }
+}
+
+/// Login details for a new mock server, which accepts any credentials
+pub fn mock_login_details() -> LoginDetails {
+    mock_login_details_for(MockServer::start())
+}
+
+/// Login details for the mock server at `server`
+pub fn mock_login_details_for(server: Url) -> LoginDetails {
+    LoginDetails {
+        server,
+        login_name: "mock".into(),
+        app_password: "mock-app-password".into(),
+        api_base_override: None,
+        client_name: None,
+        master_password: None,
+    }
+}
+
+/// A canned Nextcloud server with the passwords app, listening on localhost. It answers the
+/// login flow v2 and the endpoints used by the examples, and keeps the settings and the shares
+/// in memory.
+pub struct MockServer {
+    url: Url,
+    token_providers: Vec<Value>,
+    /// The providers whose token was sent to the user, see `needs_request`
+    requested_tokens: Vec<String>,
+    settings: Map<String, Value>,
+    shares: Vec<Value>,
+    next_id: u64,
+    /// Number of sessions opened, shared with the test
+    sessions: Arc<AtomicU64>,
+    closed: Vec<String>,
+    /// Answer the session id when opening a session, like a real server
+    session_header: bool,
+    /// Number of polls of the login flow answered before the user grants the access
+    pending_polls: u32,
+    /// Answer the pages of Nextcloud instead of the passwords API
+    app_installed: bool,
+    /// Answer every request like a server in maintenance mode while set
+    maintenance: Arc<AtomicBool>,
+    /// Version of the passwords app in the OCS capabilities
+    app_version: &'static str,
+    /// Serve the passwords API only without `index.php`, like a server rewriting the URLs
+    pretty_urls: bool,
+    /// Accept a session only with the `route` cookie set when opening it, like a load balancer
+    affinity: bool,
+    /// Number of requests to the password API answered with a `503` before the server recovers
+    transient_failures: u32,
+    /// Number of requests to `share/partners` refused with a `429` before answering
+    rate_limited_partners: u32,
+    /// Seconds in the `Retry-After` of the refused requests to `share/partners`
+    partners_retry_after: u64,
+    /// Answer something else than passwords to the requests of the password API
+    broken_passwords: bool,
+    /// Seconds added to the local clock in the `Date` header, which is only sent when set
+    clock_skew: Option<i64>,
+}
+
+const PASSWORD_ID: &str = "a2e0e74b-2ee1-4b4c-9f8b-a3d8b0bb2d27";
+const FOLDER_ID: &str = "5f3b4a2c-1d6e-4f7a-8b9c-0d1e2f3a4b5c";
+const TAG_ID: &str = "9c8b7a6d-5e4f-4a3b-8c2d-1e0f9a8b7c6d";
+const OWNER_ID: &str = "mock";
+const PARTNER_ID: &str = "bob";
+const REVISION: &str = "4a0a9fbf-6ca5-4d3f-a2bb-0a9f1d0e1c55";
+const ROOT: &str = "00000000-0000-0000-0000-000000000000";
+const TIME: u64 = 1_600_000_000;
+/// The id of the password that can't be read in the list of `start_with_broken_passwords`
+pub const BROKEN_ID: &str = "3b1f0c2e-8d4a-4e6b-9f7c-5a2d1e0b9c8f";
+/// The only second factor token accepted by the mock server
+pub const MOCK_TOKEN: &str = "123456";
+/// The password of the user of the mock server, converted into `mock-app-password`
+pub const MOCK_PASSWORD: &str = "mock-password";
+/// `mock:mock-password` and `mock:mock-app-password` in basic auth
+const USER_AUTH: &str = "Basic bW9jazptb2NrLXBhc3N3b3Jk";
+const APP_AUTH: &str = "Basic bW9jazptb2NrLWFwcC1wYXNzd29yZA==";
+
+impl MockServer {
+    fn new(url: Url) -> Self {
+        MockServer {
+            settings: default_settings(&url),
+            url,
+            token_providers: Vec::new(),
+            requested_tokens: Vec::new(),
+            shares: Vec::new(),
+            next_id: 0,
+            sessions: Arc::default(),
+            closed: Vec::new(),
+            session_header: true,
+            pending_polls: 0,
+            app_installed: true,
+            maintenance: Arc::new(AtomicBool::new(false)),
+            app_version: "2020.1.0",
+            pretty_urls: false,
+            affinity: false,
+            transient_failures: 0,
+            rate_limited_partners: 0,
+            partners_retry_after: 1,
+            broken_passwords: false,
+            clock_skew: None,
+        }
+    }
+
+    /// Start the server in a background thread, and return its URL
+    pub fn start() -> Url {
+        MockServer::start_with_tokens(Vec::new())
+    }
+
+    /// Like [start](MockServer::start), with second factors configured on the account. Only
+    /// [MOCK_TOKEN] is accepted to open a session, and only after the token was requested for
+    /// the providers with `"request": true`.
+    pub fn start_with_tokens(token_providers: Vec<Value>) -> Url {
+        listen(|url| MockServer {
+            token_providers,
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start](MockServer::start), with a login flow where the user grants the access after
+    /// `pending_polls` polls
+    pub fn start_with_pending_login(pending_polls: u32) -> Url {
+        listen(|url| MockServer {
+            pending_polls,
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start](MockServer::start), but the session id is missing when opening a session,
+    /// like behind a proxy dropping the headers
+    pub fn start_without_session_header() -> Url {
+        listen(|url| MockServer {
+            session_header: false,
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start](MockServer::start), on a Nextcloud without the passwords app
+    pub fn start_without_app() -> Url {
+        listen(|url| MockServer {
+            app_installed: false,
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start](MockServer::start), serving the passwords API only at
+    /// `apps/passwords/api/`, without `index.php`
+    pub fn start_with_pretty_urls() -> Url {
+        listen(|url| MockServer {
+            pretty_urls: true,
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start](MockServer::start), behind a load balancer routing the sessions with a
+    /// cookie
+    pub fn start_with_affinity() -> Url {
+        listen(|url| MockServer {
+            affinity: true,
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start](MockServer::start), with a clock `skew` seconds ahead of the local clock
+    pub fn start_with_clock_skew(skew: i64) -> Url {
+        listen(|url| MockServer {
+            clock_skew: Some(skew),
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start](MockServer::start), the first `transient_failures` requests to the password
+    /// API fail with a `503`, like an overloaded server
+    pub fn start_with_transient_failures(transient_failures: u32) -> Url {
+        listen(|url| MockServer {
+            transient_failures,
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start](MockServer::start), the first `rate_limited_partners` requests to
+    /// `share/partners` exceed the rate limit and must be retried after a second
+    pub fn start_with_rate_limit(rate_limited_partners: u32) -> Url {
+        listen(|url| MockServer {
+            rate_limited_partners,
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start_with_rate_limit](MockServer::start_with_rate_limit), the requests must be
+    /// retried after `retry_after` seconds
+    pub fn start_with_retry_after(rate_limited_partners: u32, retry_after: u64) -> Url {
+        listen(|url| MockServer {
+            rate_limited_partners,
+            partners_retry_after: retry_after,
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start](MockServer::start), the password API answers objects which are not passwords
+    pub fn start_with_broken_passwords() -> Url {
+        listen(|url| MockServer {
+            broken_passwords: true,
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start](MockServer::start), with this version of the passwords app
+    pub fn start_with_app_version(app_version: &'static str) -> Url {
+        listen(|url| MockServer {
+            app_version,
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start](MockServer::start), without the setting `name`, like an older version of the
+    /// passwords app
+    pub fn start_without_setting(name: &'static str) -> Url {
+        listen(|url| {
+            let mut server = MockServer::new(url);
+            server.settings.remove(name);
+            server
+        })
+    }
+
+    /// Like [start](MockServer::start), the server is in maintenance mode while `maintenance` is
+    /// set
+    pub fn start_with_maintenance(maintenance: Arc<AtomicBool>) -> Url {
+        listen(|url| MockServer {
+            maintenance,
+            ..MockServer::new(url)
+        })
+    }
+
+    /// Like [start](MockServer::start), counting the sessions opened in `sessions`
+    pub fn start_with_session_counter(sessions: Arc<AtomicU64>) -> Url {
+        listen(|url| MockServer {
+            sessions,
+            ..MockServer::new(url)
+        })
+    }
+}
+
+impl Handler for MockServer {
+    fn handle(&mut self, request: &Request) -> Response {
+        let (status, mut headers, body) = self.answer(request);
+        if let Some(skew) = self.clock_skew {
+            let now = std::time::SystemTime::now();
+            let date = if skew >= 0 {
+                now + std::time::Duration::from_secs(skew as u64)
+            } else {
+                now - std::time::Duration::from_secs(-skew as u64)
+            };
+            headers.push(("Date", httpdate::fmt_http_date(date)));
+        }
+        (status, headers, body)
+    }
+}
+
+impl MockServer {
+    fn answer(&mut self, request: &Request) -> Response {
+        let path = request.path.trim_end_matches('/');
+        if self.maintenance.load(Ordering::SeqCst) {
+            return (
+                503,
+                vec![("X-Nextcloud-Maintenance-Mode", "1".into())],
+                json!({"ocs": {"meta": {
+                    "status": "failure",
+                    "statuscode": 503,
+                    "message": "Service unavailable",
+                }, "data": []}}),
+            );
+        }
+        if !self.app_installed && path.contains("/apps/passwords/") {
+            return (
+                404,
+                vec![("Content-Type", "text/html; charset=UTF-8".into())],
+                "<!DOCTYPE html><html><body>Page not found</body></html>".into(),
+            );
+        }
+        if path.contains("/ocs/v2.php/core/getapppassword") {
+            let otp_accepted = request.otp.as_deref() == Some(MOCK_TOKEN);
+            return match request.authorization.as_deref() {
+                Some(APP_AUTH) => (403, vec![], json!({"ocs": {"meta": {}, "data": []}})),
+                Some(USER_AUTH) if self.token_providers.is_empty() || otp_accepted => (
+                    200,
+                    vec![],
+                    json!({"ocs": {
+                        "meta": {"status": "ok", "statuscode": 200, "message": "OK"},
+                        "data": {"apppassword": "mock-app-password"},
+                    }}),
+                ),
+                _ => (401, vec![], json!({"ocs": {"meta": {}, "data": []}})),
+            };
+        }
+        if self.pretty_urls && path.contains("/index.php/apps/passwords/") {
+            return not_found();
+        }
+        if path.contains("/ocs/v2.php/cloud/capabilities") {
+            return (
+                200,
+                vec![],
+                json!({"ocs": {
+                    "meta": {"status": "ok", "statuscode": 200, "message": "OK"},
+                    "data": {
+                        "version": {"major": 20, "minor": 0, "micro": 0, "string": "20.0.0"},
+                        "capabilities": {"passwords": {"version": self.app_version}},
+                    },
+                }}),
+            );
+        }
+        if path.contains("/ocs/v2.php/core/apppassword") {
+            return match request.authorization.as_deref() {
+                Some(APP_AUTH) => (200, vec![], json!({"ocs": {"meta": {}, "data": []}})),
+                _ => (401, vec![], json!({"ocs": {"meta": {}, "data": []}})),
+            };
+        }
+        if path.ends_with("/login/v2/poll") {
+            if self.pending_polls > 0 {
+                self.pending_polls -= 1;
+                return not_found();
+            }
+            return (
+                200,
+                vec![],
+                json!({
+                    "server": self.url.as_str(),
+                    "loginName": "mock",
+                    "appPassword": "mock-app-password",
+                }),
+            );
+        }
+        if path.ends_with("/login/v2") {
+            return (
+                200,
+                vec![],
+                json!({
+                    "poll": {
+                        "token": "mock-token",
+                        "endpoint": format!("{}index.php/login/v2/poll", self.url),
+                    },
+                    // The grant page shows the name of the client
+                    "login": match &request.user_agent {
+                        Some(client) => format!(
+                            "{}index.php/login/v2/flow/mock-token?client={}",
+                            self.url, client
+                        ),
+                        None => format!("{}index.php/login/v2/flow/mock-token", self.url),
+                    },
+                }),
+            );
+        }
+        let endpoint = match path.find("1.0/") {
+            Some(start) => &path[start..],
+            None => return not_found(),
+        };
+        // Another node does not know the session
+        let wrong_node = |session: &String| {
+            let route = format!("route={}", session.replace("mock-session", "node"));
+            let cookies = request.cookie.as_deref().unwrap_or_default();
+            !cookies.split("; ").any(|cookie| cookie == route)
+        };
+        if request
+            .session
+            .as_ref()
+            .is_some_and(|s| self.closed.contains(s) || (self.affinity && wrong_node(s)))
+        {
+            return (
+                401,
+                vec![],
+                json!({"status": "error", "id": 401, "message": "Authorized session required"}),
+            );
+        }
+        if self.transient_failures > 0 && endpoint.starts_with("1.0/password/") {
+            self.transient_failures -= 1;
+            return (
+                503,
+                vec![],
+                json!({"status": "error", "id": 503, "message": "Service unavailable"}),
+            );
+        }
+        if self.broken_passwords && endpoint == "1.0/password/list" {
+            return (
+                200,
+                vec![],
+                json!([
+                    password(),
+                    {"unexpected": true, "password": "hunter2"},
+                    {"id": BROKEN_ID, "label": 3},
+                ]),
+            );
+        }
+        if self.broken_passwords && endpoint.starts_with("1.0/password/") {
+            return (
+                200,
+                vec![],
+                json!([{"unexpected": true, "password": "hunter2"}]),
+            );
+        }
+        if self.rate_limited_partners > 0 && endpoint == "1.0/share/partners" {
+            self.rate_limited_partners -= 1;
+            return (
+                429,
+                vec![("Retry-After", self.partners_retry_after.to_string())],
+                json!({"status": "error", "id": 429, "message": "Too many requests"}),
+            );
+        }
+        let body = match endpoint {
+            "1.0/session/open" => {
+                let token_accepted = self.token_providers.iter().any(|provider| {
+                    let id = provider["id"].as_str().unwrap();
+                    let sent = provider["request"] != true
+                        || self
+                            .requested_tokens
+                            .iter()
+                            .any(|requested| requested == id);
+                    sent && request.body["token"][id] == MOCK_TOKEN
+                });
+                if !self.token_providers.is_empty() && !token_accepted {
+                    return (
+                        401,
+                        Vec::new(),
+                        json!({"status": "error", "id": 401, "message": "Token verification failed"}),
+                    );
+                }
+                let session = self.sessions.fetch_add(1, Ordering::SeqCst) + 1;
+                let mut headers = if self.session_header {
+                    vec![("X-API-SESSION", format!("mock-session-{}", session))]
+                } else {
+                    Vec::new()
+                };
+                if self.affinity {
+                    let route = format!("route=node-{}; path=/; HttpOnly", session);
+                    headers.push(("Set-Cookie", route));
+                    let auth = format!("nc_session_id=secret-{}; path=/; HttpOnly", session);
+                    headers.push(("Set-Cookie", auth));
+                    headers.push(("Set-Cookie", "oc_sessionPassphrase=passphrase".into()));
+                }
+                return (200, headers, json!({"success": true, "keys": []}));
+            }
+            // No master password nor second factor
+            "1.0/session/request" if self.token_providers.is_empty() => json!([]),
+            "1.0/session/request" => json!({ "token": self.token_providers }),
+            "1.0/session/keepalive" => json!({"success": true}),
+            "1.0/session/close" => {
+                self.closed.extend(request.session.clone());
+                json!({"success": true})
+            }
+            "1.0/settings/get" => {
+                let names: Vec<String> =
+                    serde_json::from_value(request.body.clone()).unwrap_or_default();
+                let settings: Map<String, Value> = names
+                    .into_iter()
+                    .map(|name| {
+                        let value = self.settings.get(&name).cloned().unwrap_or(Value::Null);
+                        (name, value)
+                    })
+                    .collect();
+                Value::Object(settings)
+            }
+            "1.0/settings/set" => {
+                let changed = request.body.as_object().cloned().unwrap_or_default();
+                for (name, value) in &changed {
+                    self.settings.insert(name.clone(), value.clone());
+                }
+                Value::Object(changed)
+            }
+            "1.0/settings/reset" => {
+                let names: Vec<String> =
+                    serde_json::from_value(request.body.clone()).unwrap_or_default();
+                let defaults = default_settings(&self.url);
+                let settings: Map<String, Value> = names
+                    .into_iter()
+                    .map(|name| {
+                        // Client settings are deleted
+                        let value = match defaults.get(&name) {
+                            Some(value) if !name.starts_with("client.") => value.clone(),
+                            _ => Value::Null,
+                        };
+                        match &value {
+                            Value::Null => self.settings.remove(&name),
+                            value => self.settings.insert(name.clone(), value.clone()),
+                        };
+                        (name, value)
+                    })
+                    .collect();
+                Value::Object(settings)
+            }
+            "1.0/settings/list" => {
+                let scopes: Vec<String> =
+                    serde_json::from_value(request.body["scopes"].clone()).unwrap_or_default();
+                let settings: Map<String, Value> = self
+                    .settings
+                    .iter()
+                    .filter(|(name, _)| {
+                        scopes.is_empty()
+                            || scopes
+                                .iter()
+                                .any(|scope| name.starts_with(&format!("{}.", scope)))
+                    })
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect();
+                Value::Object(settings)
+            }
+            "1.0/password/list" | "1.0/password/find" => json!([password()]),
+            "1.0/password/show" => password(),
+            "1.0/folder/list" => json!([folder()]),
+            "1.0/folder/find" => json!([folder()]),
+            "1.0/tag/list" => json!([tag()]),
+            "1.0/share/partners" => json!({ PARTNER_ID: "Bob" }),
+            "1.0/share/list" => Value::Array(self.shares.clone()),
+            "1.0/share/show" => {
+                let id = &request.body["id"];
+                match self.shares.iter().find(|share| &share["id"] == id) {
+                    Some(share) => share.clone(),
+                    None => return not_found(),
+                }
+            }
+            "1.0/share/create" => {
+                self.next_id += 1;
+                let id = format!("00000000-0000-4000-8000-{:012}", self.next_id);
+                self.shares.push(json!({
+                    "id": id,
+                    "created": TIME,
+                    "updated": TIME,
+                    "expires": request.body["expires"],
+                    "editable": request.body["editable"].as_bool().unwrap_or(false),
+                    "shareable": request.body["shareable"].as_bool().unwrap_or(true),
+                    "updatePending": false,
+                    "password": request.body["password"],
+                    "owner": { "id": OWNER_ID, "name": "Mock" },
+                    "receiver": { "id": request.body["receiver"], "name": "Bob" },
+                }));
+                json!({ "id": id })
+            }
+            "1.0/share/update" => {
+                let id = &request.body["id"];
+                match self.shares.iter_mut().find(|share| &share["id"] == id) {
+                    Some(share) => {
+                        for field in &["expires", "editable", "shareable"] {
+                            if let Some(value) = request.body.get(*field) {
+                                share[*field] = value.clone();
+                            }
+                        }
+                        share["updatePending"] = json!(true);
+                        json!({ "id": id })
+                    }
+                    None => return not_found(),
+                }
+            }
+            "1.0/share/delete" => {
+                let id = request.body["id"].clone();
+                let count = self.shares.len();
+                self.shares.retain(|share| share["id"] != id);
+                if self.shares.len() == count {
+                    return not_found();
+                }
+                json!({ "id": id })
+            }
+            // Before opening a session, the token can be requested with the credentials
+            endpoint
+                if endpoint.starts_with("1.0/token/")
+                    && request.session.is_none()
+                    && request.authorization.as_deref() != Some(APP_AUTH) =>
+            {
+                return (
+                    401,
+                    vec![],
+                    json!({"status": "error", "id": 401, "message": "Unauthorized"}),
+                );
+            }
+            endpoint if endpoint.starts_with("1.0/token/") && endpoint.ends_with("/request") => {
+                let provider = &endpoint["1.0/token/".len()..endpoint.len() - "/request".len()];
+                self.requested_tokens.push(provider.into());
+                match provider {
+                    "twofactor_nextcloud_notification" => {
+                        json!({ "success": true, "data": { "id": 42 } })
+                    }
+                    _ => json!({ "success": true, "data": {} }),
+                }
+            }
+            endpoint if endpoint.starts_with("1.0/service/preview/") => {
+                // Generating a preview is slow on a real server
+                std::thread::sleep(std::time::Duration::from_millis(300));
+                json!("preview")
+            }
+            _ => return not_found(),
+        };
+        (200, vec![], body)
+    }
+}
+
+fn password() -> Value {
+    json!({
+        "id": PASSWORD_ID,
+        "label": "Example",
+        "username": "alice",
+        "password": "hunter2",
+        "url": "https://example.com",
+        "notes": "",
+        "customFields": "[]",
+        "status": 0,
+        "statusCode": "GOOD",
+        "hash": "f3bbbd66a63d4bf1747940578ec3d0103530e21d",
+        "folder": FOLDER_ID,
+        "revision": REVISION,
+        "share": null,
+        "shared": false,
+        "cseType": "none",
+        "cseKey": "",
+        "sseType": "SSEv1r2",
+        "client": "CLIENT::UNKNOWN",
+        "hidden": false,
+        "trashed": false,
+        "favorite": true,
+        "editable": true,
+        "edited": TIME,
+        "created": TIME,
+        "updated": TIME,
+    })
+}
+
+fn folder() -> Value {
+    json!({
+        "id": FOLDER_ID,
+        "label": "Websites",
+        "parent": ROOT,
+        "created": TIME,
+        "updated": TIME,
+        "edited": TIME,
+        "revision": REVISION,
+        "cseType": "none",
+        "cseKey": "",
+        "sseType": "SSEv1r2",
+        "client": "CLIENT::UNKNOWN",
+        "hidden": false,
+        "trashed": false,
+        "favorite": false,
+    })
+}
+
+fn tag() -> Value {
+    json!({
+        "id": TAG_ID,
+        "label": "Work",
+        "color": "#745bca",
+        "created": TIME,
+        "updated": TIME,
+        "edited": TIME,
+        "revision": REVISION,
+        "cseType": "none",
+        "cseKey": "",
+        "sseType": "SSEv1r2",
+        "client": "CLIENT::UNKNOWN",
+        "hidden": false,
+        "trashed": false,
+        "favorite": false,
+    })
+}
@@ -0,0 +1,77 @@
+//! Log in to a server and keep the session in a state file.
+//!
+//! The first run logs in with `NC_LOGIN` and `NC_APP_PASSWORD` if they are set, else with the
+//! login flow v2: open the printed URL in a browser and grant the access. The session is then
+//! saved to `NC_STATE_FILE` (by default `nextcloud-passwords-state.json` in the temporary
+//! directory) and the next runs resume it.
+//!
+//! ```text
+//! NC_SERVER=https://cloud.example.com cargo run --example login_flow
+//! cargo run --example login_flow -- --mock
+//! ```
+
+mod common;
+
+use nextcloud_passwords_client::{AuthenticatedApi, LoginDetails, ResumeState};
+use std::path::PathBuf;
+
+fn state_file() -> PathBuf {
+    match std::env::var_os("NC_STATE_FILE") {
+        Some(path) => path.into(),
+        None if common::mock_requested() => {
+            std::env::temp_dir().join("nextcloud-passwords-mock-state.json")
+        }
+        None => std::env::temp_dir().join("nextcloud-passwords-state.json"),
+    }
+}
+
+async fn login() -> Result<LoginDetails, nextcloud_passwords_client::Error> {
+    let server = common::server();
+    if let Some((login_name, app_password)) = common::credentials() {
+        return Ok(LoginDetails {
+            server,
+            login_name,
+            app_password,
+            api_base_override: None,
+            client_name: None,
+            master_password: std::env::var("NC_MASTER_PASSWORD").ok(),
+        });
+    }
+    LoginDetails::register_login_flow_2(server, |url| {
+        println!("Grant the access at {}", url);
+    })
+    .await
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let state_file = state_file();
+    // The mock server does not outlive a run, so its sessions can't be resumed
+    let saved = match common::mock_requested() {
+        true => None,
+        false => std::fs::read(&state_file).ok(),
+    };
+    let (api, session_id) = match saved {
+        Some(state) => {
+            println!("Resuming the session from {}", state_file.display());
+            let state: ResumeState = serde_json::from_slice(&state)?;
+            AuthenticatedApi::resume_session(state).await?
+        }
+        None => {
+            let login = login().await?;
+            println!("Logged in as {}", login.login_name);
+            AuthenticatedApi::new_session(login).await?
+        }
+    };
+    println!("Session {} on {}", session_id, api.server());
+    println!("Server clock offset: {}s", api.server_time_offset());
+
+    // The state contains the app password, keep it private
+    std::fs::write(&state_file, serde_json::to_vec(&api.get_state())?)?;
+    println!("Session saved to {}", state_file.display());
+
+    let state: ResumeState = serde_json::from_slice(&std::fs::read(&state_file)?)?;
+    let (_, resumed) = AuthenticatedApi::resume_session(state).await?;
+    println!("Resumed session {} from the saved state", resumed);
+    Ok(())
+}
@@ -0,0 +1,43 @@
+//! Print and change the settings.
+//!
+//! Without argument all the settings are printed, with the name of a setting only this one is
+//! printed, and with a name and a value the user setting is changed.
+//!
+//! ```text
+//! NC_SERVER=https://cloud.example.com NC_LOGIN=alice NC_APP_PASSWORD=... cargo run --example settings_editor -- user.session.lifetime 1200
+//! cargo run --example settings_editor -- --mock user.password.generator.strength 4
+//! ```
+
+mod common;
+
+use nextcloud_passwords_client::{
+    settings::{SettingVariant, Settings, UserSettingValue, UserSettings},
+    AuthenticatedApi,
+};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (api, _) = AuthenticatedApi::new_session(common::login_details()).await?;
+
+    let args = common::args();
+    match args.as_slice() {
+        [] => println!("{:#?}", api.settings().get_all().await?),
+        [name] => {
+            let variant: SettingVariant = name.parse()?;
+            println!("{:?}", api.settings().get().from_variant(variant).await?);
+        }
+        [name, value] => {
+            let setting: UserSettings = name.parse()?;
+            let value = UserSettingValue::from_variant(setting, value)?;
+            let set = api
+                .settings()
+                .set(Settings::new().set_user_value(value))
+                .await?;
+            println!("{:?}", set);
+        }
+        _ => Err("expected at most a setting name and a value")?,
+    }
+
+    api.disconnect().await?;
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Share a password with a user, make the share editable, and delete it.
+//!
+//! The password is the first one of the vault whose label contains the first argument (any
+//! password without argument), and the receiver is the first sharing partner.
+//!
+//! ```text
+//! NC_SERVER=https://cloud.example.com NC_LOGIN=alice NC_APP_PASSWORD=... cargo run --example share_lifecycle -- Example
+//! cargo run --example share_lifecycle -- --mock
+//! ```
+
+mod common;
+
+use nextcloud_passwords_client::{
+    share::{CreateShare, SharePatch},
+    AuthenticatedApi, Filter,
+};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (api, _) = AuthenticatedApi::new_session(common::login_details()).await?;
+
+    let filter = match common::args().first() {
+        Some(label) => Filter::label_contains(label),
+        None => Filter::any(),
+    };
+    let password = filter
+        .apply(api.password().list(None).await?)
+        .into_iter()
+        .next()
+        .ok_or("no password to share")?;
+    let partner = api
+        .share()
+        .partners(None, None)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or("no user to share with")?;
+    println!(
+        "Sharing {:?} with {}",
+        password.versioned.label, partner.display_name
+    );
+
+    let id = api
+        .share()
+        .create(CreateShare::new(password.id, partner.user_id).editable(false))
+        .await?;
+    println!("Created share {}", id);
+
+    let share = api.share().get(None, id).await?;
+    let patch = SharePatch::new(&share).editable(true);
+    api.share().apply_patch(patch).await?;
+    println!("Made share {} editable", id);
+
+    for share in api.share().list(None).await? {
+        println!(
+            "Share {}: {} -> {}, editable: {}",
+            share.id, share.owner.name, share.receiver.name, share.editable
+        );
+    }
+
+    api.share().delete(id).await?;
+    println!("Deleted share {}", id);
+
+    api.disconnect().await?;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+//! Print the passwords, folders and tags of the vault as JSON.
+//!
+//! The output contains the passwords in clear text. On accounts using client side encryption,
+//! set `NC_MASTER_PASSWORD` to decrypt them.
+//!
+//! ```text
+//! NC_SERVER=https://cloud.example.com NC_LOGIN=alice NC_APP_PASSWORD=... cargo run --example vault_dump
+//! cargo run --example vault_dump -- --mock
+//! ```
+
+mod common;
+
+use nextcloud_passwords_client::{folder, password, tag, AuthenticatedApi};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (api, _) = AuthenticatedApi::new_session(common::login_details()).await?;
+
+    let passwords = api
+        .password()
+        .list_decrypted(Some(password::Details::new().folder()))
+        .await?;
+    let folders = api.folder().list(Some(folder::Details::new())).await?;
+    let tags = api.tag().list(Some(tag::Details::new())).await?;
+
+    let dump = serde_json::json!({
+        "passwords": passwords,
+        "folders": folders,
+        "tags": tags,
+    });
+    println!("{}", serde_json::to_string_pretty(&dump)?);
+
+    api.disconnect().await?;
+    Ok(())
+}
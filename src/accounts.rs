@@ -0,0 +1,98 @@
+use crate::{password::Password, AuthenticatedApi, Error, Filter};
+
+/// Identifies an account in an [AccountManager], chosen by the application
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AccountId(pub String);
+
+impl std::fmt::Display for AccountId {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{}", self.0)
+    }
+}
+
+impl From<&str> for AccountId {
+    fn from(id: &str) -> Self {
+        AccountId(id.into())
+    }
+}
+
+/// Holds the sessions of several accounts, possibly on different servers
+#[derive(Default)]
+pub struct AccountManager {
+    accounts: Vec<(AccountId, AuthenticatedApi)>,
+}
+
+/// Result of [search_all](AccountManager::search_all)
+#[derive(Debug, Default)]
+pub struct MultiSearch {
+    /// The matching passwords, grouped by account in the order the accounts were added. In an
+    /// account the order is the same as [Filter::apply].
+    pub matches: Vec<(AccountId, Password)>,
+    /// The accounts that could not be searched
+    pub errors: Vec<(AccountId, Error)>,
+}
+
+impl AccountManager {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add an account, replacing the session if an account with the same id exists. The
+    /// previous session is returned.
+    pub fn add(&mut self, id: AccountId, api: AuthenticatedApi) -> Option<AuthenticatedApi> {
+        match self.accounts.iter_mut().find(|(account, _)| *account == id) {
+            Some((_, session)) => Some(std::mem::replace(session, api)),
+            None => {
+                self.accounts.push((id, api));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: &AccountId) -> Option<AuthenticatedApi> {
+        let index = self
+            .accounts
+            .iter()
+            .position(|(account, _)| account == id)?;
+        Some(self.accounts.remove(index).1)
+    }
+
+    pub fn get(&self, id: &AccountId) -> Option<&AuthenticatedApi> {
+        self.accounts
+            .iter()
+            .find(|(account, _)| account == id)
+            .map(|(_, api)| api)
+    }
+
+    /// The accounts, in the order they were added
+    pub fn accounts(&self) -> impl Iterator<Item = &AccountId> {
+        self.accounts.iter().map(|(id, _)| id)
+    }
+
+    /// List the passwords of all the accounts concurrently and keep the ones matching the
+    /// filter. An account that fails (for example because it is offline) is reported in
+    /// [errors](MultiSearch::errors) and does not fail the search.
+    pub async fn search_all(&self, filter: &Filter) -> MultiSearch {
+        let searches = self.accounts.iter().map(|(id, api)| async move {
+            let result = match api.password().list(None).await {
+                Ok(passwords) => Ok(filter.apply_async(passwords).await),
+                Err(e) => Err(e),
+            };
+            (id, result)
+        });
+
+        let mut search = MultiSearch::default();
+        for (id, result) in futures::future::join_all(searches).await {
+            match result {
+                Ok(passwords) => search
+                    .matches
+                    .extend(passwords.into_iter().map(|password| (id.clone(), password))),
+                Err(e) => {
+                    log::warn!("Could not search account {}: {}", id, e);
+                    search.errors.push((id.clone(), e))
+                }
+            }
+        }
+        search
+    }
+}
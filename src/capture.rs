@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Replaces the redacted values
+pub const REDACTED: &str = "<redacted>";
+
+/// Keys whose values are always redacted
+const SECRET_KEYS: &[&str] = &["password", "cseKey", "challenge", "hash"];
+
+/// A request and its response, see [capture_log](crate::AuthenticatedApi::capture_log). The
+/// secrets are redacted
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Capture {
+    /// Identifies the capture, it is also given by
+    /// [UnexpectedResponse](crate::Error::UnexpectedResponse)
+    pub id: u64,
+    pub endpoint: String,
+    pub method: String,
+    pub request_headers: Vec<(String, String)>,
+    pub request: Value,
+    pub status: u16,
+    /// The body of the response, JSON bodies are redacted, binary bodies are replaced by their
+    /// size
+    pub response: String,
+}
+
+#[derive(Debug)]
+pub(crate) struct CaptureLog {
+    capacity: usize,
+    next_id: u64,
+    entries: VecDeque<Capture>,
+}
+
+impl CaptureLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        CaptureLog {
+            capacity,
+            next_id: 0,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn entries(&self) -> Vec<Capture> {
+        self.entries.iter().cloned().collect()
+    }
+
+    /// Record an exchange, returns the id of the capture
+    pub(crate) fn push(
+        &mut self,
+        endpoint: &str,
+        method: &reqwest::Method,
+        mut request: Value,
+        status: u16,
+        response: String,
+    ) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        redact(&mut request);
+        let capture = Capture {
+            id,
+            endpoint: endpoint.into(),
+            method: method.to_string(),
+            request_headers: vec![
+                ("Authorization".into(), REDACTED.into()),
+                ("X-API-SESSION".into(), REDACTED.into()),
+            ],
+            request,
+            status,
+            response: redact_text(response),
+        };
+        if self.capacity == 0 {
+            return id;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(capture);
+        id
+    }
+}
+
+pub(crate) fn redact_text(text: String) -> String {
+    match serde_json::from_str::<Value>(&text) {
+        Ok(mut value) => {
+            redact(&mut value);
+            value.to_string()
+        }
+        Err(_) => text,
+    }
+}
+
+/// Mask the secrets in a JSON value: passwords and their hashes, keys and the values of secret
+/// custom fields
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if SECRET_KEYS.contains(&key.as_str()) {
+                    if !value.is_null() {
+                        *value = REDACTED.into();
+                    }
+                } else if key == "customFields" {
+                    redact_custom_fields(value)
+                } else {
+                    redact(value)
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn redact_custom_fields(value: &mut Value) {
+    let fields = match value.as_str() {
+        None | Some("") => return,
+        Some(fields) => serde_json::from_str::<Vec<Value>>(fields),
+    };
+    *value = match fields {
+        Ok(mut fields) => {
+            for field in &mut fields {
+                if field["type"] == "secret" {
+                    field["value"] = REDACTED.into();
+                }
+            }
+            Value::Array(fields).to_string().into()
+        }
+        // Probably encrypted
+        Err(_) => REDACTED.into(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn redaction() {
+        let mut value = json!([{
+            "label": "Example",
+            "password": "hunter2",
+            "hash": "f3bbbd66a63d4bf1747940578ec3d0103530e21d",
+            "cseKey": "8d2e2a6c-0b0e-4d2f-8c7a-3c3a0c8f4b1e",
+            "customFields": r#"[{"label":"pin","type":"secret","value":"1234"},{"label":"site","type":"url","value":"https://example.com"}]"#,
+        }]);
+        redact(&mut value);
+        assert_eq!(value[0]["label"], "Example");
+        assert_eq!(value[0]["password"], REDACTED);
+        assert_eq!(value[0]["hash"], REDACTED);
+        assert_eq!(value[0]["cseKey"], REDACTED);
+        let custom_fields: Value =
+            serde_json::from_str(value[0]["customFields"].as_str().unwrap()).unwrap();
+        assert_eq!(custom_fields[0]["value"], REDACTED);
+        assert_eq!(custom_fields[1]["value"], "https://example.com");
+    }
+
+    #[test]
+    fn ring_buffer() {
+        let mut log = CaptureLog::new(2);
+        for _ in 0..3 {
+            log.push(
+                "1.0/password/list",
+                &reqwest::Method::POST,
+                json!({}),
+                200,
+                "[]".into(),
+            );
+        }
+        let ids: Vec<_> = log.entries().iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+}
@@ -0,0 +1,100 @@
+//! Solve the challenge of the server when opening a session on an account protected by a master
+//! password
+
+use crate::Error;
+use blake2::digest::{consts::U64, Mac};
+use serde::Deserialize;
+
+/// The challenge sent by [session/request](crate::AuthenticatedApi::new_session)
+#[derive(Deserialize, Debug)]
+pub(crate) struct Challenge {
+    #[serde(rename = "type")]
+    pub(crate) ty: String,
+    pub(crate) salts: Vec<String>,
+}
+
+impl Challenge {
+    /// Derive the secret expected by the server from the master password
+    pub(crate) fn solve(&self, master_password: &str) -> Result<String, Error> {
+        match self.ty.as_str() {
+            "PWDv1r1" => pwd_v1r1(master_password, &self.salts),
+            ty => Err(Error::UnsupportedChallenge(ty.into())),
+        }
+    }
+}
+
+/// Hash the password and the first salt with blake2b keyed by the second salt, then derive 32
+/// bytes with argon2id (libsodium interactive limits) salted with the third salt
+fn pwd_v1r1(master_password: &str, salts: &[String]) -> Result<String, Error> {
+    let salts = match salts {
+        [password_salt, hash_key, hash_salt] => (
+            hex::decode(password_salt),
+            hex::decode(hash_key),
+            hex::decode(hash_salt),
+        ),
+        _ => return Err(Error::ChallengeFailed),
+    };
+    let (password_salt, hash_key, hash_salt) = match salts {
+        (Ok(password_salt), Ok(hash_key), Ok(hash_salt)) => (password_salt, hash_key, hash_salt),
+        _ => return Err(Error::ChallengeFailed),
+    };
+
+    let mut generic_hash =
+        blake2::Blake2bMac::<U64>::new_from_slice(&hash_key).map_err(|_| Error::ChallengeFailed)?;
+    generic_hash.update(master_password.as_bytes());
+    generic_hash.update(&password_salt);
+    let generic_hash = generic_hash.finalize().into_bytes();
+
+    let secret =
+        pwhash_interactive(&generic_hash, &hash_salt).map_err(|_| Error::ChallengeFailed)?;
+    Ok(hex::encode(secret))
+}
+
+/// `crypto_pwhash` of libsodium with the interactive limits: 32 bytes derived with argon2id,
+/// 2 passes over 64 MiB
+pub(crate) fn pwhash_interactive(password: &[u8], salt: &[u8]) -> Result<[u8; 32], argon2::Error> {
+    let params = argon2::Params::new(64 * 1024, 2, 1, Some(32))?;
+    let argon2 = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    let mut hash = [0; 32];
+    argon2.hash_password_into(password, salt, &mut hash)?;
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge() -> Challenge {
+        Challenge {
+            ty: "PWDv1r1".into(),
+            salts: vec![
+                "00112233445566778899aabbccddeeff".repeat(16),
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f".into(),
+                "0f0e0d0c0b0a09080706050403020100".into(),
+            ],
+        }
+    }
+
+    #[test]
+    fn pwd_v1r1() {
+        let secret = challenge().solve("correct horse battery").unwrap();
+        assert_eq!(secret.len(), 64);
+        assert_eq!(secret, challenge().solve("correct horse battery").unwrap());
+        assert_ne!(secret, challenge().solve("correct horse battery!").unwrap());
+
+        let mut malformed = challenge();
+        malformed.salts.pop();
+        assert!(matches!(
+            malformed.solve("correct horse battery"),
+            Err(Error::ChallengeFailed)
+        ));
+        let unknown = Challenge {
+            ty: "PWDv2".into(),
+            salts: Vec::new(),
+        };
+        assert!(matches!(
+            unknown.solve("correct horse battery"),
+            Err(Error::UnsupportedChallenge(_))
+        ));
+    }
+}
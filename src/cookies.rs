@@ -0,0 +1,158 @@
+use reqwest::header::{HeaderMap, COOKIE, SET_COOKIE};
+use reqwest::RequestBuilder;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// The cookies set by the server, sent back with every request of the session. Behind a load
+/// balancer they route the requests to the node which issued the session (`PHPSESSID`, `route`
+/// and the like).
+///
+/// The cookies are not scoped by path nor domain, all the requests of a session go to the same
+/// server.
+#[derive(Debug, Default)]
+pub(crate) struct CookieJar(RwLock<BTreeMap<String, String>>);
+
+impl CookieJar {
+    pub(crate) fn new(cookies: BTreeMap<String, String>) -> Self {
+        CookieJar(RwLock::new(cookies))
+    }
+
+    /// Keep the cookies set by a response
+    pub(crate) fn store(&self, headers: &HeaderMap) {
+        let mut cookies = self.0.write().unwrap();
+        for set_cookie in headers.get_all(SET_COOKIE) {
+            match set_cookie.to_str().ok().and_then(parse) {
+                Some((name, Some(value))) => {
+                    cookies.insert(name.into(), value.into());
+                }
+                Some((name, None)) => {
+                    cookies.remove(name);
+                }
+                None => log::debug!("Ignoring invalid cookie {:?}", set_cookie),
+            }
+        }
+    }
+
+    /// Send the cookies with `request`
+    pub(crate) fn add_to(&self, request: RequestBuilder) -> RequestBuilder {
+        let cookies = self.0.read().unwrap();
+        if cookies.is_empty() {
+            return request;
+        }
+        let header = cookies
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        request.header(COOKIE, header)
+    }
+
+    /// The cookies to keep in a [ResumeState](crate::ResumeState), without the ones which
+    /// authenticate the session
+    pub(crate) fn to_persisted(&self) -> BTreeMap<String, String> {
+        persisted(self.0.read().unwrap().clone())
+    }
+}
+
+/// Drop the authentication cookies of nextcloud: they would give access to the session to
+/// anyone reading the state. The routing cookies of load balancers are kept.
+pub(crate) fn persisted(mut cookies: BTreeMap<String, String>) -> BTreeMap<String, String> {
+    cookies.retain(|name, _| !is_auth(name));
+    cookies
+}
+
+/// The remember-me cookies, the session passphrase, and the PHP session named after the
+/// instance id (`oc` followed by 10 alphanumeric characters)
+fn is_auth(name: &str) -> bool {
+    let instance_session = name.len() == 12
+        && name.starts_with("oc")
+        && name[2..].bytes().all(|b| b.is_ascii_alphanumeric());
+    name.starts_with("nc_") || name == "oc_sessionPassphrase" || instance_session
+}
+
+/// The name and the value of a `Set-Cookie` header, without value if the cookie is deleted
+fn parse(set_cookie: &str) -> Option<(&str, Option<&str>)> {
+    let mut attributes = set_cookie.split(';');
+    let (name, value) = attributes.next()?.split_once('=')?;
+    let (name, value) = (name.trim(), value.trim());
+    if name.is_empty() {
+        return None;
+    }
+    let expired = attributes.any(|attribute| match attribute.trim().split_once('=') {
+        Some((name, max_age)) if name.eq_ignore_ascii_case("max-age") => {
+            max_age == "0" || max_age.starts_with('-')
+        }
+        Some((name, expires)) if name.eq_ignore_ascii_case("expires") => expired(expires),
+        _ => false,
+    });
+    if expired || value.is_empty() {
+        Some((name, None))
+    } else {
+        Some((name, Some(value)))
+    }
+}
+
+/// Whether the date of an `Expires` attribute is in the past. Servers often use dashes in the
+/// date (`Thu, 01-Jan-1970 00:00:01 GMT`), which is not one of the HTTP date formats.
+fn expired(expires: &str) -> bool {
+    match httpdate::parse_http_date(&expires.replace('-', " ")) {
+        Ok(expires) => expires <= std::time::SystemTime::now(),
+        Err(_) => {
+            log::debug!("Ignoring invalid cookie expiry {:?}", expires);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_cookies() {
+        assert_eq!(
+            parse("route=node-2; path=/; HttpOnly"),
+            Some(("route", Some("node-2")))
+        );
+        assert_eq!(
+            parse("PHPSESSID=deleted; expires=Thu, 01-Jan-1970 00:00:01 GMT; Max-Age=0"),
+            Some(("PHPSESSID", None))
+        );
+        assert_eq!(
+            parse("nc_token=abc; expires=Thu, 01-Jan-1970 00:00:01 GMT; path=/"),
+            Some(("nc_token", None))
+        );
+        assert_eq!(
+            parse("nc_token=abc; Expires=Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(("nc_token", None))
+        );
+        assert_eq!(
+            parse("route=node-2; Expires=Fri, 01 Jan 2100 00:00:00 GMT"),
+            Some(("route", Some("node-2")))
+        );
+        assert_eq!(
+            parse("route=node-2; Max-Age=3600"),
+            Some(("route", Some("node-2")))
+        );
+        assert_eq!(parse("no value"), None);
+
+        let jar = CookieJar::default();
+        let mut headers = HeaderMap::new();
+        headers.append(SET_COOKIE, "route=node-2; path=/".parse().unwrap());
+        headers.append(SET_COOKIE, "oc_sessionPassphrase=abc".parse().unwrap());
+        headers.append(SET_COOKIE, "nc_session_id=abc".parse().unwrap());
+        headers.append(SET_COOKIE, "oc4x2ecd8s1k=abc".parse().unwrap());
+        jar.store(&headers);
+        assert_eq!(jar.0.read().unwrap().len(), 4);
+        let mut headers = HeaderMap::new();
+        headers.append(
+            SET_COOKIE,
+            "oc_sessionPassphrase=; Max-Age=0".parse().unwrap(),
+        );
+        jar.store(&headers);
+        assert_eq!(
+            jar.to_persisted().into_iter().collect::<Vec<_>>(),
+            vec![("route".to_string(), "node-2".to_string())]
+        );
+    }
+}
@@ -0,0 +1,505 @@
+use crate::{challenge::pwhash_interactive, password::HASH_LENGTH};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use xsalsa20poly1305::{
+    aead::{Aead, KeyInit, OsRng},
+    XSalsa20Poly1305,
+};
+use zeroize::Zeroizing;
+
+/// Client side encryption of the objects, the only one supported
+pub const CSE_V1R1: &str = "CSEv1r1";
+/// `cseType` of objects that are not encrypted on the client
+pub const CSE_NONE: &str = "none";
+
+const SALT_BYTES: usize = 16;
+const NONCE_BYTES: usize = 24;
+const KEY_BYTES: usize = 32;
+
+/// Why an object could not be decrypted
+#[derive(thiserror::Error, Debug)]
+pub enum DecryptError {
+    #[error("the object is encrypted with {0}, which is not supported")]
+    UnsupportedType(String),
+    #[error("the object is encrypted but the session has no keychain")]
+    NoKeychain,
+    #[error("the key {0} is not in the keychain")]
+    UnknownKey(String),
+    #[error("the encrypted data is not valid hex or is too short")]
+    InvalidData,
+    #[error("the data could not be decrypted with the key")]
+    WrongKey,
+    #[error("the decrypted data is not valid")]
+    InvalidPlaintext,
+    /// The current key is missing, or a key does not have the length of the cipher
+    #[error("the keychain is not valid: {0}")]
+    InvalidKeychain(String),
+}
+
+/// The keys used for client side encryption (CSEv1r1). It is sent encrypted with the master
+/// password when opening a session, see [keychain](crate::AuthenticatedApi::keychain)
+pub struct Keychain {
+    keys: HashMap<String, Zeroizing<Vec<u8>>>,
+    current: String,
+}
+
+impl std::fmt::Debug for Keychain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keychain")
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+/// `nonce || crypto_secretbox(data)`
+fn open_secretbox(encrypted: &[u8], key: &[u8]) -> Result<Vec<u8>, DecryptError> {
+    if encrypted.len() < NONCE_BYTES {
+        return Err(DecryptError::InvalidData);
+    }
+    let (nonce, ciphertext) = encrypted.split_at(NONCE_BYTES);
+    let cipher = XSalsa20Poly1305::new_from_slice(key).map_err(|_| DecryptError::WrongKey)?;
+    cipher
+        .decrypt(nonce.into(), ciphertext)
+        .map_err(|_| DecryptError::WrongKey)
+}
+
+impl Keychain {
+    /// Decrypt the keychain sent by the server (hex of `salt || nonce || ciphertext`) with the
+    /// master password
+    pub fn decrypt(encrypted: &str, master_password: &str) -> Result<Self, DecryptError> {
+        #[derive(Deserialize)]
+        struct Keys {
+            keys: HashMap<String, String>,
+            current: String,
+        }
+        let encrypted = hex::decode(encrypted).map_err(|_| DecryptError::InvalidData)?;
+        if encrypted.len() < SALT_BYTES {
+            return Err(DecryptError::InvalidData);
+        }
+        let (salt, encrypted) = encrypted.split_at(SALT_BYTES);
+        let key = Zeroizing::new(
+            pwhash_interactive(master_password.as_bytes(), salt)
+                .map_err(|_| DecryptError::InvalidData)?,
+        );
+        let keys = Zeroizing::new(open_secretbox(encrypted, &*key)?);
+        let Keys { keys, current } =
+            serde_json::from_slice(&keys).map_err(|_| DecryptError::InvalidPlaintext)?;
+        let keys = keys
+            .into_iter()
+            .map(|(id, key)| {
+                let key = Zeroizing::new(key);
+                let key = hex::decode(&*key).map_err(|_| DecryptError::InvalidPlaintext)?;
+                Ok((id, Zeroizing::new(key)))
+            })
+            .collect::<Result<HashMap<_, Zeroizing<Vec<u8>>>, DecryptError>>()?;
+        if !keys.contains_key(&current) {
+            return Err(DecryptError::InvalidKeychain(format!(
+                "the current key {} is missing",
+                current
+            )));
+        }
+        if let Some((id, _)) = keys.iter().find(|(_, key)| key.len() != KEY_BYTES) {
+            return Err(DecryptError::InvalidKeychain(format!(
+                "the key {} is not {} bytes long",
+                id, KEY_BYTES
+            )));
+        }
+        Ok(Keychain { keys, current })
+    }
+
+    /// The id of the key used to encrypt new objects
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /// Decrypt a field (hex of `nonce || ciphertext`) encrypted with the key `key_id`
+    pub fn decrypt_field(&self, key_id: &str, field: &str) -> Result<String, DecryptError> {
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| DecryptError::UnknownKey(key_id.into()))?;
+        if field.is_empty() {
+            return Ok(String::new());
+        }
+        let encrypted = hex::decode(field).map_err(|_| DecryptError::InvalidData)?;
+        let plain = open_secretbox(&encrypted, key)?;
+        String::from_utf8(plain).map_err(|_| DecryptError::InvalidPlaintext)
+    }
+
+    /// Encrypt a field with the [current](Keychain::current) key, the result is the hex of
+    /// `nonce || ciphertext`
+    pub fn encrypt_field(&self, field: &str) -> String {
+        // Checked by `decrypt`
+        let key = &self.keys[&self.current];
+        let cipher = XSalsa20Poly1305::new_from_slice(key).expect("keys are 32 bytes long");
+        let nonce = XSalsa20Poly1305::generate_nonce(&mut OsRng);
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend(
+            cipher
+                .encrypt(&nonce, field.as_bytes())
+                .expect("encryption does not fail"),
+        );
+        hex::encode(encrypted)
+    }
+
+    /// Encrypt the fields of an object to create or update with the current key, and mark it as
+    /// encrypted with CSEv1r1. The hash of a password is computed before the encryption and cut
+    /// to `hash_length`, the setting of the user read with
+    /// [hash_length](crate::AuthenticatedApi::hash_length).
+    pub fn encrypt<T: Encrypt>(
+        &self,
+        object: T,
+        hash_length: usize,
+    ) -> Result<Encrypted<T>, crate::Error> {
+        let object = object.complete(hash_length);
+        let mut body = serde_json::to_value(&object)?;
+        let fields = body
+            .as_object_mut()
+            .expect("objects serialize to JSON objects");
+        for name in T::ENCRYPTED_FIELDS {
+            if let Some(serde_json::Value::String(field)) = fields.get(*name) {
+                let encrypted = self.encrypt_field(field);
+                fields.insert((*name).into(), encrypted.into());
+            }
+        }
+        fields.insert("cseType".into(), CSE_V1R1.into());
+        fields.insert("cseKey".into(), self.current.clone().into());
+        Ok(Encrypted { body, object })
+    }
+}
+
+/// Objects to create or update whose fields can be encrypted on the client
+pub trait Encrypt: Serialize + Sized {
+    /// Name of the encrypted fields in the JSON of the object
+    const ENCRYPTED_FIELDS: &'static [&'static str];
+
+    /// Encrypt the fields with the keychain, see [Keychain::encrypt]
+    fn encrypt(
+        self,
+        keychain: &Keychain,
+        hash_length: usize,
+    ) -> Result<Encrypted<Self>, crate::Error> {
+        keychain.encrypt(self, hash_length)
+    }
+
+    /// Fill the fields computed from the plain values, before the object is encrypted or sent.
+    /// The password hashes are cut to `hash_length`, see [hash_password](crate::password::hash_password)
+    #[doc(hidden)]
+    fn complete(self, _hash_length: usize) -> Self {
+        self
+    }
+
+    /// Whether [complete](Self::complete) computes a password hash, its length is then read from
+    /// the [settings](crate::AuthenticatedApi::hash_length) of the user
+    #[doc(hidden)]
+    fn needs_hash_length(&self) -> bool {
+        false
+    }
+}
+
+/// An object whose fields were encrypted by [Keychain::encrypt], it can be given to the `create`
+/// and `update` methods like the object itself
+pub struct Encrypted<T> {
+    body: serde_json::Value,
+    object: T,
+}
+
+impl<T> Serialize for Encrypted<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.body.serialize(serializer)
+    }
+}
+
+/// The body of a create or update request, encrypted or not
+pub enum Payload<T> {
+    Plain(T),
+    Encrypted(Encrypted<T>),
+}
+
+impl<T> Payload<T> {
+    /// The object before encryption
+    pub fn object(&self) -> &T {
+        match self {
+            Payload::Plain(object) => object,
+            Payload::Encrypted(encrypted) => &encrypted.object,
+        }
+    }
+}
+
+impl<T: crate::Validate> Payload<T> {
+    /// Check the limits of the server on the plain values: the object of an encrypted payload
+    /// is checked before its encryption, a plain payload is only checked if its values are not
+    /// ciphertexts (see [Validate::cse_type](crate::Validate::cse_type))
+    pub(crate) fn validate(&self) -> Result<(), crate::ValidationError> {
+        match self {
+            Payload::Plain(object) => match object.cse_type() {
+                Some(cse_type) if !cse_type.is_empty() && cse_type != CSE_NONE => Ok(()),
+                _ => object.validate(),
+            },
+            Payload::Encrypted(encrypted) => encrypted.object.validate(),
+        }
+    }
+}
+
+impl<T: Encrypt> Payload<T> {
+    /// See [Encrypt::complete], encrypted objects were completed before their encryption
+    pub(crate) async fn complete(
+        self,
+        api: &crate::AuthenticatedApi,
+    ) -> Result<Self, crate::Error> {
+        match self {
+            Payload::Plain(object) => {
+                let hash_length = if object.needs_hash_length() {
+                    api.hash_length().await?
+                } else {
+                    HASH_LENGTH
+                };
+                Ok(Payload::Plain(object.complete(hash_length)))
+            }
+            encrypted => Ok(encrypted),
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Payload<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Payload::Plain(object) => object.serialize(serializer),
+            Payload::Encrypted(encrypted) => encrypted.serialize(serializer),
+        }
+    }
+}
+
+impl<T> From<T> for Payload<T> {
+    fn from(object: T) -> Self {
+        Payload::Plain(object)
+    }
+}
+
+impl<T> From<Encrypted<T>> for Payload<T> {
+    fn from(encrypted: Encrypted<T>) -> Self {
+        Payload::Encrypted(encrypted)
+    }
+}
+
+macro_rules! encrypted_fields {
+    ($($ty:ty => [$($field:expr),* $(,)?]),* $(,)?) => {
+        $(
+            impl Encrypt for $ty {
+                const ENCRYPTED_FIELDS: &'static [&'static str] = &[$($field),*];
+            }
+        )*
+    };
+}
+
+impl Encrypt for crate::password::CreatePassword {
+    const ENCRYPTED_FIELDS: &'static [&'static str] = &[
+        "label",
+        "username",
+        "password",
+        "url",
+        "notes",
+        "customFields",
+    ];
+
+    fn complete(self, hash_length: usize) -> Self {
+        self.with_computed_hash(hash_length)
+    }
+
+    fn needs_hash_length(&self) -> bool {
+        self.hash.is_none()
+    }
+}
+
+impl Encrypt for crate::password::UpdatePassword {
+    const ENCRYPTED_FIELDS: &'static [&'static str] = &[
+        "label",
+        "username",
+        "password",
+        "url",
+        "notes",
+        "customFields",
+    ];
+
+    fn complete(self, hash_length: usize) -> Self {
+        self.with_computed_hash(hash_length)
+    }
+
+    fn needs_hash_length(&self) -> bool {
+        self.hash.is_none()
+    }
+}
+
+encrypted_fields! {
+    crate::folder::CreateFolder => ["label"],
+    crate::folder::UpdateFolder => ["label"],
+    crate::tag::CreateTag => ["label", "color"],
+    crate::tag::UpdateTag => ["label", "color"],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::password::{tests::password_json, Password};
+    use serde_json::json;
+
+    const KEY_ID: &str = "7a2f6c1e-3b4d-4e5f-8a9b-0c1d2e3f4a5b";
+
+    fn seal(plain: &[u8], key: &[u8], nonce: u8) -> Vec<u8> {
+        let cipher = xsalsa20poly1305::XSalsa20Poly1305::new_from_slice(key).unwrap();
+        let nonce = [nonce; NONCE_BYTES];
+        let mut sealed = nonce.to_vec();
+        sealed.extend(cipher.encrypt((&nonce).into(), plain).unwrap());
+        sealed
+    }
+
+    fn encrypt_keychain(keys: serde_json::Value) -> String {
+        let salt = [7; SALT_BYTES];
+        let key = pwhash_interactive(b"master password", &salt).unwrap();
+        let mut encrypted = salt.to_vec();
+        encrypted.extend(seal(keys.to_string().as_bytes(), &key, 1));
+        hex::encode(encrypted)
+    }
+
+    fn keychain() -> Keychain {
+        let encrypted = encrypt_keychain(json!({
+            "keys": { KEY_ID: hex::encode([42; 32]) },
+            "current": KEY_ID,
+        }));
+
+        assert!(matches!(
+            Keychain::decrypt(&encrypted, "wrong password"),
+            Err(DecryptError::WrongKey)
+        ));
+        Keychain::decrypt(&encrypted, "master password").unwrap()
+    }
+
+    #[test]
+    fn invalid_keychain() {
+        let missing_current = encrypt_keychain(json!({
+            "keys": { KEY_ID: hex::encode([42; 32]) },
+            "current": "other",
+        }));
+        assert!(matches!(
+            Keychain::decrypt(&missing_current, "master password"),
+            Err(DecryptError::InvalidKeychain(_))
+        ));
+        let short_key = encrypt_keychain(json!({
+            "keys": { KEY_ID: hex::encode([42; 16]) },
+            "current": KEY_ID,
+        }));
+        assert!(matches!(
+            Keychain::decrypt(&short_key, "master password"),
+            Err(DecryptError::InvalidKeychain(_))
+        ));
+    }
+
+    #[test]
+    fn decrypt() {
+        let keychain = keychain();
+        assert_eq!(keychain.current(), KEY_ID);
+
+        let field = hex::encode(seal(b"hunter2", &[42; 32], 2));
+        assert_eq!(keychain.decrypt_field(KEY_ID, &field).unwrap(), "hunter2");
+        assert!(matches!(
+            keychain.decrypt_field("other", &field),
+            Err(DecryptError::UnknownKey(_))
+        ));
+        let other_key = hex::encode(seal(b"hunter2", &[43; 32], 2));
+        assert!(matches!(
+            keychain.decrypt_field(KEY_ID, &other_key),
+            Err(DecryptError::WrongKey)
+        ));
+        assert!(matches!(
+            keychain.decrypt_field(KEY_ID, "not hex"),
+            Err(DecryptError::InvalidData)
+        ));
+
+        let plain: Password = serde_json::from_value(password_json()).unwrap();
+        let plain = plain.decrypt(&keychain).unwrap();
+        assert_eq!(plain.versioned.label, "Example");
+
+        let mut json = password_json();
+        for (field, value) in &[
+            ("label", "Example"),
+            ("username", "alice"),
+            ("password", "hunter2"),
+            ("url", "https://example.com"),
+            ("notes", "some notes"),
+            ("customFields", "[]"),
+        ] {
+            json[field] = hex::encode(seal(value.as_bytes(), &[42; 32], 3)).into();
+        }
+        json["cseType"] = CSE_V1R1.into();
+        json["cseKey"] = KEY_ID.into();
+        let encrypted: Password = serde_json::from_value(json.clone()).unwrap();
+        let decrypted = encrypted.decrypt(&keychain).unwrap();
+        assert_eq!(decrypted.versioned.label, "Example");
+        assert_eq!(decrypted.versioned.username, "alice");
+        assert_eq!(decrypted.reveal().expose(), "hunter2");
+        assert_eq!(decrypted.versioned.url, "https://example.com");
+        assert_eq!(decrypted.versioned.notes, "some notes");
+        assert_eq!(decrypted.versioned.custom_fields.fields(), Some(&[][..]));
+
+        json["cseType"] = "CSEv2".into();
+        let unknown: Password = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            unknown.decrypt(&keychain),
+            Err(DecryptError::UnsupportedType(_))
+        ));
+    }
+
+    #[test]
+    fn encrypt() {
+        use crate::{
+            password::{CreatePassword, UpdatePassword},
+            tag::CreateTag,
+            Color,
+        };
+
+        let keychain = keychain();
+        let create = || CreatePassword::new("Example", "hunter2").username("alice");
+        let plain = serde_json::to_value(create().complete(HASH_LENGTH)).unwrap();
+        assert_eq!(plain["hash"], "f3bbbd66a63d4bf1747940578ec3d0103530e21d");
+
+        let encrypted = create().encrypt(&keychain, HASH_LENGTH).unwrap();
+        let encrypted = serde_json::to_value(encrypted).unwrap();
+        assert_eq!(encrypted["cseType"], CSE_V1R1);
+        assert_eq!(encrypted["cseKey"], KEY_ID);
+        assert_eq!(encrypted["hash"], plain["hash"]);
+        assert_eq!(encrypted["url"], serde_json::Value::Null);
+        for name in &["label", "username", "password"] {
+            let field = encrypted[name].as_str().unwrap();
+            assert_eq!(
+                keychain.decrypt_field(KEY_ID, field).unwrap(),
+                plain[name].as_str().unwrap()
+            );
+        }
+        // Fresh nonces
+        assert_ne!(keychain.encrypt_field("a"), keychain.encrypt_field("a"));
+
+        // The hash follows the setting of the user, even set to 0
+        for &length in &[20, 0] {
+            let encrypted = create().encrypt(&keychain, length).unwrap();
+            let encrypted = serde_json::to_value(encrypted).unwrap();
+            assert_eq!(
+                encrypted["hash"],
+                &plain["hash"].as_str().unwrap()[..length]
+            );
+        }
+        let update = UpdatePassword::new("Example", "hunter2", uuid::Uuid::nil());
+        let encrypted = serde_json::to_value(keychain.encrypt(update, 0).unwrap()).unwrap();
+        assert_eq!(encrypted["hash"], "");
+
+        let color = Color {
+            red: 0x74,
+            green: 0x5b,
+            blue: 0xca,
+        };
+        let tag = CreateTag::new("Work", color).encrypt(&keychain, 0).unwrap();
+        let tag = serde_json::to_value(tag).unwrap();
+        let color = tag["color"].as_str().unwrap();
+        assert_eq!(keychain.decrypt_field(KEY_ID, color).unwrap(), "#745bca");
+    }
+}
@@ -0,0 +1,326 @@
+use crate::{
+    settings::{SettingValue, Settings},
+    LoginDetails,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Maximum time a single probe can take
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The checks done by [diagnose](crate::AuthenticatedApi::diagnose), in order
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Probe {
+    /// The server answers to HTTP requests (DNS, TCP and TLS are working)
+    Reachability,
+    /// `status.php` reports an installed server that is not in maintenance
+    Status,
+    /// The OCS capabilities can be read with the credentials
+    Capabilities,
+    /// The passwords app answers on its API
+    PasswordsApp,
+    /// A passwords session can be opened
+    SessionOpen,
+    /// A setting can be read in the session
+    SettingsRead,
+    /// The clock of the server is close to the local clock
+    ClockSkew,
+}
+
+/// The outcome of a [Probe]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Outcome {
+    Passed(String),
+    Failed(String),
+    /// A probe it depends on failed
+    Skipped,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiagnosticStep {
+    pub probe: Probe,
+    pub outcome: Outcome,
+    /// What to check when the probe failed
+    pub hint: Option<String>,
+    pub duration: Duration,
+}
+
+/// The result of all the probes
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DiagnosticReport {
+    pub steps: Vec<DiagnosticStep>,
+}
+
+impl DiagnosticReport {
+    /// All the probes passed
+    pub fn passed(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| matches!(step.outcome, Outcome::Passed(_)))
+    }
+
+    fn skip(&mut self, probes: &[Probe]) {
+        for &probe in probes {
+            self.steps.push(DiagnosticStep {
+                probe,
+                outcome: Outcome::Skipped,
+                hint: None,
+                duration: Duration::default(),
+            })
+        }
+    }
+
+    /// Run the probe with a timeout, returning its value if it passed
+    async fn run<T, F>(&mut self, probe: Probe, fut: F) -> Option<T>
+    where
+        F: std::future::Future<Output = Result<(T, String), Failure>>,
+    {
+        let start = std::time::Instant::now();
+        let result = match crate::runtime::timeout(PROBE_TIMEOUT, fut).await {
+            Some(result) => result,
+            None => Err(Failure {
+                message: format!("timed out after {:?}", PROBE_TIMEOUT),
+                hint: "the server or a proxy is not answering",
+            }),
+        };
+        let duration = start.elapsed();
+        match result {
+            Ok((value, message)) => {
+                self.steps.push(DiagnosticStep {
+                    probe,
+                    outcome: Outcome::Passed(message),
+                    hint: None,
+                    duration,
+                });
+                Some(value)
+            }
+            Err(failure) => {
+                log::debug!("Probe {:?} failed: {}", probe, failure.message);
+                self.steps.push(DiagnosticStep {
+                    probe,
+                    outcome: Outcome::Failed(failure.message),
+                    hint: Some(failure.hint.into()),
+                    duration,
+                });
+                None
+            }
+        }
+    }
+}
+
+struct Failure {
+    message: String,
+    hint: &'static str,
+}
+
+fn failure(hint: &'static str) -> impl Fn(reqwest::Error) -> Failure {
+    move |e| Failure {
+        message: e.to_string(),
+        hint,
+    }
+}
+
+pub(crate) async fn diagnose(login: &LoginDetails) -> DiagnosticReport {
+    let client = reqwest::Client::new();
+    let server = &login.server;
+    let api_root = format!("{}{}/", login.passwords_url(), crate::DEFAULT_API_VERSION);
+    let mut report = DiagnosticReport::default();
+
+    let reachable = report
+        .run(Probe::Reachability, async {
+            let resp = client
+                .get(server.as_str())
+                .send()
+                .await
+                .map_err(failure("check the server URL, DNS and TLS certificates"))?;
+            Ok(((), format!("server answered with {}", resp.status())))
+        })
+        .await;
+    if reachable.is_none() {
+        report.skip(&[
+            Probe::Status,
+            Probe::Capabilities,
+            Probe::PasswordsApp,
+            Probe::SessionOpen,
+            Probe::SettingsRead,
+            Probe::ClockSkew,
+        ]);
+        return report;
+    }
+
+    let server_offset = report
+        .run(Probe::Status, async {
+            #[derive(Deserialize)]
+            struct Status {
+                installed: bool,
+                maintenance: bool,
+                versionstring: String,
+            }
+            let resp = client
+                .get(crate::join_url(server, "status.php"))
+                .send()
+                .await
+                .map_err(failure("check the server URL"))?;
+            let offset = crate::server_time_offset(resp.headers());
+            let status: Status = resp.json().await.map_err(failure(
+                "the URL does not point to a nextcloud server, check the path and proxies",
+            ))?;
+            if !status.installed {
+                Err(Failure {
+                    message: "nextcloud is not installed".into(),
+                    hint: "finish the nextcloud installation",
+                })
+            } else if status.maintenance {
+                Err(Failure {
+                    message: "nextcloud is in maintenance mode".into(),
+                    hint: "wait for the end of the maintenance",
+                })
+            } else {
+                Ok((offset, format!("nextcloud {}", status.versionstring)))
+            }
+        })
+        .await;
+
+    report
+        .run(Probe::Capabilities, async {
+            let resp = client
+                .get(format!(
+                    "{}ocs/v2.php/cloud/capabilities?format=json",
+                    server
+                ))
+                .header("OCS-APIRequest", "true")
+                .basic_auth(&login.login_name, Some(&login.app_password))
+                .send()
+                .await
+                .map_err(failure("check the server URL"))?;
+            match resp.status().as_u16() {
+                200 => Ok(((), "capabilities are readable".into())),
+                401 => Err(Failure {
+                    message: "credentials were refused".into(),
+                    hint: "check the login name and app password",
+                }),
+                code => Err(Failure {
+                    message: format!("capabilities returned {}", code),
+                    hint: "check the proxies in front of the server",
+                }),
+            }
+        })
+        .await;
+
+    let app_present = report
+        .run(Probe::PasswordsApp, async {
+            let resp = client
+                .get(format!("{}session/request", api_root))
+                .basic_auth(&login.login_name, Some(&login.app_password))
+                .send()
+                .await
+                .map_err(failure("check the server URL"))?;
+            let status = resp.status();
+            let value: Result<serde_json::Value, _> = resp.json().await;
+            match value {
+                Ok(_) if status.is_success() => Ok(((), "passwords API answered".into())),
+                _ => Err(Failure {
+                    message: format!("passwords API returned {}", status),
+                    hint: "install and enable the passwords app",
+                }),
+            }
+        })
+        .await;
+
+    let session = match app_present {
+        Some(()) => {
+            report
+                .run(Probe::SessionOpen, async {
+                    let resp = client
+                        .post(format!("{}session/open", api_root))
+                        .basic_auth(&login.login_name, Some(&login.app_password))
+                        .send()
+                        .await
+                        .map_err(failure("check the server URL"))?;
+                    let status = resp.status();
+                    let session = resp
+                        .headers()
+                        .get("X-API-SESSION")
+                        .and_then(|session| session.to_str().ok())
+                        .map(String::from);
+                    match session {
+                        Some(session) if status.is_success() => {
+                            Ok((session, "session opened".into()))
+                        }
+                        _ => Err(Failure {
+                            message: format!("session open returned {} without a session", status),
+                            hint: "the account may require a master password or a second factor, \
+                                   or a proxy removes the X-API-SESSION header",
+                        }),
+                    }
+                })
+                .await
+        }
+        None => {
+            report.skip(&[Probe::SessionOpen]);
+            None
+        }
+    };
+
+    match session {
+        Some(session) => {
+            report
+                .run(Probe::SettingsRead, async {
+                    let resp = client
+                        .post(format!("{}settings/get", api_root))
+                        .json(&["server.version"])
+                        .header("X-API-SESSION", &session)
+                        .basic_auth(&login.login_name, Some(&login.app_password))
+                        .send()
+                        .await
+                        .map_err(failure("check the server URL"))?;
+                    let settings: Settings = resp
+                        .json()
+                        .await
+                        .map_err(failure("the session was not accepted, check the proxies"))?;
+                    match settings.to_values().pop() {
+                        Some(SettingValue::Version(version)) => {
+                            Ok(((), format!("passwords app {}", version)))
+                        }
+                        _ => Err(Failure {
+                            message: "the server did not return its version".into(),
+                            hint: "update the passwords app",
+                        }),
+                    }
+                })
+                .await;
+            let close = client
+                .get(format!("{}session/close", api_root))
+                .header("X-API-SESSION", &session)
+                .basic_auth(&login.login_name, Some(&login.app_password))
+                .send()
+                .await;
+            if let Err(e) = close {
+                log::debug!("Could not close the diagnostic session: {}", e);
+            }
+        }
+        None => report.skip(&[Probe::SettingsRead]),
+    }
+
+    match server_offset.flatten() {
+        Some(offset) => {
+            report
+                .run(Probe::ClockSkew, async {
+                    let message = format!("clocks differ by {}s", offset);
+                    // The Date header has a precision of one second
+                    if offset.abs() > 60 {
+                        Err(Failure {
+                            message,
+                            hint: "synchronize the clock of this device or of the server",
+                        })
+                    } else {
+                        Ok(((), message))
+                    }
+                })
+                .await;
+        }
+        None => report.skip(&[Probe::ClockSkew]),
+    }
+
+    report
+}
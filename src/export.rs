@@ -0,0 +1,216 @@
+use crate::{folder::Folder, utils::for_each_yielding, AuthenticatedApi, Error};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// The folder tree of an account, without any password. It can be applied to another account
+/// with [apply_folder_template](crate::import::apply_folder_template)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct FolderTemplate {
+    pub folders: Vec<FolderNode>,
+}
+
+/// A folder of a [FolderTemplate]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FolderNode {
+    pub label: String,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<FolderNode>,
+}
+
+impl FolderNode {
+    pub fn new(label: String) -> Self {
+        FolderNode {
+            label,
+            favorite: false,
+            hidden: false,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// The folders of a [FolderTemplate] being built, see [from_folders](FolderTemplate::from_folders)
+struct Builder<'f> {
+    known: HashSet<uuid::Uuid>,
+    /// The folders of each parent, `None` for the root, sorted by label
+    children: HashMap<Option<uuid::Uuid>, Vec<&'f Folder>>,
+    /// The nodes built for each parent, in the reverse order of the labels
+    built: HashMap<Option<uuid::Uuid>, Vec<FolderNode>>,
+}
+
+impl<'f> Builder<'f> {
+    fn new(folders: &[Folder]) -> Self {
+        Builder {
+            known: folders.iter().map(|f| f.id).collect(),
+            children: HashMap::new(),
+            built: HashMap::new(),
+        }
+    }
+
+    fn parent(&self, folder: &Folder) -> Option<uuid::Uuid> {
+        let parent = folder.versioned.parent.id();
+        if self.known.contains(&parent) && parent != folder.id {
+            Some(parent)
+        } else {
+            None
+        }
+    }
+
+    fn add(&mut self, folder: &'f Folder) {
+        let parent = self.parent(folder);
+        self.children.entry(parent).or_default().push(folder);
+    }
+
+    fn sort(siblings: &mut [&'f Folder]) {
+        siblings.sort_by(|a, b| a.versioned.label.cmp(&b.versioned.label));
+    }
+
+    fn children_of(&self, parent: Option<uuid::Uuid>) -> impl Iterator<Item = &'f Folder> + '_ {
+        self.children.get(&parent).into_iter().flatten().copied()
+    }
+
+    /// Build the node of the folder, once the nodes of its children are built
+    fn build(&mut self, folder: &Folder) {
+        let mut children = self.built.remove(&Some(folder.id)).unwrap_or_default();
+        children.reverse();
+        let node = FolderNode {
+            label: folder.versioned.label.clone(),
+            favorite: folder.versioned.favorite,
+            hidden: folder.versioned.hidden,
+            children,
+        };
+        let parent = self.parent(folder);
+        self.built.entry(parent).or_default().push(node);
+    }
+
+    fn finish(mut self) -> FolderTemplate {
+        let mut folders = self.built.remove(&None).unwrap_or_default();
+        folders.reverse();
+        FolderTemplate { folders }
+    }
+}
+
+impl FolderTemplate {
+    /// Build the tree from a flat list of folders. Folders whose parent is not in the list are
+    /// placed at the root. Siblings are sorted by label.
+    ///
+    /// This is CPU heavy on large vaults, see [from_folders_async](Self::from_folders_async)
+    pub fn from_folders(folders: &[Folder]) -> Self {
+        let mut builder = Builder::new(folders);
+        for folder in folders {
+            builder.add(folder);
+        }
+        for siblings in builder.children.values_mut() {
+            Builder::sort(siblings);
+        }
+        // The folders reachable from the root, parents before their children
+        let mut order = Vec::new();
+        let mut level: Vec<_> = builder.children_of(None).collect();
+        while !level.is_empty() {
+            let mut next = Vec::new();
+            for folder in &level {
+                next.extend(builder.children_of(Some(folder.id)));
+            }
+            order.append(&mut level);
+            level = next;
+        }
+        for folder in order.into_iter().rev() {
+            builder.build(folder);
+        }
+        builder.finish()
+    }
+
+    /// Same as [from_folders](Self::from_folders), but yields to the executor regularly so that
+    /// a large vault does not block the other tasks
+    pub async fn from_folders_async(folders: &[Folder]) -> Self {
+        let mut builder = Builder::new(folders);
+        for_each_yielding(folders, |folder| builder.add(folder)).await;
+        for_each_yielding(builder.children.values_mut(), |siblings| {
+            Builder::sort(siblings)
+        })
+        .await;
+        let mut order = Vec::new();
+        let mut level: Vec<_> = builder.children_of(None).collect();
+        while !level.is_empty() {
+            let mut next = Vec::new();
+            for_each_yielding(&level, |folder| {
+                next.extend(builder.children_of(Some(folder.id)))
+            })
+            .await;
+            order.append(&mut level);
+            level = next;
+        }
+        for_each_yielding(order.into_iter().rev(), |folder| builder.build(folder)).await;
+        builder.finish()
+    }
+}
+
+/// Export the folder tree of the account.
+///
+/// Notes
+///  - Hidden folders are not listed by the server, so they are not part of the template
+pub async fn folder_template(api: &AuthenticatedApi) -> Result<FolderTemplate, Error> {
+    let folders = api.folder().list(None).await?;
+    Ok(FolderTemplate::from_folders_async(&folders).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn folder(id: u8, parent: u8, label: &str) -> Folder {
+        let uuid = |n: u8| format!("00000000-0000-0000-0000-0000000000{:02x}", n);
+        serde_json::from_value(json!({
+            "id": uuid(id),
+            "label": label,
+            "parent": uuid(parent),
+            "created": 0,
+            "updated": 0,
+            "edited": 0,
+            "revision": uuid(0xff),
+            "cseType": "none",
+            "cseKey": "",
+            "sseType": "SSEv1r1",
+            "client": "CLIENT::UNKNOWN",
+            "hidden": false,
+            "trashed": false,
+            "favorite": label == "Team",
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn tree() {
+        let folders = vec![
+            folder(3, 1, "Servers"),
+            folder(1, 0, "Team"),
+            folder(2, 1, "Accounts"),
+            folder(4, 0, "Personal"),
+            folder(5, 9, "Orphan"),
+        ];
+        let template = FolderTemplate::from_folders(&folders);
+        assert_eq!(FolderTemplate::from_folders_async(&folders).await, template);
+        assert_eq!(
+            serde_json::to_value(&template).unwrap(),
+            json!({
+                "folders": [
+                    {"label": "Orphan", "favorite": false, "hidden": false},
+                    {"label": "Personal", "favorite": false, "hidden": false},
+                    {
+                        "label": "Team",
+                        "favorite": true,
+                        "hidden": false,
+                        "children": [
+                            {"label": "Accounts", "favorite": false, "hidden": false},
+                            {"label": "Servers", "favorite": false, "hidden": false},
+                        ]
+                    },
+                ]
+            })
+        );
+    }
+}
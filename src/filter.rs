@@ -0,0 +1,308 @@
+use crate::password::Password;
+
+type Predicate = Box<dyn Fn(&Password) -> bool + Send + Sync>;
+
+/// A client side filter on passwords, built from predicates and combined with
+/// [and](Filter::and), [or](Filter::or) and [not](Filter::not)
+pub struct Filter {
+    predicate: Predicate,
+}
+
+impl std::fmt::Debug for Filter {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("Filter").finish()
+    }
+}
+
+/// The url of the password, the scheme is optional
+fn password_url(password: &Password) -> Option<url::Url> {
+    let url = password.versioned.url.trim();
+    if url.is_empty() {
+        return None;
+    }
+    match url::Url::parse(url) {
+        Ok(url) if url.has_host() => Some(url),
+        _ => url::Url::parse(&format!("https://{}", url)).ok(),
+    }
+}
+
+/// The host of the url of the password, the scheme is optional
+pub(crate) fn password_host(password: &Password) -> Option<String> {
+    password_url(password)?.host_str().map(str::to_lowercase)
+}
+
+/// Second level labels used as public suffixes under country codes, like `co.uk`
+const SECOND_LEVEL_SUFFIXES: &[&str] = &[
+    "ac", "co", "com", "edu", "gov", "go", "ne", "net", "or", "org",
+];
+
+/// The domain that can be registered for the host, like `example.co.uk` for
+/// `login.example.co.uk`. This uses a heuristic instead of the public suffix list, IP addresses
+/// are returned as is.
+fn registrable_domain(host: &url::Host<&str>) -> String {
+    let domain = match host {
+        url::Host::Domain(domain) => domain.trim_end_matches('.').to_lowercase(),
+        ip => return ip.to_string(),
+    };
+    let labels: Vec<_> = domain.split('.').collect();
+    let kept = match labels.as_slice() {
+        [.., second, tld] if tld.len() == 2 && SECOND_LEVEL_SUFFIXES.contains(second) => 3,
+        _ => 2,
+    };
+    labels[labels.len().saturating_sub(kept)..].join(".")
+}
+
+/// The registrable domain of the url of the password, used for its favicon
+pub(crate) fn password_domain(password: &Password) -> Option<String> {
+    Some(registrable_domain(&password_url(password)?.host()?))
+}
+
+/// How well a password matches a url, from the weakest to the strongest, see
+/// [find_for_url](crate::password::PasswordApi::find_for_url)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum UrlMatch {
+    /// The label or the url of the password contains the name of the domain, like `Example`
+    /// for `https://login.example.com`
+    Substring,
+    /// The url of the password has the same registrable domain, like `example.com` for
+    /// `https://login.example.com`
+    Domain,
+    /// The url of the password has the same host and port
+    Host,
+}
+
+/// How well the password matches the url, `None` if it does not match
+pub(crate) fn url_match(password: &Password, url: &url::Url) -> Option<UrlMatch> {
+    let host = url.host()?;
+    let domain = registrable_domain(&host);
+    if let Some(password_url) = password_url(password) {
+        if let Some(password_host) = password_url.host() {
+            let same_domain = registrable_domain(&password_host) == domain;
+            if same_domain
+                && password_host.to_string().to_lowercase() == host.to_string().to_lowercase()
+                && password_url.port_or_known_default() == url.port_or_known_default()
+            {
+                return Some(UrlMatch::Host);
+            }
+            if same_domain {
+                return Some(UrlMatch::Domain);
+            }
+        }
+    }
+
+    let name = match host {
+        url::Host::Domain(_) => domain.split('.').next().unwrap_or_default().to_string(),
+        _ => domain,
+    };
+    let contains = |text: &str| text.to_lowercase().contains(&name);
+    if !name.is_empty()
+        && (contains(&password.versioned.label) || contains(&password.versioned.url))
+    {
+        Some(UrlMatch::Substring)
+    } else {
+        None
+    }
+}
+
+impl Filter {
+    /// Filter on an arbitrary predicate
+    pub fn new(predicate: impl Fn(&Password) -> bool + Send + Sync + 'static) -> Self {
+        Filter {
+            predicate: Box::new(predicate),
+        }
+    }
+
+    /// Matches every password
+    pub fn any() -> Self {
+        Filter::new(|_| true)
+    }
+
+    /// The label contains `text`, ignoring case
+    pub fn label_contains(text: &str) -> Self {
+        let text = text.to_lowercase();
+        Filter::new(move |password| password.versioned.label.to_lowercase().contains(&text))
+    }
+
+    /// The host of the url matches `pattern`, ignoring case. A pattern starting with `*.`
+    /// matches all the subdomains of the rest of the pattern (but not the domain itself)
+    pub fn url_host_matches(pattern: &str) -> Self {
+        let pattern = pattern.to_lowercase();
+        Filter::new(move |password| match password_host(password) {
+            None => false,
+            Some(host) => match pattern.strip_prefix('*') {
+                Some(suffix) => host.ends_with(suffix),
+                None => host == pattern,
+            },
+        })
+    }
+
+    /// The password is directly in this folder
+    pub fn in_folder(folder: uuid::Uuid) -> Self {
+        Filter::new(move |password| password.folder.id() == folder)
+    }
+
+    /// The password is marked as favorite
+    pub fn favorite() -> Self {
+        Filter::new(|password| password.versioned.favorite)
+    }
+
+    /// Both filters match
+    pub fn and(self, other: Filter) -> Self {
+        Filter::new(move |password| self.matches(password) && other.matches(password))
+    }
+
+    /// One of the filters match
+    pub fn or(self, other: Filter) -> Self {
+        Filter::new(move |password| self.matches(password) || other.matches(password))
+    }
+
+    /// The filter does not match
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Filter::new(move |password| !self.matches(password))
+    }
+
+    pub fn matches(&self, password: &Password) -> bool {
+        (self.predicate)(password)
+    }
+
+    /// Keep the matching passwords, in the same order.
+    ///
+    /// This is CPU heavy on large vaults, see [apply_async](Filter::apply_async)
+    pub fn apply(&self, passwords: Vec<Password>) -> Vec<Password> {
+        passwords
+            .into_iter()
+            .filter(|password| self.matches(password))
+            .collect()
+    }
+
+    /// Same as [apply](Filter::apply), but yields to the executor regularly so that a large vault
+    /// does not block the other tasks
+    pub async fn apply_async(&self, passwords: Vec<Password>) -> Vec<Password> {
+        let mut kept = Vec::new();
+        let mut passwords = passwords.into_iter().peekable();
+        while passwords.peek().is_some() {
+            kept.extend(
+                passwords
+                    .by_ref()
+                    .take(crate::utils::YIELD_EVERY)
+                    .filter(|password| self.matches(password)),
+            );
+            crate::runtime::yield_now().await;
+        }
+        kept
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::password::tests::password_json;
+
+    fn with_url(url: &str) -> Password {
+        let mut json = password_json();
+        json["url"] = url.into();
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn password_domains() {
+        let cases = [
+            ("https://login.example.com/path", Some("example.com")),
+            ("shop.example.co.uk", Some("example.co.uk")),
+            ("http://192.168.1.10:8080", Some("192.168.1.10")),
+            ("not a url", None),
+            ("  ", None),
+        ];
+        for (url, expected) in &cases {
+            assert_eq!(
+                password_domain(&with_url(url)).as_deref(),
+                *expected,
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn url_matches() {
+        let url_only = |url: &str| {
+            let mut password = with_url(url);
+            password.versioned.label = "Account".into();
+            password
+        };
+        let url = url::Url::parse("https://login.accounts.example.com/signin?next=/").unwrap();
+        let cases = [
+            ("https://login.accounts.example.com", Some(UrlMatch::Host)),
+            ("LOGIN.accounts.example.com/other", Some(UrlMatch::Host)),
+            (
+                "https://login.accounts.example.com:8443",
+                Some(UrlMatch::Domain),
+            ),
+            ("https://example.com", Some(UrlMatch::Domain)),
+            ("https://example.org", Some(UrlMatch::Substring)),
+            ("https://example.com.evil.org", Some(UrlMatch::Substring)),
+            ("https://other.net", None),
+            ("", None),
+        ];
+        for (password_url, expected) in &cases {
+            assert_eq!(
+                url_match(&url_only(password_url), &url),
+                *expected,
+                "{}",
+                password_url
+            );
+        }
+
+        let mut labelled = url_only("");
+        labelled.versioned.label = "My Example account".into();
+        assert_eq!(url_match(&labelled, &url), Some(UrlMatch::Substring));
+
+        let url = url::Url::parse("https://shop.example.co.uk").unwrap();
+        assert_eq!(
+            url_match(&url_only("https://www.example.co.uk"), &url),
+            Some(UrlMatch::Domain)
+        );
+        assert_eq!(url_match(&url_only("https://other.co.uk"), &url), None);
+
+        let url = url::Url::parse("http://192.168.1.10:8080/admin").unwrap();
+        assert_eq!(
+            url_match(&url_only("http://192.168.1.10:8080"), &url),
+            Some(UrlMatch::Host)
+        );
+        assert_eq!(
+            url_match(&url_only("https://192.168.1.10"), &url),
+            Some(UrlMatch::Domain)
+        );
+        assert_eq!(url_match(&url_only("https://10.1.168.192"), &url), None);
+        let url = url::Url::parse("https://[::1]:8443").unwrap();
+        assert_eq!(
+            url_match(&url_only("[::1]:8443"), &url),
+            Some(UrlMatch::Host)
+        );
+
+        let url = url::Url::parse("https://bücher.example").unwrap();
+        assert_eq!(
+            url_match(&url_only("https://xn--bcher-kva.example/"), &url),
+            Some(UrlMatch::Host)
+        );
+        assert_eq!(
+            url_match(&url_only("shop.BÜCHER.example"), &url),
+            Some(UrlMatch::Domain)
+        );
+    }
+
+    #[test]
+    fn host_glob() {
+        let filter = Filter::url_host_matches("*.corp.example");
+        assert!(filter.matches(&with_url("https://git.corp.example/login")));
+        assert!(filter.matches(&with_url("mail.CORP.example")));
+        assert!(!filter.matches(&with_url("https://corp.example")));
+        assert!(!filter.matches(&with_url("https://corp.example.org")));
+        assert!(!filter.matches(&with_url("")));
+
+        let filter = filter.and(Filter::label_contains("exam")).not();
+        assert!(!filter.matches(&with_url("https://git.corp.example")));
+        assert!(filter.matches(&with_url("https://example.com")));
+    }
+}
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 create_calls! {
     FolderApi where 
-        Endpoint = "1.0/folder",
+        Resource = "folder",
         Details: Details,
         Type: Folder,
         Create: CreateFolder,
@@ -35,7 +35,10 @@ create_calls! {
         /// Notes
         ///  - The property trashed will be set to false if not present
         ///  - The property parent is only supported in 2019.5.0 and later
-        ///  - The list will not include hidden folders
+        ///  - Only the created, updated, edited, cseType, sseType, trashed, favorite and parent
+        ///    properties can be searched
+        ///  - The list will not include hidden folders, they can not be searched either: they are
+        ///    only reachable by their id with [get](Self::get)
         ///  - The list will not include suspended folders where a parent folder is in the trash
         pub async fn find(&self, criteria: Criteria, details: Option<Details>) -> Result<Vec<Type>, Error>;
 
@@ -43,6 +46,7 @@ create_calls! {
         /// The create action creates a new folder with the given attributes.
         ///
         /// Notes
+        ///  - Without a parent, the folder is created in the base folder ([BASE_FOLDER])
         ///  - If the uuid of the parent folder is invalid or does not exist, the base folder uuid will be used instead
         ///  - If the folder is not hidden and should be created in a hidden folder, it will be created in the base folder instead
         ///  - If the edited argument is "0", missing or in the future, the current time will be used
@@ -83,9 +87,39 @@ create_calls! {
         ///  - If the parent folder does not exist anymore, it will be moved to the base folder
         ///  - Deleted folders can not be restored
         pub async fn restore(&self, id: uuid::Uuid, revision: Option<uuid::Uuid>) -> Result<Identifier, Error>;
+
+        Patches: FolderPatch;
+        /// Apply the changes of the patch, see [FolderPatch]. This is the recommended way to modify
+        /// a folder.
+        ///
+        /// Notes
+        ///  - An empty patch sends nothing and returns `None`
+        ///  - If the folder changed on the server since the patch was created, this fails with
+        ///    [Conflict](crate::Error::Conflict)
+        pub async fn apply_patch(&self, patch: Patch) -> Result<Option<Identifier>, Error>;
+
+        Summaries: FolderSummary;
+        /// List all the folders like [list](FolderApi::list), but only read the fields of
+        /// [FolderSummary]
+        pub async fn list_summaries(&self) -> Result<Vec<Summary>, Error>;
+
+        Trash;
+        /// List the folders in the trash, that [list](FolderApi::list) leaves out
+        pub async fn trashed(&self, details: Option<Details>) -> Result<Vec<Type>, Error>;
     }
 }
 
+/// The fields of a folder needed to display it in a tree, see
+/// [list_summaries](FolderApi::list_summaries)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FolderSummary {
+    pub id: uuid::Uuid,
+    pub revision: uuid::Uuid,
+    pub label: String,
+    pub parent: uuid::Uuid,
+    pub favorite: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct FolderIdentifier {
     pub id: uuid::Uuid,
@@ -107,6 +141,13 @@ create_details! {
     }
 }
 
+/// Maximum number of ancestors of a folder followed by [ancestors](FolderApi::ancestors)
+pub const MAX_DEPTH: usize = 256;
+
+/// The id of the base folder, the root of the folders and the default folder of the passwords.
+/// It can be fetched with [get](FolderApi::get) like any folder, see [base](FolderApi::base).
+pub const BASE_FOLDER: uuid::Uuid = uuid::Uuid::from_bytes([0; 16]);
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(untagged)]
 pub enum ParentInfoKind {
@@ -122,21 +163,35 @@ impl ParentInfo {
     pub fn get(&self) -> &ParentInfoKind {
         &self.0
     }
+    /// Reference the parent by its id, this is also the value to use in a
+    /// [FolderSearch::and_parent] query
     pub fn new(id: uuid::Uuid) -> Self {
         Self(ParentInfoKind::Id(id))
     }
+    /// The id of the parent
+    pub fn id(&self) -> uuid::Uuid {
+        match &self.0 {
+            ParentInfoKind::Id(id) => *id,
+            ParentInfoKind::Data(folder) => folder.id,
+        }
+    }
+    /// Whether the parent is the [base folder](BASE_FOLDER)
+    pub fn is_base(&self) -> bool {
+        self.id() == BASE_FOLDER
+    }
 }
 
 
 create_binding! {
 #[derive(Serialize, Deserialize, Debug)]
-pub struct Folder {
+pub struct Folder [extra] {
     pub id: uuid::Uuid [update(required) versioned(false)],
     pub label: String [versioned(true) create(required) update(required)],
+    /// The parent folder, the [base folder](BASE_FOLDER) for the top level folders
     pub parent: ParentInfo [versioned(true) create(optional) update(optional) search],
-    pub created: u64 [search versioned(false)],
-    pub updated: u64 [versioned(true) search],
-    pub edited: u64 [versioned(true) update(optional)],
+    pub created: crate::UnixTime [search versioned(false)],
+    pub updated: crate::UnixTime [versioned(true) search],
+    pub edited: crate::UnixTime [versioned(true) update(optional) search],
     pub revision: uuid::Uuid [versioned(true)],
     #[serde(rename = "cseType")]
     pub cse_type: String [versioned(true) create(optional) update(optional) search],
@@ -154,3 +209,346 @@ pub struct Folder {
     pub passwords: Option<Vec<crate::password::Password>> [versioned(false)],
 }
 }
+
+/// Maximum length of the label of a folder
+pub const MAX_LABEL_LENGTH: usize = 64;
+
+impl crate::Validate for CreateFolder {
+    fn validate(&self) -> Result<(), crate::ValidationError> {
+        crate::utils::check_length("label", &self.label, MAX_LABEL_LENGTH)
+    }
+
+    fn cse_type(&self) -> Option<&str> {
+        self.cse_type.as_deref()
+    }
+}
+
+impl crate::Validate for UpdateFolder {
+    fn validate(&self) -> Result<(), crate::ValidationError> {
+        crate::utils::check_length("label", &self.label, MAX_LABEL_LENGTH)
+    }
+
+    fn cse_type(&self) -> Option<&str> {
+        self.cse_type.as_deref()
+    }
+}
+
+/// An update that keeps all the current values of the folder
+pub(crate) fn update_from(folder: &Folder) -> UpdateFolder {
+    let versioned = &folder.versioned;
+    UpdateFolder::new(folder.id, versioned.label.clone())
+        .parent(ParentInfo::new(versioned.parent.id()))
+        .edited(versioned.edited)
+        .cse_type(versioned.cse_type.clone())
+        .cse_key(versioned.cse_key.clone())
+        .hidden(versioned.hidden)
+        .favorite(versioned.favorite)
+        .extra(folder.extra.clone())
+}
+
+/// Keep all the current values of the folder
+impl From<Folder> for UpdateFolder {
+    fn from(folder: Folder) -> Self {
+        update_from(&folder)
+    }
+}
+
+impl FolderPatch {
+    /// Start a patch of this folder. Applying the patch fails if the folder was modified on the
+    /// server in the meantime.
+    pub fn new(original: &Folder) -> Self {
+        Self::from_update(update_from(original).expected_revision(original.versioned.revision))
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.update.label = label.into();
+        self.mark("label");
+        self
+    }
+}
+
+impl<'a> FolderApi<'a> {
+    /// Get the base folder, which is not included in [list](Self::list). Its content can be
+    /// fetched with [Details::folders] and [Details::passwords].
+    pub async fn base(&self, details: Option<Details>) -> Result<Folder, Error> {
+        self.get(details, BASE_FOLDER).await
+    }
+
+    /// Move the folder into another folder, keeping its other properties
+    ///
+    /// Notes
+    ///  - The new parent is fetched first, to fail if it does not exist instead of letting the
+    ///    server move the folder to the base folder
+    ///  - A folder that is not hidden can't be moved to a hidden folder, the server would move it
+    ///    to the base folder instead. This fails with [Error::HiddenFolder].
+    pub async fn move_to(
+        &self,
+        folder_id: uuid::Uuid,
+        new_parent: uuid::Uuid,
+    ) -> Result<FolderIdentifier, Error> {
+        let parent = self.get(None, new_parent).await?;
+        let folder = self.get(None, folder_id).await?;
+        if parent.versioned.hidden && !folder.versioned.hidden {
+            return Err(Error::HiddenFolder {
+                object: folder_id,
+                folder: new_parent,
+            });
+        }
+        self.update(update_from(&folder).parent(ParentInfo::new(new_parent)))
+            .await
+    }
+
+    /// What the web interface shows in the folder: its folders and passwords that are neither
+    /// trashed nor hidden, sorted by label. Use [BASE_FOLDER] for the top level.
+    pub async fn contents(&self, id: uuid::Uuid) -> Result<FolderContents, Error> {
+        let details = Details::new().folders().passwords();
+        let folder = self.get(Some(details), id).await?;
+        let mut subfolders: Vec<_> = folder
+            .folders
+            .into_iter()
+            .flatten()
+            .filter(|folder| !folder.versioned.trashed && !folder.versioned.hidden)
+            .collect();
+        subfolders.sort_by(|a, b| a.versioned.label.cmp(&b.versioned.label));
+        let mut passwords: Vec<_> = folder
+            .passwords
+            .into_iter()
+            .flatten()
+            .filter(|password| !password.versioned.trashed && !password.versioned.hidden)
+            .collect();
+        passwords.sort_by(|a, b| a.versioned.label.cmp(&b.versioned.label));
+        Ok(FolderContents {
+            subfolders,
+            passwords,
+        })
+    }
+
+    /// The folders containing this folder for a breadcrumb, from the base folder to its parent.
+    /// This is empty for the base folder.
+    ///
+    /// Notes
+    ///  - The folders are fetched with their parent, so a request gives two ancestors
+    ///  - If the parents form a cycle, or there are more than [MAX_DEPTH] ancestors, this fails
+    ///    with [FolderCycle](crate::Error::FolderCycle)
+    pub async fn ancestors(&self, id: uuid::Uuid) -> Result<Vec<Folder>, Error> {
+        let details = || Some(Details::new().parent());
+        let mut ancestors = Vec::new();
+        if id == BASE_FOLDER {
+            return Ok(ancestors);
+        }
+        let folder = self.get(details(), id).await?;
+        let mut visited = std::collections::HashSet::new();
+        visited.insert(id);
+        let mut next = folder.versioned.parent.0;
+        loop {
+            let parent_id = match &next {
+                ParentInfoKind::Id(id) => *id,
+                ParentInfoKind::Data(folder) => folder.id,
+            };
+            if !visited.insert(parent_id) || ancestors.len() >= MAX_DEPTH {
+                let mut folders: Vec<_> = visited.into_iter().collect();
+                folders.sort();
+                return Err(Error::FolderCycle { folders });
+            }
+            let mut parent = match next {
+                ParentInfoKind::Data(parent) => *parent,
+                ParentInfoKind::Id(id) => self.get(details(), id).await?,
+            };
+            // Keep only the id in the returned folder, its parent is the previous ancestor
+            let grandparent = ParentInfo::new(parent.versioned.parent.id());
+            next = std::mem::replace(&mut parent.versioned.parent, grandparent).0;
+            let is_base = parent.id == BASE_FOLDER;
+            ancestors.push(parent);
+            if is_base {
+                break;
+            }
+        }
+        ancestors.reverse();
+        Ok(ancestors)
+    }
+
+    /// Find the folder at this path of labels, like `Work/Infra/AWS`, starting from the base
+    /// folder. The labels are compared case-sensitively, and an empty path is the base folder.
+    ///
+    /// Notes
+    ///  - This lists all the folders, the hidden and trashed ones are not found
+    ///  - If several sibling folders match a segment, this fails with
+    ///    [AmbiguousPath](crate::Error::AmbiguousPath)
+    pub async fn get_by_path(&self, path: &str) -> Result<Option<Folder>, Error> {
+        let folders = self.list(None).await?;
+        let mut parent = BASE_FOLDER;
+        for label in path_segments(path) {
+            match find_child(&folders, parent, label, path)? {
+                Some(folder) => parent = folder.id,
+                None => return Ok(None),
+            }
+        }
+        match folders.into_iter().find(|folder| folder.id == parent) {
+            Some(folder) => Ok(Some(folder)),
+            // The base folder is not listed
+            None => self.get(None, parent).await.map(Some),
+        }
+    }
+
+    /// Create the missing folders of the path, like `mkdir -p`, and return the last one. The
+    /// existing folders are reused, see [get_by_path](Self::get_by_path).
+    ///
+    /// Notes
+    ///  - A hidden folder is not found, a visible folder with the same label is created next to
+    ///    it
+    pub async fn create_path(&self, path: &str) -> Result<FolderIdentifier, Error> {
+        let folders = self.list(None).await?;
+        let mut current: Option<FolderIdentifier> = None;
+        let mut creating = false;
+        for label in path_segments(path) {
+            let parent = current.as_ref().map_or(BASE_FOLDER, |folder| folder.id);
+            let existing = if creating {
+                None
+            } else {
+                find_child(&folders, parent, label, path)?
+            };
+            current = Some(match existing {
+                Some(folder) => FolderIdentifier {
+                    id: folder.id,
+                    revision: folder.versioned.revision,
+                },
+                None => {
+                    creating = true;
+                    let folder =
+                        CreateFolder::new(label.to_string()).parent(ParentInfo::new(parent));
+                    self.create(folder).await?
+                }
+            });
+        }
+        match current {
+            Some(folder) => Ok(folder),
+            None => {
+                let base = self.base(None).await?;
+                Ok(FolderIdentifier {
+                    id: base.id,
+                    revision: base.versioned.revision,
+                })
+            }
+        }
+    }
+}
+
+/// The content of a folder, see [contents](FolderApi::contents)
+#[derive(Debug)]
+pub struct FolderContents {
+    pub subfolders: Vec<Folder>,
+    pub passwords: Vec<crate::password::Password>,
+}
+
+/// The labels of a path of folders, the empty segments are ignored
+fn path_segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// The child of `parent` with this label, failing if there are several
+fn find_child<'f>(
+    folders: &'f [Folder],
+    parent: uuid::Uuid,
+    label: &str,
+    path: &str,
+) -> Result<Option<&'f Folder>, Error> {
+    let matching: Vec<_> = folders
+        .iter()
+        .filter(|folder| folder.versioned.parent.id() == parent && folder.versioned.label == label)
+        .collect();
+    match matching.as_slice() {
+        [] => Ok(None),
+        [folder] => Ok(Some(folder)),
+        _ => Err(Error::AmbiguousPath {
+            path: path.into(),
+            label: label.into(),
+            folders: matching.iter().map(|folder| folder.id).collect(),
+        }),
+    }
+}
+
+impl crate::utils::Revisioned for Folder {
+    fn current_revision(&self) -> uuid::Uuid {
+        self.versioned.revision
+    }
+}
+
+impl crate::Sortable for Folder {
+    fn label(&self) -> &str {
+        &self.versioned.label
+    }
+
+    fn updated(&self) -> crate::UnixTime {
+        self.versioned.updated
+    }
+
+    fn created(&self) -> crate::UnixTime {
+        self.created
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{QueryKind, SearchQuery};
+    use serde_json::json;
+
+    #[test]
+    fn into_update() {
+        let json = json!({
+            "id": "8ed3e9b2-5a49-43c9-8d7e-5b1f8e1fd5d4",
+            "label": "Work",
+            "parent": "00000000-0000-0000-0000-000000000000",
+            "created": 1_600_000_000,
+            "updated": 1_600_000_000,
+            "edited": 1_500_000_000,
+            "revision": "4a0a9fbf-6ca5-4d3f-a2bb-0a9f1d0e1c55",
+            "cseType": "none",
+            "cseKey": "7d0e1c55-4a0a-4d3f-a2bb-0a9f1d0e1c55",
+            "sseType": "SSEv1r2",
+            "client": "CLIENT::UNKNOWN",
+            "hidden": true,
+            "trashed": false,
+            "favorite": true,
+            "otherApp": {"key": "value"},
+        });
+        let folder: Folder = serde_json::from_value(json.clone()).unwrap();
+        assert!(folder.versioned.parent.is_base());
+        let update = serde_json::to_value(UpdateFolder::from(folder)).unwrap();
+        for field in &[
+            "id", "label", "parent", "edited", "cseType", "cseKey", "hidden", "favorite",
+            "otherApp",
+        ] {
+            assert_eq!(update[field], json[field], "{}", field);
+        }
+    }
+
+    #[test]
+    fn search_body() {
+        let parent = uuid::Uuid::parse_str("8ed3e9b2-5a49-43c9-8d7e-5b1f8e1fd5d4").unwrap();
+        let search = FolderSearch::new()
+            .and_parent(SearchQuery::new(ParentInfo::new(parent), QueryKind::Exact))
+            .unwrap()
+            .and_created(SearchQuery::new(
+                crate::utils::unix_time(10),
+                QueryKind::LessOrEqual,
+            ))
+            .unwrap()
+            .and_trashed(SearchQuery::new(false, QueryKind::Exact))
+            .unwrap()
+            .and_cse_type(SearchQuery::new("none".into(), QueryKind::NotEqual))
+            .unwrap()
+            .and_edited(SearchQuery::at_least(crate::utils::unix_time(20)))
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&search).unwrap(),
+            json!({
+                "parent": "8ed3e9b2-5a49-43c9-8d7e-5b1f8e1fd5d4",
+                "created": ["le", 10],
+                "edited": ["ge", 20],
+                "trashed": false,
+                "cseType": ["ne", "none"],
+            })
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! The identifiers returned by the API can be used directly where an id is expected: they
+//! convert to [Uuid] and implement [HasId].
+
+pub use crate::folder::FolderIdentifier;
+pub use crate::password::PasswordIdentifier;
+pub use crate::tag::TagIdentifier;
+use uuid::Uuid;
+
+/// Something that identifies an object of the API
+pub trait HasId {
+    fn id(&self) -> Uuid;
+}
+
+impl HasId for Uuid {
+    fn id(&self) -> Uuid {
+        *self
+    }
+}
+
+macro_rules! identifies {
+    ($($object:ty),* $(,)?) => {
+        $(
+            impl HasId for $object {
+                fn id(&self) -> Uuid {
+                    self.id
+                }
+            }
+
+            impl AsRef<Uuid> for $object {
+                fn as_ref(&self) -> &Uuid {
+                    &self.id
+                }
+            }
+
+            impl From<&$object> for Uuid {
+                fn from(object: &$object) -> Uuid {
+                    object.id
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! identifiers {
+    ($($identifier:ty),* $(,)?) => {
+        identifies!($($identifier),*);
+        $(
+            impl From<$identifier> for Uuid {
+                fn from(identifier: $identifier) -> Uuid {
+                    identifier.id
+                }
+            }
+        )*
+    };
+}
+
+identifies! {
+    crate::password::Password,
+    crate::password::PasswordSummary,
+    crate::folder::Folder,
+    crate::folder::FolderSummary,
+    crate::tag::TagSummary,
+    crate::share::Share,
+}
+
+identifiers! {
+    PasswordIdentifier,
+    crate::password::TrashedIdentifier,
+    FolderIdentifier,
+    crate::folder::TrashedIdentifier,
+    TagIdentifier,
+    crate::tag::TrashedIdentifier,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn takes_id(id: impl Into<Uuid>) -> Uuid {
+        id.into()
+    }
+
+    #[test]
+    fn conversions() {
+        let id = Uuid::parse_str("a2e0e74b-2ee1-4b4c-9f8b-a3d8b0bb2d27").unwrap();
+        let identifier = PasswordIdentifier {
+            id,
+            revision: Uuid::nil(),
+        };
+        assert_eq!(identifier.id(), id);
+        assert_eq!(identifier.as_ref(), &id);
+        assert_eq!(takes_id(&identifier), id);
+        assert_eq!(takes_id(identifier), id);
+
+        let trashed = crate::folder::TrashedIdentifier { id, revision: None };
+        assert_eq!(takes_id(trashed), id);
+
+        let password: crate::password::Password =
+            serde_json::from_value(crate::password::tests::password_json()).unwrap();
+        assert_eq!(takes_id(&password), password.id);
+    }
+}
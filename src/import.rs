@@ -0,0 +1,73 @@
+use crate::{
+    export::{FolderNode, FolderTemplate},
+    folder::{CreateFolder, ParentInfo, BASE_FOLDER},
+    AuthenticatedApi, Error,
+};
+use std::collections::HashMap;
+
+/// What to do when a folder of the template already exists with the same label in the same parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyMode {
+    /// Use the existing folder (and apply the children of the node in it)
+    Reuse,
+    /// Always create a new folder
+    Duplicate,
+}
+
+/// The folder used for a node of the template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedFolder {
+    /// Labels from the root of the template to the node
+    pub path: Vec<String>,
+    pub id: uuid::Uuid,
+    /// false if an existing folder was reused
+    pub created: bool,
+}
+
+/// Create the folders of the template in the base folder. The result has one entry per node of
+/// the template, parents before their children.
+///
+/// Notes
+///  - Hidden folders are not listed by the server, so they can't be reused
+pub async fn apply_folder_template(
+    api: &AuthenticatedApi,
+    template: &FolderTemplate,
+    mode: ApplyMode,
+) -> Result<Vec<AppliedFolder>, Error> {
+    let mut existing = HashMap::new();
+    if mode == ApplyMode::Reuse {
+        for folder in api.folder().list(None).await? {
+            existing
+                .entry((folder.versioned.parent.id(), folder.versioned.label))
+                .or_insert(folder.id);
+        }
+    }
+
+    let mut applied = Vec::new();
+    let mut stack: Vec<(&FolderNode, uuid::Uuid, Vec<String>)> = template
+        .folders
+        .iter()
+        .rev()
+        .map(|node| (node, BASE_FOLDER, Vec::new()))
+        .collect();
+    while let Some((node, parent, mut path)) = stack.pop() {
+        path.push(node.label.clone());
+        let (id, created) = match existing.get(&(parent, node.label.clone())) {
+            Some(&id) if mode == ApplyMode::Reuse => (id, false),
+            _ => {
+                let create = CreateFolder::new(node.label.clone())
+                    .parent(ParentInfo::new(parent))
+                    .favorite(node.favorite)
+                    .hidden(node.hidden);
+                let id = api.folder().create(create).await?.id;
+                existing.insert((parent, node.label.clone()), id);
+                (id, true)
+            }
+        };
+        for child in node.children.iter().rev() {
+            stack.push((child, id, path.clone()));
+        }
+        applied.push(AppliedFolder { path, id, created });
+    }
+    Ok(applied)
+}
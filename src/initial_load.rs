@@ -0,0 +1,154 @@
+//! Fetch what most applications need when they start, see
+//! [initial_load](crate::AuthenticatedApi::initial_load)
+
+use crate::{
+    folder::Folder,
+    password::{Password, PasswordSearch},
+    settings::AllSettings,
+    tag::Tag,
+    AuthenticatedApi, Error, QueryKind, SearchQuery,
+};
+
+/// The parts fetched by [initial_load](AuthenticatedApi::initial_load), all of them with
+/// [all](InitialLoadOptions::all)
+#[derive(Default, Debug, Clone, Copy)]
+pub struct InitialLoadOptions {
+    pub settings: bool,
+    pub passwords: bool,
+    pub folders: bool,
+    pub tags: bool,
+    pub favorites: bool,
+}
+
+impl InitialLoadOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn all() -> Self {
+        InitialLoadOptions {
+            settings: true,
+            passwords: true,
+            folders: true,
+            tags: true,
+            favorites: true,
+        }
+    }
+
+    pub fn settings(self) -> Self {
+        Self {
+            settings: true,
+            ..self
+        }
+    }
+
+    pub fn passwords(self) -> Self {
+        Self {
+            passwords: true,
+            ..self
+        }
+    }
+
+    pub fn folders(self) -> Self {
+        Self {
+            folders: true,
+            ..self
+        }
+    }
+
+    pub fn tags(self) -> Self {
+        Self { tags: true, ..self }
+    }
+
+    pub fn favorites(self) -> Self {
+        Self {
+            favorites: true,
+            ..self
+        }
+    }
+}
+
+/// A part of [InitialData]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitialPart {
+    Settings,
+    Passwords,
+    Folders,
+    Tags,
+    Favorites,
+}
+
+/// Result of [initial_load](AuthenticatedApi::initial_load). A part is `None` if it was not
+/// requested or could not be fetched, the errors of the parts are in
+/// [errors](InitialData::errors).
+#[derive(Debug, Default)]
+pub struct InitialData {
+    pub settings: Option<AllSettings>,
+    pub passwords: Option<Vec<Password>>,
+    pub folders: Option<Vec<Folder>>,
+    pub tags: Option<Vec<Tag>>,
+    /// The passwords marked as favorite
+    pub favorites: Option<Vec<Password>>,
+    pub errors: Vec<(InitialPart, Error)>,
+}
+
+impl InitialData {
+    fn record<T>(&mut self, part: InitialPart, result: Option<Result<T, Error>>) -> Option<T> {
+        match result? {
+            Ok(value) => Some(value),
+            Err(e) => {
+                log::warn!("Could not load the {:?} part: {}", part, e);
+                self.errors.push((part, e));
+                None
+            }
+        }
+    }
+}
+
+/// Run `fetch` only if the part was requested
+async fn optional<T, F>(requested: bool, fetch: F) -> Option<Result<T, Error>>
+where
+    F: std::future::Future<Output = Result<T, Error>>,
+{
+    match requested {
+        true => Some(fetch.await),
+        false => None,
+    }
+}
+
+pub(crate) async fn load(
+    api: &AuthenticatedApi,
+    options: InitialLoadOptions,
+) -> Result<InitialData, Error> {
+    let (settings_api, password_api, folder_api, tag_api) =
+        (api.settings(), api.password(), api.folder(), api.tag());
+    let favorites = async {
+        let favorite =
+            PasswordSearch::new().and_favorite(SearchQuery::new(true, QueryKind::Equals))?;
+        password_api.find(favorite, None).await
+    };
+    let (settings, passwords, folders, tags, favorites) = futures::join!(
+        optional(options.settings, settings_api.get_all()),
+        optional(options.passwords, password_api.list(None)),
+        optional(options.folders, folder_api.list(None)),
+        optional(options.tags, tag_api.list(None)),
+        optional(options.favorites, favorites),
+    );
+
+    let mut data = InitialData::default();
+    data.settings = data.record(InitialPart::Settings, settings);
+    data.passwords = data.record(InitialPart::Passwords, passwords);
+    data.folders = data.record(InitialPart::Folders, folders);
+    data.tags = data.record(InitialPart::Tags, tags);
+    data.favorites = data.record(InitialPart::Favorites, favorites);
+
+    let loaded = data.settings.is_some()
+        || data.passwords.is_some()
+        || data.folders.is_some()
+        || data.tags.is_some()
+        || data.favorites.is_some();
+    if !loaded && !data.errors.is_empty() {
+        return Err(data.errors.remove(0).1);
+    }
+    Ok(data)
+}
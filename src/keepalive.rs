@@ -0,0 +1,80 @@
+use crate::{AuthenticatedApi, Error};
+use futures::channel::oneshot;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Time between the keepalive request and the end of the session
+const MARGIN: u64 = 30;
+
+/// A task calling [keep_alive](AuthenticatedApi::keep_alive) in background, started by
+/// [spawn_keepalive](AuthenticatedApi::spawn_keepalive). The task stops when the handle is
+/// dropped, or after the first error.
+pub struct KeepaliveHandle {
+    _stop: oneshot::Sender<()>,
+    error: oneshot::Receiver<Error>,
+}
+
+impl KeepaliveHandle {
+    /// Wait for the task to fail, the session is probably lost and must be opened again. `None`
+    /// if the task stopped without error, when the runtime running it shut down.
+    pub async fn failed(&mut self) -> Option<Error> {
+        match (&mut self.error).await {
+            Ok(error) => Some(error),
+            Err(oneshot::Canceled) => {
+                log::debug!("Keepalive task stopped without error");
+                None
+            }
+        }
+    }
+
+    /// The error of the task if it failed
+    pub fn try_failed(&mut self) -> Option<Error> {
+        self.error.try_recv().ok().flatten()
+    }
+}
+
+/// The interval between the keepalive requests for a session lasting `lifetime` seconds
+fn interval(lifetime: u64) -> Duration {
+    let seconds = if lifetime > 2 * MARGIN {
+        lifetime - MARGIN
+    } else {
+        lifetime / 2
+    };
+    Duration::from_secs(seconds.max(1))
+}
+
+pub(crate) fn spawn(api: Arc<AuthenticatedApi>) -> KeepaliveHandle {
+    let (stop, mut stopped) = oneshot::channel();
+    let (report, error) = oneshot::channel();
+    let interval = interval(api.keepalive);
+    crate::runtime::spawn(async move {
+        loop {
+            let tick = Box::pin(crate::runtime::sleep(interval));
+            if let futures::future::Either::Right(_) =
+                futures::future::select(tick, &mut stopped).await
+            {
+                log::debug!("Keepalive handle dropped, stopping");
+                return;
+            }
+            if let Err(e) = api.keep_alive().await {
+                log::warn!("Keepalive failed: {}", e);
+                // The handle may have been dropped in the meantime
+                let _ = report.send(e);
+                return;
+            }
+        }
+    });
+    KeepaliveHandle { _stop: stop, error }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals() {
+        assert_eq!(interval(600), Duration::from_secs(570));
+        assert_eq!(interval(60), Duration::from_secs(30));
+        assert_eq!(interval(0), Duration::from_secs(1));
+    }
+}
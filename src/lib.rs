@@ -1,11 +1,37 @@
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 pub use url::Url;
+use zeroize::Zeroizing;
 
+/// Management of several accounts at once, see [AccountManager](accounts::AccountManager)
+pub mod accounts;
+/// Record of the requests made to the server, see
+/// [enable_capture](AuthenticatedApi::enable_capture)
+pub mod capture;
+/// Client side encryption, see [Keychain](cse::Keychain)
+pub mod cse;
+/// Report of [diagnose](AuthenticatedApi::diagnose), to find out why a connection fails
+pub mod diagnostics;
+/// Export of data from an account, see [folder_template](export::folder_template)
+pub mod export;
+/// Client side filters on passwords
+pub mod filter;
 /// Data types to interract with the folder API. Check [FolderApi](folder::FolderApi) for the
 /// available actions. You can also check the [HTTP
 /// API](https://git.mdns.eu/nextcloud/passwords/wikis/Developers/Api/Folder-Api)
 pub mod folder;
+/// Conversions between the identifiers and ids, see [HasId](identifiers::HasId)
+pub mod identifiers;
+/// Import of data in an account, see [apply_folder_template](import::apply_folder_template)
+pub mod import;
+/// Fetch the data needed at startup in one call, see
+/// [initial_load](AuthenticatedApi::initial_load)
+pub mod initial_load;
+/// Background keepalive of a session, see
+/// [spawn_keepalive](AuthenticatedApi::spawn_keepalive)
+pub mod keepalive;
+/// The login flow v2 driven by the application, see [LoginFlow2](login_flow::LoginFlow2)
+pub mod login_flow;
 /// Data types and builders to interact with the passwords API. Check
 /// [PasswordApi](password::PasswordApi) for the available actions. You can also check the [HTTP
 /// API](https://git.mdns.eu/nextcloud/passwords/wikis/Developers/Api/Password-Api)
@@ -32,10 +58,30 @@ pub mod tag;
 /// API](https://git.mdns.eu/nextcloud/passwords/wikis/Developers/Api/Token-Api)
 pub mod token;
 
+/// The async runtime used for the timers and the background tasks, tokio by default, see
+/// [set_runtime](runtime::set_runtime)
+pub mod runtime;
+/// Local servers answering the requests of the client, to test code using this crate without a
+/// Nextcloud instance. The [fake](testing::fake::FakeServer) server follows the rules of the
+/// API, other servers can be written by implementing [Handler](testing::Handler). Requires the
+/// `testing` feature.
+#[cfg(feature = "testing")]
+pub mod testing;
+/// The hierarchy of the folders and their passwords, see [FolderTree](tree::FolderTree)
+pub mod tree;
+
 // TODO: sort the session required methods from the non-session required
 
+mod challenge;
+mod cookies;
+mod sha1;
+mod throttle;
 mod utils;
-pub use utils::{QueryKind, SearchQuery};
+pub use filter::{Filter, UrlMatch};
+pub use utils::{
+    paginate, sort, BatchResult, Detailed, EmptyTrashReport, ItemError, Order, Page, Purged,
+    QueryKind, SearchQuery, SortBy, Sortable, Timestamp, UnixTime, Validate, ValidationError,
+};
 
 mod private {
     pub trait Sealed {}
@@ -45,7 +91,7 @@ mod private {
     impl Sealed for super::settings::ClientSettings {}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Color {
     pub red: u8,
     pub green: u8,
@@ -113,6 +159,31 @@ impl<'de> Deserialize<'de> for Color {
     }
 }
 
+/// The state on the server of an object that was modified concurrently, see
+/// [Conflict](Error::Conflict)
+#[derive(Debug)]
+pub enum Conflicting {
+    Password(Box<password::Password>),
+    Folder(Box<folder::Folder>),
+    Tag(Box<tag::Tag>),
+}
+
+impl From<password::Password> for Conflicting {
+    fn from(password: password::Password) -> Self {
+        Conflicting::Password(Box::new(password))
+    }
+}
+impl From<folder::Folder> for Conflicting {
+    fn from(folder: folder::Folder) -> Self {
+        Conflicting::Folder(Box::new(folder))
+    }
+}
+impl From<tag::Tag> for Conflicting {
+    fn from(tag: tag::Tag) -> Self {
+        Conflicting::Tag(Box::new(tag))
+    }
+}
+
 /// Errors
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -126,19 +197,379 @@ pub enum Error {
     TimeError(#[from] std::time::SystemTimeError),
     #[error("setting was not valid in this context")]
     InvalidSetting,
+    /// The name or the serialized value of a client setting exceeds the limits of the server
+    #[error("{name} is too large for a client setting: {length} characters, at most {limit}")]
+    SettingTooLarge {
+        name: String,
+        length: usize,
+        limit: usize,
+    },
+    /// The server did not answer the setting, or answered null, for example a setting added by a
+    /// later version of the app
+    #[error("the server did not provide the setting {0}")]
+    MissingSetting(String),
     #[error("serde error")]
     Serde(#[from] serde_json::Error),
     #[error("endpoint error: {}", .0.message)]
     EndpointError(EndpointError),
     #[error("error in the login flow: request returned {0}")]
     LoginFlowError(u16),
+    #[error("the access was not granted before the end of the login flow")]
+    LoginFlowTimedOut,
+    #[error("could not obtain an app password: request returned {0}")]
+    AppPasswordError(u16),
+    /// See [from_nc_login_url](LoginDetails::from_nc_login_url)
+    #[error("invalid nc://login URL: {0}")]
+    InvalidLoginUrl(String),
+    /// See [CustomFields::validate](password::CustomFields::validate)
+    #[error("the custom fields exceed the server limits: {count} fields, {length} characters")]
+    CustomFieldsTooLarge { count: usize, length: usize },
+    #[error("invalid value: {0}")]
+    Validation(#[from] ValidationError),
+    /// The string parsed into a `Details` has an unknown segment
+    #[error("unknown details `{segment}`, expected `model` followed by any of {allowed}")]
+    InvalidDetails {
+        segment: String,
+        /// The allowed segments, separated by `+`
+        allowed: &'static str,
+    },
+    /// See [destroy_app_password](AuthenticatedApi::destroy_app_password)
+    #[error("could not delete the app password: request returned {0}")]
+    AppPasswordNotRevoked(u16),
+    /// The response is not the one expected from the endpoint
+    #[error("could not read the response of {endpoint} (status {status})")]
+    UnexpectedResponse {
+        endpoint: String,
+        /// The HTTP status of the response
+        status: u16,
+        /// The body of the response, or the element that could not be read in a list, with the
+        /// secrets [redacted](capture::redact)
+        body: String,
+        source: serde_json::Error,
+        /// The id of the [capture](capture::Capture) of the exchange if the capture is enabled
+        capture: Option<u64>,
+    },
+    #[error("share type {requested} is not supported, the server supports {supported:?}")]
+    UnsupportedShareType {
+        requested: share::ShareType,
+        supported: Vec<share::ShareType>,
+    },
+    #[error("the object was modified on the server since the expected revision")]
+    Conflict { current: Conflicting },
+    #[error("revision {revision} of {id} does not exist")]
+    RevisionNotFound {
+        id: uuid::Uuid,
+        revision: uuid::Uuid,
+    },
+    /// Several sibling folders have the label of a segment of the path, see
+    /// [get_by_path](folder::FolderApi::get_by_path)
+    #[error("{path} is ambiguous, {} folders are named {label}", .folders.len())]
+    AmbiguousPath {
+        path: String,
+        label: String,
+        /// The ids of the folders having the label
+        folders: Vec<uuid::Uuid>,
+    },
+    /// The parents of these folders form a cycle, see [FolderTree::build](tree::FolderTree::build)
+    /// and [ancestors](folder::FolderApi::ancestors)
+    #[error("the parents of the folders {folders:?} form a cycle")]
+    FolderCycle { folders: Vec<uuid::Uuid> },
+    #[error("the passwords API was not found at {url} (opening a session returned {status})")]
+    InvalidApiBase { url: Url, status: u16 },
+    /// An idempotent request was not answered in time, see
+    /// [set_timeout](AuthenticatedApi::set_timeout). The other requests fail with
+    /// [Interrupted](Error::Interrupted) as they may have been applied.
+    #[error("{endpoint} was not answered in time")]
+    Timeout { endpoint: String },
+    #[error(
+        "the connection failed during {endpoint}, the operation may or may not have been applied"
+    )]
+    Interrupted {
+        endpoint: String,
+        source: reqwest::Error,
+    },
+    /// The request failed after all the attempts of the [RetryPolicy]
+    #[error("{endpoint} failed after {attempts} attempts")]
+    RetriesExhausted {
+        endpoint: String,
+        attempts: u32,
+        source: Box<Error>,
+    },
+    #[error("the response of {endpoint} is {exceeded}")]
+    ResponseTooLarge {
+        endpoint: String,
+        exceeded: LimitExceeded,
+    },
+    #[error("the server is throttling logins, retry in {}s", .retry_in.as_secs())]
+    Throttled {
+        /// How long the server delayed the response, `None` if the session open was refused
+        /// without contacting the server because of a previous throttling
+        delay: Option<std::time::Duration>,
+        /// Opening a session on this server is refused until then
+        retry_in: std::time::Duration,
+    },
+    #[error("the account is protected by a master password, but none was given")]
+    MasterPasswordRequired,
+    /// The server did not answer a session id, it is probably not a Nextcloud server with the
+    /// passwords app, or a proxy dropped the header
+    #[error("the server did not return a valid X-API-SESSION header")]
+    MissingSessionHeader,
+    /// The server answered a page of Nextcloud instead of the passwords API
+    #[error("the passwords app is not installed on the server (request returned {status})")]
+    AppNotInstalled { status: u16 },
+    /// The server refuses more requests to this endpoint for now, like
+    /// [partners](share::ShareApi::partners) limited to 45 requests per minute
+    #[error("too many requests, retry in {}s", .retry_after.as_secs())]
+    RateLimited { retry_after: std::time::Duration },
+    #[error("the server is in maintenance mode (request returned {status})")]
+    MaintenanceMode { status: u16 },
+    #[error("the server refused the master password")]
+    ChallengeFailed,
+    #[error("the server requested an unsupported challenge: {0}")]
+    UnsupportedChallenge(String),
+    /// The version of the passwords app is too old for a feature, see
+    /// [capabilities](AuthenticatedApi::capabilities)
+    #[error("{feature} requires the passwords app {required}, the server has {actual}")]
+    UnsupportedServer {
+        feature: String,
+        required: settings::Version,
+        actual: settings::Version,
+    },
+    #[error("{0} is not a valid version")]
+    InvalidVersion(String),
+    /// The account requires a second factor, open the session with
+    /// [new_session_with_token](AuthenticatedApi::new_session_with_token)
+    #[error("the account requires a second factor token")]
+    TokenRequired(Vec<token::ProviderInfo>),
+    #[error("the server refused the second factor token")]
+    TokenRejected,
+    #[error("the server could not request a token from {0}")]
+    TokenRequestFailed(token::TokenProvider),
+    #[error("could not decrypt: {0}")]
+    Decryption(#[from] cse::DecryptError),
+    /// A task run out of the threads of the runtime, like the key derivation of the master
+    /// password, panicked or was cancelled
+    #[error("a background task failed: {0}")]
+    TaskFailed(String),
+    /// The [ResumeState] was resumed without its secret, see
+    /// [take_secret](ResumeState::take_secret)
+    #[error("the app password was taken out of the resume state and not given back")]
+    MissingSecret,
+    /// The server moves an object that is not hidden to the base folder instead of a hidden
+    /// folder, see [move_to_folder](password::PasswordApi::move_to_folder)
+    #[error("{object} is not hidden and can not be moved to the hidden folder {folder}")]
+    HiddenFolder {
+        object: uuid::Uuid,
+        folder: uuid::Uuid,
+    },
 }
 
+/// An error answered by the passwords API
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EndpointError {
-    status: String,
-    id: u64,
-    message: String,
+    /// Always `"error"`
+    pub status: String,
+    /// The id of the error, the HTTP status for the generic errors
+    pub id: u64,
+    pub message: String,
+    /// The HTTP status of the response
+    #[serde(skip)]
+    pub http_status: Option<u16>,
+}
+
+/// The documented failures of the passwords API, see [kind](EndpointError::kind)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EndpointErrorKind {
+    /// The revision given to an update is not the current one, the object must be fetched again
+    OutdatedRevision,
+    /// The revision does not exist or does not belong to the object
+    InvalidRevision,
+    /// The id does not match any object
+    EntityNotFound,
+    /// The object does not belong to the user, or the session is not authorized
+    AccessDenied,
+    /// Any other error, see the [message](EndpointError::message)
+    Other,
+}
+
+impl EndpointError {
+    /// The kind of the error, from its id. The API uses the HTTP status as the id: the two
+    /// revision failures share the id 400, they are told apart by their message which is not
+    /// translated.
+    pub fn kind(&self) -> EndpointErrorKind {
+        match self.id {
+            400 => match self.message.as_str() {
+                "Outdated revision id" => EndpointErrorKind::OutdatedRevision,
+                "Invalid revision id" => EndpointErrorKind::InvalidRevision,
+                _ => EndpointErrorKind::Other,
+            },
+            404 => EndpointErrorKind::EntityNotFound,
+            401 | 403 => EndpointErrorKind::AccessDenied,
+            _ => EndpointErrorKind::Other,
+        }
+    }
+}
+
+/// Stable code of an [Error], see [code](Error::code).
+///
+/// The meaning and the numeric value of an existing code never change across minor versions,
+/// new codes can be added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[repr(u16)]
+pub enum ErrorCode {
+    /// Anything not covered by another code
+    Unknown = 0,
+    /// The server could not be reached or the connection was interrupted
+    Network = 1,
+    NetworkTimeout = 2,
+    /// The credentials were refused, or the session could not be opened
+    AuthFailed = 3,
+    NotFound = 4,
+    /// The object was modified concurrently
+    Conflict = 5,
+    RateLimited = 6,
+    /// The server is in maintenance mode or temporarily unavailable
+    ServerMaintenance = 7,
+    /// The server failed to handle the request
+    ServerError = 8,
+    /// The response of the server could not be read
+    ParseError = 9,
+    /// The request built by the caller was refused before being sent
+    InvalidInput = 10,
+    /// The server does not support the requested feature
+    Unsupported = 11,
+    /// The local clock is not usable
+    Clock = 12,
+    /// The connection failed during a modification, it may or may not have been applied
+    Interrupted = 13,
+    /// The response of the server exceeded the [ResponseLimits]
+    ResponseTooLarge = 14,
+}
+
+impl ErrorCode {
+    /// The numeric value of the code
+    pub fn value(self) -> u16 {
+        self as u16
+    }
+
+    /// A short english sentence that can be shown to end users
+    pub fn user_message(self) -> &'static str {
+        match self {
+            ErrorCode::Unknown => "An unexpected error occurred.",
+            ErrorCode::Network => "Could not reach the server.",
+            ErrorCode::NetworkTimeout => "The server took too long to respond.",
+            ErrorCode::AuthFailed => "Could not log in to the server.",
+            ErrorCode::NotFound => "The item does not exist.",
+            ErrorCode::Conflict => "The item was changed by someone else.",
+            ErrorCode::RateLimited => "Too many requests, try again later.",
+            ErrorCode::ServerMaintenance => "The server is in maintenance, try again later.",
+            ErrorCode::ServerError => "The server could not handle the request.",
+            ErrorCode::ParseError => "The response of the server could not be read.",
+            ErrorCode::InvalidInput => "The request is not valid.",
+            ErrorCode::Unsupported => "This is not supported by the server.",
+            ErrorCode::Clock => "The clock of this device is not valid.",
+            ErrorCode::Interrupted => {
+                "The connection was lost, the change may not have been saved."
+            }
+            ErrorCode::ResponseTooLarge => "The response of the server is too large.",
+        }
+    }
+
+    fn from_http_status(status: u16) -> Self {
+        match status {
+            401 | 403 => ErrorCode::AuthFailed,
+            404 => ErrorCode::NotFound,
+            408 | 504 => ErrorCode::NetworkTimeout,
+            409 | 412 => ErrorCode::Conflict,
+            429 => ErrorCode::RateLimited,
+            503 => ErrorCode::ServerMaintenance,
+            400..=499 => ErrorCode::InvalidInput,
+            500..=599 => ErrorCode::ServerError,
+            _ => ErrorCode::Unknown,
+        }
+    }
+}
+
+impl Error {
+    /// The stable code of the error, meant to be exposed to other languages
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::ApiError(e) => {
+                if e.is_timeout() {
+                    ErrorCode::NetworkTimeout
+                } else if let Some(status) = e.status() {
+                    ErrorCode::from_http_status(status.as_u16())
+                } else if e.is_decode() {
+                    ErrorCode::ParseError
+                } else if e.is_builder() {
+                    ErrorCode::InvalidInput
+                } else {
+                    ErrorCode::Network
+                }
+            }
+            Error::ConnectionFailed => ErrorCode::AuthFailed,
+            Error::DisconnectionFailed => ErrorCode::ServerError,
+            Error::TimeError(_) => ErrorCode::Clock,
+            Error::InvalidSetting => ErrorCode::InvalidInput,
+            Error::SettingTooLarge { .. } => ErrorCode::InvalidInput,
+            Error::MissingSetting(_) => ErrorCode::Unsupported,
+            Error::Serde(_) => ErrorCode::ParseError,
+            Error::EndpointError(e) => match e.kind() {
+                EndpointErrorKind::OutdatedRevision => ErrorCode::Conflict,
+                EndpointErrorKind::EntityNotFound => ErrorCode::NotFound,
+                EndpointErrorKind::AccessDenied => ErrorCode::AuthFailed,
+                _ => e
+                    .http_status
+                    .map(ErrorCode::from_http_status)
+                    .unwrap_or(ErrorCode::ServerError),
+            },
+            Error::LoginFlowError(status) => ErrorCode::from_http_status(*status),
+            Error::LoginFlowTimedOut => ErrorCode::AuthFailed,
+            Error::AppPasswordError(status) => ErrorCode::from_http_status(*status),
+            Error::AppPasswordNotRevoked(status) => ErrorCode::from_http_status(*status),
+            Error::InvalidLoginUrl(_) => ErrorCode::InvalidInput,
+            Error::InvalidDetails { .. } => ErrorCode::InvalidInput,
+            Error::CustomFieldsTooLarge { .. } => ErrorCode::InvalidInput,
+            Error::Validation(_) => ErrorCode::InvalidInput,
+            Error::UnexpectedResponse { .. } => ErrorCode::ParseError,
+            Error::UnsupportedShareType { .. } => ErrorCode::Unsupported,
+            Error::Conflict { .. } => ErrorCode::Conflict,
+            Error::RevisionNotFound { .. } => ErrorCode::NotFound,
+            Error::AmbiguousPath { .. } => ErrorCode::InvalidInput,
+            Error::FolderCycle { .. } => ErrorCode::ParseError,
+            Error::InvalidApiBase { .. } => ErrorCode::InvalidInput,
+            Error::Throttled { .. } => ErrorCode::RateLimited,
+            Error::Timeout { .. } => ErrorCode::NetworkTimeout,
+            Error::Interrupted { .. } => ErrorCode::Interrupted,
+            Error::RetriesExhausted { source, .. } => source.code(),
+            Error::ResponseTooLarge { .. } => ErrorCode::ResponseTooLarge,
+            Error::MasterPasswordRequired => ErrorCode::AuthFailed,
+            Error::ChallengeFailed => ErrorCode::AuthFailed,
+            Error::MissingSessionHeader => ErrorCode::ServerError,
+            Error::AppNotInstalled { .. } => ErrorCode::Unsupported,
+            Error::MaintenanceMode { .. } => ErrorCode::ServerMaintenance,
+            Error::RateLimited { .. } => ErrorCode::RateLimited,
+            Error::TokenRequired(_) => ErrorCode::AuthFailed,
+            Error::TokenRejected => ErrorCode::AuthFailed,
+            Error::TokenRequestFailed(_) => ErrorCode::AuthFailed,
+            Error::UnsupportedChallenge(_) => ErrorCode::Unsupported,
+            Error::UnsupportedServer { .. } => ErrorCode::Unsupported,
+            Error::InvalidVersion(_) => ErrorCode::ParseError,
+            Error::Decryption(cse::DecryptError::UnsupportedType(_)) => ErrorCode::Unsupported,
+            Error::Decryption(_) => ErrorCode::ParseError,
+            Error::TaskFailed(_) => ErrorCode::Unknown,
+            Error::MissingSecret => ErrorCode::InvalidInput,
+            Error::HiddenFolder { .. } => ErrorCode::InvalidInput,
+        }
+    }
+
+    /// A short english sentence that can be shown to end users, it does not contain any detail
+    /// of the error
+    pub fn user_message(&self) -> &'static str {
+        self.code().user_message()
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -152,73 +583,428 @@ pub enum EndpointResponse<T> {
 /// The best way to obtain some is using [Login flow
 /// v2](https://docs.nextcloud.com/server/19/developer_manual/client_apis/LoginFlow/index.html#login-flow-v2).
 /// You can use [register_login_flow_2](LoginDetails::register_login_flow_2) to do this authentication
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct LoginDetails {
     pub server: Url,
     #[serde(rename = "loginName")]
     pub login_name: String,
     #[serde(rename = "appPassword")]
     pub app_password: String,
+    /// Base URL of the passwords API, used instead of `index.php/apps/passwords/api/` on the
+    /// server. The login flow still uses the server.
+    #[serde(
+        rename = "apiBaseOverride",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub api_base_override: Option<Url>,
+    /// Name of the application, sent as the `User-Agent` of the requests. The server shows it in
+    /// the security settings and as the `client` of the revisions.
+    #[serde(
+        rename = "clientName",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub client_name: Option<String>,
+    /// Master password of the account, needed to open a session if it was enabled in the web
+    /// interface. It is never serialized.
+    #[serde(skip)]
+    pub master_password: Option<String>,
+}
+
+impl std::fmt::Debug for LoginDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redacted = capture::REDACTED;
+        f.debug_struct("LoginDetails")
+            .field("server", &self.server)
+            .field("login_name", &self.login_name)
+            .field("app_password", &redacted)
+            .field("api_base_override", &self.api_base_override)
+            .field("client_name", &self.client_name)
+            .field(
+                "master_password",
+                &self.master_password.as_ref().map(|_| redacted),
+            )
+            .finish()
+    }
 }
 
 impl LoginDetails {
+    /// Check that the credentials are accepted by opening a session and closing it.
+    ///
+    /// Notes
+    ///  - Like [new_session](AuthenticatedApi::new_session), this fails with [Error::Throttled]
+    ///    without contacting the server while the server is throttling logins
+    pub async fn verify(&self) -> Result<(), Error> {
+        let (api, _) = AuthenticatedApi::new_session(self.clone()).await?;
+        api.disconnect().await
+    }
+
+    /// Use this URL as the base of the passwords API, for example when a reverse proxy serves it
+    /// under a different path or on a different host
+    pub fn with_api_base(self, api_base: Url) -> Self {
+        Self {
+            api_base_override: Some(api_base),
+            ..self
+        }
+    }
+
+    /// Send this name as the `User-Agent` of the requests, for example `"my-cli/1.2"`, see
+    /// [client_name](Self::client_name). The login flow sends the
+    /// [client_name](LoginFlow2Options::client_name) of its options instead.
+    pub fn with_client_name(self, client_name: impl Into<String>) -> Self {
+        Self {
+            client_name: Some(client_name.into()),
+            ..self
+        }
+    }
+
+    /// Use this master password to solve the challenge of the server when opening a session
+    pub fn with_master_password(self, master_password: String) -> Self {
+        Self {
+            master_password: Some(master_password),
+            ..self
+        }
+    }
+
+    pub(crate) fn passwords_url(&self) -> String {
+        match &self.api_base_override {
+            Some(base) if base.as_str().ends_with('/') => base.to_string(),
+            Some(base) => format!("{}/", base),
+            None => join_url(&self.server, "index.php/apps/passwords/api/").into(),
+        }
+    }
+
+    /// The root of the passwords API on servers rewriting the URLs without `index.php`, `None`
+    /// if the root was [overridden](Self::with_api_base)
+    pub(crate) fn pretty_passwords_url(&self) -> Option<String> {
+        match &self.api_base_override {
+            Some(_) => None,
+            None => Some(join_url(&self.server, "apps/passwords/api/").into()),
+        }
+    }
+
     /// Login with the login flow v2 to the server. The `auth_callback` is given the URL where the
     /// user will grant the permissions, this function should not block (or the authentication will
     /// never finish) waiting for the end of the login_flow.
+    ///
+    /// The server is polled with the default [LoginFlow2Options], this fails with
+    /// [Error::LoginFlowTimedOut] if the user did not grant the access in time.
     pub async fn register_login_flow_2(
         server: Url,
+        auth_callback: impl FnMut(Url),
+    ) -> Result<Self, Error> {
+        Self::register_login_flow_2_with_client(server, Client::new(), auth_callback).await
+    }
+
+    /// Like [register_login_flow_2](Self::register_login_flow_2), making the requests with
+    /// `client`
+    pub async fn register_login_flow_2_with_client(
+        server: Url,
+        client: Client,
+        auth_callback: impl FnMut(Url),
+    ) -> Result<Self, Error> {
+        let options = LoginFlow2Options::default();
+        Self::register_login_flow_2_with_options(server, client, options, auth_callback).await
+    }
+
+    /// Like [register_login_flow_2_with_client](Self::register_login_flow_2_with_client),
+    /// polling the server as configured by `options`
+    pub async fn register_login_flow_2_with_options(
+        server: Url,
+        client: Client,
+        options: LoginFlow2Options,
         mut auth_callback: impl FnMut(Url),
+    ) -> Result<Self, Error> {
+        let client_name = options.client_name.as_deref();
+        let (login_url, flow) =
+            login_flow::LoginFlow2::start_with_client_name(server, client, client_name).await?;
+        log::debug!("Got poll request for login_flow_v2");
+        auth_callback(login_url);
+        let deadline = std::time::Instant::now() + options.timeout;
+        loop {
+            if let Some(details) = flow.poll_once().await? {
+                return Ok(details);
+            }
+            if std::time::Instant::now() + options.poll_interval > deadline {
+                return Err(Error::LoginFlowTimedOut);
+            }
+            log::debug!("Not ready, need to retry");
+            runtime::sleep(options.poll_interval).await
+        }
+    }
+
+    /// Read the `nc://login/user:<user>&password:<app password>&server:<url>` URL shown as a QR
+    /// code by the web interface when creating an app password for a device
+    pub fn from_nc_login_url(url: &str) -> Result<Self, Error> {
+        let invalid = Error::InvalidLoginUrl;
+        let payload = url
+            .strip_prefix("nc://login/")
+            .ok_or_else(|| invalid("it does not start with nc://login/".into()))?;
+        let (mut user, mut password, mut server) = (None, None, None);
+        for component in payload.split('&') {
+            let (key, value) = component
+                .split_once(':')
+                .ok_or_else(|| invalid(format!("{} is not a key:value component", component)))?;
+            let value = percent_encoding::percent_decode_str(value)
+                .decode_utf8()
+                .map_err(|_| invalid(format!("{} is not valid UTF-8", key)))?
+                .into_owned();
+            match key {
+                "user" => user = Some(value),
+                "password" => password = Some(value),
+                "server" => server = Some(value),
+                _ => log::debug!("Ignoring the {} component of the login URL", key),
+            }
+        }
+        let missing: Vec<_> = [
+            ("user", user.is_none()),
+            ("password", password.is_none()),
+            ("server", server.is_none()),
+        ]
+        .iter()
+        .filter(|(_, missing)| *missing)
+        .map(|(name, _)| *name)
+        .collect();
+        let (login_name, app_password, server) = match (user, password, server) {
+            (Some(user), Some(password), Some(server)) => (user, password, server),
+            _ => return Err(invalid(format!("missing {}", missing.join(", ")))),
+        };
+        let mut server: Url = server
+            .parse()
+            .map_err(|e| invalid(format!("the server is not an absolute URL: {}", e)))?;
+        if server.cannot_be_a_base() {
+            return Err(invalid(format!("{} is not the URL of a server", server)));
+        }
+        // The endpoints are joined to the server
+        if !server.path().ends_with('/') {
+            server.set_path(&format!("{}/", server.path()));
+        }
+        Ok(LoginDetails {
+            server,
+            login_name,
+            app_password,
+            api_base_override: None,
+            client_name: None,
+            master_password: None,
+        })
+    }
+
+    /// Login without a browser, converting the password of the user into an app password.
+    /// Accounts with a TOTP second factor need the current `otp`.
+    ///
+    /// If `password` is already an app password it is used as is.
+    pub async fn from_user_credentials(
+        server: Url,
+        user: &str,
+        password: &str,
+        otp: Option<&str>,
+    ) -> Result<Self, Error> {
+        Self::from_user_credentials_with_client(server, Client::new(), user, password, otp).await
+    }
+
+    /// Like [from_user_credentials](Self::from_user_credentials), making the request with
+    /// `client`
+    pub async fn from_user_credentials_with_client(
+        server: Url,
+        client: Client,
+        user: &str,
+        password: &str,
+        otp: Option<&str>,
     ) -> Result<Self, Error> {
         #[derive(Deserialize)]
-        struct Poll {
-            token: String,
-            endpoint: Url,
+        struct AppPassword {
+            apppassword: String,
         }
         #[derive(Deserialize)]
-        struct PollRequest {
-            poll: Poll,
-            login: Url,
+        struct Ocs {
+            data: AppPassword,
         }
-        #[derive(Serialize)]
-        struct Token {
-            token: String,
+        #[derive(Deserialize)]
+        struct Envelope {
+            ocs: Ocs,
         }
-        let client = reqwest::Client::new();
-        let resp = client
-            .post(&format!("{}index.php/login/v2", server))
-            .send()
-            .await?;
-        if !resp.status().is_success() {
-            return Err(Error::LoginFlowError(resp.status().as_u16()));
+        let mut request = client
+            .get(join_url(&server, "ocs/v2.php/core/getapppassword"))
+            .query(&[("format", "json")])
+            .header("OCS-APIRequest", "true")
+            .basic_auth(user, Some(password));
+        if let Some(otp) = otp {
+            request = request.header("OTP", otp);
         }
-
-        let resp: PollRequest = resp.json().await?;
-        log::debug!("Got poll request for login_flow_v2");
-        auth_callback(resp.login);
-        let token = Token {
-            token: resp.poll.token,
-        };
-        let details: LoginDetails = loop {
-            let poll = client
-                .post(resp.poll.endpoint.as_str())
-                .form(&token)
-                .send()
-                .await?;
-            log::debug!("Polled endpoint");
-            match poll.status().as_u16() {
-                404 => {
-                    log::debug!("Not ready, need to retry");
-                    tokio::time::delay_for(std::time::Duration::from_millis(100)).await
-                }
-                200 => break poll.json().await?,
-                code => return Err(Error::LoginFlowError(code)),
+        let resp = request.send().await?;
+        let app_password = match resp.status().as_u16() {
+            200 => resp.json::<Envelope>().await?.ocs.data.apppassword,
+            // The server refuses to convert an app password
+            403 => {
+                log::debug!("The password is already an app password");
+                password.into()
             }
+            code => return Err(Error::AppPasswordError(code)),
+        };
+        Ok(LoginDetails {
+            server,
+            login_name: user.into(),
+            app_password,
+            api_base_override: None,
+            client_name: None,
+            master_password: None,
+        })
+    }
+}
+
+/// How [register_login_flow_2](LoginDetails::register_login_flow_2) polls the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginFlow2Options {
+    /// Time between two polls, 1 second by default
+    pub poll_interval: std::time::Duration,
+    /// Time given to the user to grant the access, 20 minutes by default like the lifetime of
+    /// the token on the server
+    pub timeout: std::time::Duration,
+    /// Sent as the `User-Agent` of the flow, the server names the app password after it. Kept
+    /// as the [client_name](LoginDetails::client_name) of the details, `None` by default
+    pub client_name: Option<String>,
+}
+
+impl Default for LoginFlow2Options {
+    fn default() -> Self {
+        LoginFlow2Options {
+            poll_interval: std::time::Duration::from_secs(1),
+            timeout: std::time::Duration::from_secs(20 * 60),
+            client_name: None,
+        }
+    }
+}
+
+/// The error for a refused session request or session open. Throttled logins penalize the
+/// server, see [Error::Throttled]
+async fn session_failure(
+    response: reqwest::Response,
+    start: std::time::Instant,
+    login_details: &LoginDetails,
+    challenged: bool,
+    token_sent: bool,
+) -> Error {
+    let server = login_details.server.to_string();
+    let status = response.status();
+    let elapsed = start.elapsed();
+    let retry_after = throttle::retry_after(response.headers());
+    let unavailable = unavailable(status.as_u16(), response.headers());
+    let body = response.text().await.unwrap_or_default();
+    if throttle::is_throttled(status.as_u16(), elapsed, &body) {
+        let retry_in = retry_after
+            .unwrap_or(elapsed * 2)
+            .max(throttle::MIN_COOLDOWN);
+        log::warn!(
+            "Login to {} is throttled (delayed {:?}), waiting {:?}",
+            server,
+            elapsed,
+            retry_in
+        );
+        throttle::penalize(&server, retry_in);
+        return Error::Throttled {
+            delay: Some(elapsed),
+            retry_in,
         };
-        Ok(details)
+    }
+    // The server tells which of the token or the challenge failed only in the message
+    if token_sent && (!challenged || body.to_lowercase().contains("token")) {
+        return Error::TokenRejected;
+    }
+    if challenged {
+        return Error::ChallengeFailed;
+    }
+    match (&login_details.api_base_override, unavailable) {
+        (_, Some(e @ Error::MaintenanceMode { .. })) => e,
+        (Some(url), _) => Error::InvalidApiBase {
+            url: url.clone(),
+            status: status.as_u16(),
+        },
+        (None, Some(e)) => e,
+        (None, None) => Error::ConnectionFailed,
+    }
+}
+
+/// The error of a response that does not come from the passwords API: the server is in
+/// maintenance mode, or answered a page of Nextcloud because the app is not installed
+fn unavailable(status: u16, headers: &reqwest::header::HeaderMap) -> Option<Error> {
+    if status == 503 || headers.contains_key("X-Nextcloud-Maintenance-Mode") {
+        return Some(Error::MaintenanceMode { status });
+    }
+    let content_type = headers.get(reqwest::header::CONTENT_TYPE)?.to_str().ok()?;
+    if content_type.contains("json") {
+        None
+    } else {
+        Some(Error::AppNotInstalled { status })
+    }
+}
+
+/// How long the server asks to wait when `Retry-After` is missing, its rate limits are per minute
+const DEFAULT_RETRY_AFTER: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// The error of a response refused before reaching the passwords API, or rate limited by it
+fn rejected(status: u16, headers: &reqwest::header::HeaderMap) -> Option<Error> {
+    if status == 429 {
+        let retry_after = throttle::retry_after(headers).unwrap_or(DEFAULT_RETRY_AFTER);
+        return Some(Error::RateLimited { retry_after });
+    }
+    unavailable(status, headers)
+}
+
+/// The passwords API is not served at the requested URL
+fn api_missing(response: &reqwest::Response) -> bool {
+    let status = response.status().as_u16();
+    status == 404
+        || matches!(
+            unavailable(status, response.headers()),
+            Some(Error::AppNotInstalled { .. })
+        )
+}
+
+/// Join a path below `base`, which is a directory even without the trailing `/`: the server may
+/// be in a subdirectory like `https://example.com/cloud`, and the path may start with a `/`
+pub(crate) fn join_url(base: &Url, path: &str) -> Url {
+    let mut base = base.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    base.join(path.trim_start_matches('/'))
+        .expect("the paths of the API are relative URLs")
+}
+
+/// Join the root of the passwords API and an endpoint with exactly one `/`, see [join_url]
+pub(crate) fn endpoint_url(passwords_url: &str, endpoint: &str) -> String {
+    match Url::parse(passwords_url) {
+        Ok(root) => join_url(&root, endpoint).into(),
+        // The request fails with the error of reqwest
+        Err(_) => format!("{}/{}", passwords_url, endpoint.trim_start_matches('/')),
+    }
+}
+
+/// Estimate the offset of the server clock from the `Date` header
+pub(crate) fn server_time_offset(headers: &reqwest::header::HeaderMap) -> Option<i64> {
+    let date = headers.get(reqwest::header::DATE)?.to_str().ok()?;
+    let date = httpdate::parse_http_date(date).ok()?;
+    Some(match date.duration_since(std::time::SystemTime::now()) {
+        Ok(ahead) => ahead.as_secs() as i64,
+        Err(behind) => -(behind.duration().as_secs() as i64),
+    })
+}
+
+/// Move `time` by `offset` seconds, to convert it between the local clock and the clock of the
+/// server
+fn shift(time: std::time::SystemTime, offset: i64) -> std::time::SystemTime {
+    let offset_duration = std::time::Duration::from_secs(offset.unsigned_abs());
+    if offset >= 0 {
+        time + offset_duration
+    } else {
+        time - offset_duration
     }
 }
 
-/// The state needed to re-connect to a nextcloud instance
+/// The state needed to re-connect to a nextcloud instance. It keeps the routing cookies of the
+/// server, so that a load balancer routes the resumed session to the node which issued it. The
+/// authentication cookies of nextcloud are not kept.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ResumeState {
     server_url: Url,
@@ -227,9 +1013,294 @@ pub struct ResumeState {
     keepalive: u64,
     session_id: String,
     shutdown_time: std::time::SystemTime,
+    #[serde(default)]
+    server_time_offset: i64,
+    #[serde(default)]
+    api_base_override: Option<Url>,
+    #[serde(default = "default_api_version")]
+    api_version: String,
+    #[serde(default)]
+    client_name: Option<String>,
+    /// The cookies of the server, see [cookies::CookieJar]
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    cookies: std::collections::BTreeMap<String, String>,
 
     login: String,
-    password: String,
+    #[serde(default, skip_serializing_if = "ResumeSecret::is_empty")]
+    password: ResumeSecret,
+    #[serde(skip)]
+    strict: bool,
+}
+
+impl ResumeState {
+    /// The URL of the nextcloud instance
+    pub fn server(&self) -> &Url {
+        &self.server_url
+    }
+    /// The login name of the user
+    pub fn login(&self) -> &str {
+        &self.login
+    }
+    /// See [LoginDetails::api_base_override]
+    pub fn api_base_override(&self) -> Option<&Url> {
+        self.api_base_override.as_ref()
+    }
+    /// See [api_version](AuthenticatedApi::api_version)
+    pub fn api_version(&self) -> &str {
+        &self.api_version
+    }
+    /// See [LoginDetails::client_name]
+    pub fn client_name(&self) -> Option<&str> {
+        self.client_name.as_deref()
+    }
+    /// See [keepalive](AuthenticatedApi::keepalive)
+    pub fn keepalive(&self) -> u64 {
+        self.keepalive
+    }
+    /// The time of the [last request](AuthenticatedApi::last_request) of the session, in the
+    /// clock of the server (see [server_time_offset](AuthenticatedApi::server_time_offset))
+    pub fn last_request(&self) -> std::time::SystemTime {
+        self.shutdown_time
+    }
+
+    /// Fail to resume if the session can't be kept alive, instead of opening a new session. This
+    /// detects revoked credentials or sessions.
+    pub fn strict(self) -> Self {
+        Self {
+            strict: true,
+            ..self
+        }
+    }
+
+    /// Remove the app password from the state, to store it apart (for example in the keyring of
+    /// the OS). The state is serialized without it, and can't be resumed until the secret is
+    /// given back with [with_secret](Self::with_secret), resuming fails with
+    /// [Error::MissingSecret] meanwhile.
+    pub fn take_secret(&mut self) -> ResumeSecret {
+        std::mem::take(&mut self.password)
+    }
+    /// Give back the secret taken by [take_secret](Self::take_secret)
+    pub fn with_secret(self, secret: ResumeSecret) -> Self {
+        Self {
+            password: secret,
+            ..self
+        }
+    }
+}
+
+/// The app password of a [ResumeState], zeroed when dropped. It does not implement `Debug`
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(transparent)]
+pub struct ResumeSecret(String);
+
+impl ResumeSecret {
+    pub fn new(app_password: String) -> Self {
+        ResumeSecret(app_password)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Drop for ResumeSecret {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.0);
+    }
+}
+
+fn default_api_version() -> String {
+    DEFAULT_API_VERSION.into()
+}
+
+/// Version of the passwords API used by default, see
+/// [set_api_version](AuthenticatedApi::set_api_version)
+pub const DEFAULT_API_VERSION: &str = "1.0";
+
+/// Statistics on the requests made to an endpoint, see
+/// [request_stats](AuthenticatedApi::request_stats)
+#[derive(Debug, Clone, Default)]
+pub struct EndpointStats {
+    /// Number of requests made
+    pub requests: u64,
+    /// Cumulated time of all the requests, including reading the response
+    pub total_time: std::time::Duration,
+}
+
+/// Requests expected to be slow (like previews or lists) only warn after this multiple of the
+/// slow request threshold
+pub const EXPECTED_SLOW_FACTOR: u32 = 10;
+
+/// Limits on the responses of the server, to protect against broken or hostile servers. See
+/// [set_response_limits](AuthenticatedApi::set_response_limits)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseLimits {
+    /// Maximum size in bytes of a JSON response
+    pub max_size: usize,
+    /// Maximum nesting of arrays and objects in a JSON response. The JSON parser refuses more
+    /// than 128 levels whatever this limit is.
+    pub max_depth: usize,
+    /// Maximum size in bytes of the images (avatars, favicons and previews)
+    pub max_image_size: usize,
+}
+
+impl Default for ResponseLimits {
+    fn default() -> Self {
+        ResponseLimits {
+            max_size: 64 * 1024 * 1024,
+            max_depth: 64,
+            max_image_size: 8 * 1024 * 1024,
+        }
+    }
+}
+
+/// How the requests failing with a transient error are retried, see
+/// [set_retry_policy](AuthenticatedApi::set_retry_policy)
+///
+/// The connection errors and the `429`, `502`, `503` and `504` statuses are retried, not the
+/// timeouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts of a request, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each following retry. A `429` response is
+    /// retried after its `Retry-After` delay instead.
+    pub base_delay: std::time::Duration,
+    /// A random delay up to this one is added to each delay, so that the clients failing
+    /// together do not retry together
+    pub jitter: std::time::Duration,
+    /// Retry the creations, updates and deletions too. A retried modification may be applied
+    /// twice if the server received the failed attempt.
+    pub retry_modifications: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: std::time::Duration::from_millis(500),
+            jitter: std::time::Duration::from_millis(250),
+            retry_modifications: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay after the failed attempt number `attempt`, starting at 1
+    fn delay(&self, attempt: u32) -> std::time::Duration {
+        let backoff = self.base_delay * 2u32.saturating_pow(attempt - 1);
+        let jitter = match self.jitter.as_nanos() as u64 {
+            0 => 0,
+            // Precise enough to spread the retries, without a random generator
+            jitter => match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+                Ok(now) => now.subsec_nanos() as u64 % jitter,
+                Err(_) => 0,
+            },
+        };
+        backoff + std::time::Duration::from_nanos(jitter)
+    }
+}
+
+/// The statuses of a server overloaded or restarting
+fn is_transient_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 502 | 503 | 504)
+}
+
+/// The limit of [ResponseLimits] exceeded by a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Size(usize),
+    Depth(usize),
+}
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LimitExceeded::Size(limit) => write!(fmt, "larger than {} bytes", limit),
+            LimitExceeded::Depth(limit) => write!(fmt, "nested deeper than {} levels", limit),
+        }
+    }
+}
+
+/// Read the body of the response, `None` if it is larger than `limit`
+async fn read_limited(
+    mut response: reqwest::Response,
+    limit: usize,
+) -> Result<(u16, Option<bytes::Bytes>), reqwest::Error> {
+    let status = response.status().as_u16();
+    if response.content_length().unwrap_or(0) > limit as u64 {
+        return Ok((status, None));
+    }
+    let mut body = bytes::BytesMut::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Ok((status, None));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((status, Some(body.freeze())))
+}
+
+/// Whether arrays and objects are nested deeper than `limit` in the JSON document
+fn json_deeper_than(json: &str, limit: usize) -> bool {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for byte in json.bytes() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > limit {
+                    return true;
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+    false
+}
+
+/// Number of times an idempotent request is retried after a connection error
+const IDEMPOTENT_RETRIES: u32 = 2;
+
+/// The connection failed, as opposed to an error returned by the server or in the request
+fn is_connection_error(error: &reqwest::Error) -> bool {
+    !(error.is_status() || error.is_builder() || error.is_decode() || error.is_redirect())
+}
+
+/// Send `client_name` as the `User-Agent` of the request instead of the one of the client
+pub(crate) fn with_client_name(
+    request: reqwest::RequestBuilder,
+    client_name: Option<&str>,
+) -> reqwest::RequestBuilder {
+    match client_name {
+        Some(name) => request.header(reqwest::header::USER_AGENT, name),
+        None => request,
+    }
+}
+
+/// The result of [handshake](AuthenticatedApi::handshake)
+struct OpenedSession {
+    client: Client,
+    passwords_url: String,
+    session_id: String,
+    server_time_offset: i64,
+    keychain: Option<cse::Keychain>,
 }
 
 /// The main entrypoint to the nextcloud API
@@ -237,12 +1308,33 @@ pub struct AuthenticatedApi {
     server_url: Url,
     client: Client,
     passwords_url: String,
+    api_base_override: Option<Url>,
+    api_version: String,
 
-    session_id: String,
+    session_id: std::sync::RwLock<String>,
     keepalive: u64,
+    last_request: std::sync::Mutex<std::time::SystemTime>,
+    server_time_offset: std::sync::atomic::AtomicI64,
+    /// Held while the session is reopened, so that concurrent requests open only one session
+    reopening: futures::lock::Mutex<()>,
+    auto_reopen: bool,
 
     login: String,
-    password: String,
+    password: Zeroizing<String>,
+    client_name: Option<String>,
+    cookies: cookies::CookieJar,
+
+    capabilities: std::sync::RwLock<Option<settings::ClientCapabilities>>,
+
+    slow_request_threshold: Option<std::time::Duration>,
+    timeout: Option<std::time::Duration>,
+    media_timeout: Option<std::time::Duration>,
+    retry: Option<RetryPolicy>,
+    limits: ResponseLimits,
+    stats: std::sync::Mutex<std::collections::HashMap<String, EndpointStats>>,
+    capture: std::sync::Mutex<Option<capture::CaptureLog>>,
+
+    keychain: Option<cse::Keychain>,
 }
 
 impl AuthenticatedApi {
@@ -250,55 +1342,481 @@ impl AuthenticatedApi {
     pub fn server(&self) -> &Url {
         &self.server_url
     }
+    /// The version of the passwords API used by the requests, [DEFAULT_API_VERSION] unless
+    /// changed with [set_api_version](Self::set_api_version)
+    pub fn api_version(&self) -> &str {
+        &self.api_version
+    }
+    /// Use this version of the passwords API for the following requests, for example `"1.1"`.
+    /// The session is always opened with [DEFAULT_API_VERSION].
+    pub fn set_api_version(&mut self, version: impl Into<String>) {
+        self.api_version = version.into();
+    }
+    /// The id of the current session, it changes when the session is reopened
+    pub fn session_id(&self) -> String {
+        self.session_id.read().unwrap().clone()
+    }
+    /// How long the session stays open without request, in seconds
+    pub fn keepalive(&self) -> u64 {
+        self.keepalive
+    }
+    /// When the last successful request was answered, the session expires
+    /// [keepalive](Self::keepalive) seconds later
+    pub fn last_request(&self) -> std::time::SystemTime {
+        *self.last_request.lock().unwrap()
+    }
+    /// Reopen the session when the server reports it expired and retry the request, enabled by
+    /// default. The session is reopened without master password nor second factor, accounts
+    /// requiring them get the error of [new_session](Self::new_session) instead.
+    pub fn set_auto_reopen(&mut self, enabled: bool) {
+        self.auto_reopen = enabled;
+    }
+    /// Open a new session with the credentials of this one, unless a concurrent request already
+    /// replaced the `expired` session
+    async fn reopen_session(&self, expired: &str) -> Result<(), Error> {
+        let _reopening = self.reopening.lock().await;
+        if *self.session_id.read().unwrap() != expired {
+            return Ok(());
+        }
+        log::debug!("Session expired, opening a new one");
+        let login_details = LoginDetails {
+            server: self.server_url.clone(),
+            login_name: self.login.clone(),
+            app_password: self.password.to_string(),
+            api_base_override: self.api_base_override.clone(),
+            client_name: self.client_name.clone(),
+            master_password: None,
+        };
+        let opened =
+            Self::handshake(&login_details, None, self.client.clone(), &self.cookies).await?;
+        *self.session_id.write().unwrap() = opened.session_id;
+        Ok(())
+    }
+    /// The path of an action of a resource of the API, relative to the base of the API
+    pub(crate) fn endpoint(&self, resource: &str, action: &str) -> String {
+        format!("{}/{}/{}", self.api_version, resource, action)
+    }
+    /// The URL of an endpoint, see [endpoint_url]
+    pub(crate) fn endpoint_url(&self, endpoint: &str) -> String {
+        endpoint_url(&self.passwords_url, endpoint)
+    }
     async fn reqwest<D: serde::Serialize>(
         &self,
         endpoint: impl AsRef<str>,
         method: reqwest::Method,
-        data: D,
+        data: &D,
+        timeout: Option<std::time::Duration>,
     ) -> Result<reqwest::Response, reqwest::Error> {
-        self.client
-            .request(
-                method,
-                &format!("{}/{}", self.passwords_url, endpoint.as_ref()),
-            )
-            .json(&data)
-            .header("X-API-SESSION", &self.session_id)
-            .basic_auth(&self.login, Some(&self.password))
-            .send()
-            .await
+        let request = self
+            .client
+            .request(method, self.endpoint_url(endpoint.as_ref()))
+            .json(data)
+            .header("X-API-SESSION", self.session_id())
+            .basic_auth(&self.login, Some(self.password.as_str()));
+        let request = self.cookies.add_to(request);
+        let request = with_client_name(request, self.client_name.as_deref());
+        let response = match timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+        .send()
+        .await?;
+        self.cookies.store(response.headers());
+        Ok(response)
     }
-    pub(crate) async fn bytes_request<D: serde::Serialize>(
-        &self,
-        endpoint: impl AsRef<str>,
-        method: reqwest::Method,
-        data: D,
-    ) -> Result<bytes::Bytes, Error> {
-        let r = self.reqwest(endpoint, method, data).await?;
-        r.bytes().await.map_err(Into::into)
+    /// Fail the requests not answered after `timeout` with [Timeout](Error::Timeout), by default
+    /// there is no timeout other than the ones of the client. The sessions are opened with the
+    /// timeouts of the client, see [new_session_with_client](Self::new_session_with_client).
+    pub fn set_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.timeout = timeout;
     }
-    async fn passwords_request<R: serde::de::DeserializeOwned, D: serde::Serialize>(
+    /// Use this timeout instead of the one of [set_timeout](Self::set_timeout) for the images of
+    /// the [service API](Self::service), the favicons and previews can be slow to generate
+    pub fn set_media_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.media_timeout = timeout;
+    }
+    /// Emit a warning for requests slower than `threshold`, or `threshold` times
+    /// [EXPECTED_SLOW_FACTOR] for requests expected to be slow. Warnings are logged with the
+    /// `nextcloud_passwords_client::slow_request` target
+    pub fn set_slow_request_threshold(&mut self, threshold: Option<std::time::Duration>) {
+        self.slow_request_threshold = threshold;
+    }
+    /// Change the limits on the responses, a response over the limits fails with
+    /// [ResponseTooLarge](Error::ResponseTooLarge). The default limits can be too low for huge
+    /// vaults.
+    pub fn set_response_limits(&mut self, limits: ResponseLimits) {
+        self.limits = limits;
+    }
+    /// Retry the requests failing with a transient error as configured by `policy`. By default
+    /// the reads are retried twice right away after a connection error only. A request still
+    /// failing after the last attempt returns [RetriesExhausted](Error::RetriesExhausted).
+    pub fn set_retry_policy(&mut self, policy: Option<RetryPolicy>) {
+        self.retry = policy;
+    }
+    /// Statistics on the requests made by this API, by endpoint (without the parameters in the
+    /// path)
+    pub fn request_stats(&self) -> std::collections::HashMap<String, EndpointStats> {
+        self.stats.lock().unwrap().clone()
+    }
+    fn record_request(
         &self,
-        endpoint: impl AsRef<str>,
-        method: reqwest::Method,
-        data: D,
-    ) -> Result<R, Error> {
-        let r = self.reqwest(endpoint, method, data).await?;
-        let text = r.text().await?;
-        let resp = serde_json::from_str(&text).map_err(|e| {
-            log::warn!("Response could not be read: {}", text);
-            e
-        })?;
-        match resp {
-            EndpointResponse::Success(r) => Ok(r),
-            EndpointResponse::Error(e) => Err(Error::EndpointError(e)),
+        endpoint: &str,
+        start: std::time::Instant,
+        size: usize,
+        expect_slow: bool,
+    ) {
+        let duration = start.elapsed();
+        let key = endpoint
+            .trim_start_matches('/')
+            .splitn(4, '/')
+            .take(3)
+            .collect::<Vec<_>>()
+            .join("/");
+        {
+            let mut stats = self.stats.lock().unwrap();
+            let stats = stats.entry(key).or_default();
+            stats.requests += 1;
+            stats.total_time += duration;
         }
+
+        if let Some(threshold) = self.slow_request_threshold {
+            let threshold = if expect_slow {
+                threshold * EXPECTED_SLOW_FACTOR
+            } else {
+                threshold
+            };
+            if duration > threshold {
+                log::warn!(
+                    target: "nextcloud_passwords_client::slow_request",
+                    "slow request: endpoint={} duration_ms={} response_bytes={}",
+                    endpoint,
+                    duration.as_millis(),
+                    size
+                );
+            }
+        }
+    }
+    /// Follow the changes of the clocks during the session with the `Date` of the responses
+    fn record_server_time(&self, headers: &reqwest::header::HeaderMap) {
+        if let Some(offset) = server_time_offset(headers) {
+            let previous = self
+                .server_time_offset
+                .swap(offset, std::sync::atomic::Ordering::Relaxed);
+            // The header has a precision of one second
+            if (offset - previous).abs() > 1 {
+                log::debug!(
+                    "Server clock offset changed from {}s to {}s",
+                    previous,
+                    offset
+                );
+            }
+        }
+    }
+    /// A successful answer extends the session, see [last_request](Self::last_request)
+    fn record_activity(&self, status: u16) {
+        if (200..300).contains(&status) {
+            *self.last_request.lock().unwrap() = std::time::SystemTime::now();
+        }
+    }
+    /// Keep the last `capacity` requests and responses, with the secrets redacted. This is meant
+    /// to be attached to bug reports
+    pub fn enable_capture(&self, capacity: usize) {
+        *self.capture.lock().unwrap() = Some(capture::CaptureLog::new(capacity));
+    }
+    /// Stop capturing the requests, dropping the captures
+    pub fn disable_capture(&self) {
+        *self.capture.lock().unwrap() = None;
+    }
+    /// The captured requests, oldest first. Empty if the capture is not enabled
+    pub fn capture_log(&self) -> Vec<capture::Capture> {
+        self.capture
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|log| log.entries())
+            .unwrap_or_default()
+    }
+    fn captured_request<D: serde::Serialize>(&self, data: &D) -> Option<serde_json::Value> {
+        if self.capture.lock().unwrap().is_some() {
+            Some(serde_json::to_value(data).unwrap_or(serde_json::Value::Null))
+        } else {
+            None
+        }
+    }
+    fn record_capture(
+        &self,
+        endpoint: &str,
+        method: &reqwest::Method,
+        request: Option<serde_json::Value>,
+        status: u16,
+        response: impl FnOnce() -> String,
+    ) -> Option<u64> {
+        let request = request?;
+        self.capture
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(|log| log.push(endpoint, method, request, status, response()))
+    }
+    /// Send the request and read the response with `read`. Idempotent requests are retried on
+    /// connection errors, or as configured by the [RetryPolicy]. The others fail with
+    /// [Interrupted](Error::Interrupted) if the connection failed after the request may have
+    /// reached the server.
+    async fn send<D, T, F, Fut>(
+        &self,
+        endpoint: &str,
+        method: &reqwest::Method,
+        data: &D,
+        idempotent: bool,
+        timeout: Option<std::time::Duration>,
+        read: F,
+    ) -> Result<T, Error>
+    where
+        D: serde::Serialize,
+        F: Fn(reqwest::Response) -> Fut,
+        Fut: std::future::Future<Output = Result<T, reqwest::Error>>,
+    {
+        let policy = self
+            .retry
+            .filter(|policy| idempotent || policy.retry_modifications);
+        let mut attempts = 1;
+        // The default retries of the reads are not reported
+        let exhausted = |attempts, source: Error| match policy {
+            Some(_) if attempts > 1 => Error::RetriesExhausted {
+                endpoint: endpoint.into(),
+                attempts,
+                source: Box::new(source),
+            },
+            _ => source,
+        };
+        loop {
+            let result = match self.reqwest(endpoint, method.clone(), data, timeout).await {
+                Ok(response) => match policy {
+                    Some(policy) if is_transient_status(response.status()) => {
+                        if attempts >= policy.max_attempts {
+                            let error = response.error_for_status().unwrap_err();
+                            return Err(exhausted(attempts, error.into()));
+                        }
+                        let retry_after = match response.status().as_u16() {
+                            429 => throttle::retry_after(response.headers()),
+                            _ => None,
+                        };
+                        let delay = retry_after.unwrap_or_else(|| policy.delay(attempts));
+                        log::debug!(
+                            "Retrying {} in {:?} after a {} response",
+                            endpoint,
+                            delay,
+                            response.status()
+                        );
+                        runtime::sleep(delay).await;
+                        attempts += 1;
+                        continue;
+                    }
+                    _ => {
+                        if response.status().is_success() {
+                            self.record_server_time(response.headers());
+                        }
+                        read(response).await
+                    }
+                },
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(response) => return Ok(response),
+                // Retrying a slow server would only make it slower
+                Err(e) if e.is_timeout() && idempotent => {
+                    return Err(Error::Timeout {
+                        endpoint: endpoint.into(),
+                    })
+                }
+                Err(e) if is_connection_error(&e) => {
+                    let retry = match policy {
+                        Some(policy) => attempts < policy.max_attempts,
+                        None => idempotent && attempts <= IDEMPOTENT_RETRIES,
+                    };
+                    if retry {
+                        log::debug!("Retrying {} after a connection error: {}", endpoint, e);
+                        if let Some(policy) = policy {
+                            runtime::sleep(policy.delay(attempts)).await;
+                        }
+                        attempts += 1;
+                    } else if !idempotent && !e.is_connect() {
+                        let error = Error::Interrupted {
+                            endpoint: endpoint.into(),
+                            source: e,
+                        };
+                        return Err(exhausted(attempts, error));
+                    } else {
+                        return Err(exhausted(attempts, e.into()));
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    pub(crate) async fn bytes_request<D: serde::Serialize>(
+        &self,
+        endpoint: impl AsRef<str>,
+        method: reqwest::Method,
+        data: D,
+        expect_slow: bool,
+    ) -> Result<bytes::Bytes, Error> {
+        let captured = self.captured_request(&data);
+        let start = std::time::Instant::now();
+        let idempotent = method == reqwest::Method::GET;
+        let limit = self.limits.max_image_size;
+        let timeout = self.media_timeout.or(self.timeout);
+        let (status, bytes) = self
+            .send(
+                endpoint.as_ref(),
+                &method,
+                &data,
+                idempotent,
+                timeout,
+                |r| read_limited(r, limit),
+            )
+            .await?;
+        let bytes = bytes.ok_or_else(|| Error::ResponseTooLarge {
+            endpoint: endpoint.as_ref().into(),
+            exceeded: LimitExceeded::Size(limit),
+        })?;
+        self.record_request(endpoint.as_ref(), start, bytes.len(), expect_slow);
+        self.record_activity(status);
+        let capture = self.record_capture(endpoint.as_ref(), &method, captured, status, || {
+            format!("<{} bytes>", bytes.len())
+        });
+        if !(200..300).contains(&status) {
+            // An error of the API instead of the image
+            let text = String::from_utf8_lossy(&bytes);
+            return Err(match serde_json::from_str::<EndpointError>(&text) {
+                Ok(mut e) => {
+                    e.http_status = Some(status);
+                    Error::EndpointError(e)
+                }
+                Err(source) => Error::UnexpectedResponse {
+                    endpoint: endpoint.as_ref().into(),
+                    status,
+                    body: capture::redact_text(text.into_owned()),
+                    source,
+                    capture,
+                },
+            });
+        }
+        Ok(bytes)
+    }
+    /// Send the request, reopening the session and retrying once if it expired, see
+    /// [set_auto_reopen](Self::set_auto_reopen)
+    async fn passwords_text<D: serde::Serialize>(
+        &self,
+        endpoint: impl AsRef<str>,
+        method: reqwest::Method,
+        data: D,
+        expect_slow: bool,
+        idempotent: bool,
+    ) -> Result<(String, u16, Option<u64>), Error> {
+        let endpoint = endpoint.as_ref();
+        let session = self.session_id();
+        let response = self
+            .passwords_text_once(endpoint, &method, &data, expect_slow, idempotent)
+            .await?;
+        if response.1 == 401 && self.auto_reopen {
+            self.reopen_session(&session).await?;
+            log::debug!("Retrying {} in the new session", endpoint);
+            return self
+                .passwords_text_once(endpoint, &method, &data, expect_slow, idempotent)
+                .await;
+        }
+        Ok(response)
+    }
+    async fn passwords_text_once<D: serde::Serialize>(
+        &self,
+        endpoint: &str,
+        method: &reqwest::Method,
+        data: &D,
+        expect_slow: bool,
+        idempotent: bool,
+    ) -> Result<(String, u16, Option<u64>), Error> {
+        let captured = self.captured_request(data);
+        let start = std::time::Instant::now();
+        let limits = self.limits;
+        let (rejected, (status, body)) = self
+            .send(endpoint, method, data, idempotent, self.timeout, |r| {
+                let rejected = rejected(r.status().as_u16(), r.headers());
+                async move { Ok((rejected, read_limited(r, limits.max_size).await?)) }
+            })
+            .await?;
+        let too_large = |exceeded| Error::ResponseTooLarge {
+            endpoint: endpoint.into(),
+            exceeded,
+        };
+        let body = body.ok_or_else(|| too_large(LimitExceeded::Size(limits.max_size)))?;
+        let text = String::from_utf8_lossy(&body).into_owned();
+        if json_deeper_than(&text, limits.max_depth) {
+            return Err(too_large(LimitExceeded::Depth(limits.max_depth)));
+        }
+        self.record_request(endpoint, start, text.len(), expect_slow);
+        self.record_activity(status);
+        let capture = self.record_capture(endpoint, method, captured, status, || text.clone());
+        if let Some(e) = rejected {
+            log::warn!("{} was rejected: {}", endpoint, text);
+            return Err(e);
+        }
+        Ok((text, status, capture))
+    }
+    fn decode_response<R: serde::de::DeserializeOwned>(
+        endpoint: &str,
+        text: &str,
+        status: u16,
+        capture: Option<u64>,
+    ) -> Result<R, Error> {
+        // The status tells errors apart, the payloads can't: a success can have the fields of
+        // an error, and an error is a valid `serde_json::Value`
+        let resp = if (200..300).contains(&status) {
+            serde_json::from_str(text).map(EndpointResponse::Success)
+        } else {
+            serde_json::from_str(text).map(EndpointResponse::Error)
+        };
+        let resp = resp
+            .or_else(|_| serde_json::from_str(text))
+            .map_err(|source| {
+                let body = capture::redact_text(text.into());
+                log::warn!("Response could not be read: {}", body);
+                Error::UnexpectedResponse {
+                    endpoint: endpoint.into(),
+                    status,
+                    body,
+                    source,
+                    capture,
+                }
+            })?;
+        match resp {
+            EndpointResponse::Success(r) => Ok(r),
+            EndpointResponse::Error(mut e) => {
+                e.http_status = Some(status);
+                Err(Error::EndpointError(e))
+            }
+        }
+    }
+    async fn passwords_request<R: serde::de::DeserializeOwned, D: serde::Serialize>(
+        &self,
+        endpoint: impl AsRef<str>,
+        method: reqwest::Method,
+        data: D,
+        expect_slow: bool,
+        idempotent: bool,
+    ) -> Result<R, Error> {
+        let endpoint = endpoint.as_ref();
+        let (text, status, capture) = self
+            .passwords_text(endpoint, method, data, expect_slow, idempotent)
+            .await?;
+        Self::decode_response(endpoint, &text, status, capture)
     }
     pub(crate) async fn passwords_get<R: serde::de::DeserializeOwned, D: serde::Serialize>(
         &self,
         endpoint: impl AsRef<str>,
         data: D,
     ) -> Result<R, Error> {
-        self.passwords_request(endpoint, reqwest::Method::GET, data)
+        self.passwords_request(endpoint, reqwest::Method::GET, data, false, true)
             .await
     }
     pub(crate) async fn passwords_post<R: serde::de::DeserializeOwned, D: serde::Serialize>(
@@ -306,15 +1824,120 @@ impl AuthenticatedApi {
         endpoint: impl AsRef<str>,
         data: D,
     ) -> Result<R, Error> {
-        self.passwords_request(endpoint, reqwest::Method::POST, data)
+        self.passwords_request(endpoint, reqwest::Method::POST, data, false, false)
             .await
     }
+    /// POST to an endpoint that only reads data, it is retried on connection errors
+    pub(crate) async fn passwords_post_read<R: serde::de::DeserializeOwned, D: serde::Serialize>(
+        &self,
+        endpoint: impl AsRef<str>,
+        data: D,
+    ) -> Result<R, Error> {
+        self.passwords_request(endpoint, reqwest::Method::POST, data, false, true)
+            .await
+    }
+    /// POST to a list endpoint. The elements are deserialized by chunks, yielding to the
+    /// executor between them so that a large list does not block it.
+    pub(crate) async fn passwords_list<R: serde::de::DeserializeOwned, D: serde::Serialize>(
+        &self,
+        endpoint: impl AsRef<str>,
+        data: D,
+    ) -> Result<Vec<R>, Error> {
+        let endpoint = endpoint.as_ref();
+        self.passwords_list_with(endpoint, data, |_, element, status, capture, source| {
+            let body = capture::redact_text(element.get().into());
+            log::warn!("Response could not be read: {}", body);
+            Err(Error::UnexpectedResponse {
+                endpoint: endpoint.into(),
+                status,
+                body,
+                source,
+                capture,
+            })
+        })
+        .await
+    }
+    /// Like [passwords_list](Self::passwords_list), returning the elements that could not be
+    /// read along the others
+    pub(crate) async fn passwords_list_lossy<R, D>(
+        &self,
+        endpoint: impl AsRef<str>,
+        data: D,
+    ) -> Result<(Vec<R>, Vec<ItemError>), Error>
+    where
+        R: serde::de::DeserializeOwned,
+        D: serde::Serialize,
+    {
+        #[derive(Deserialize)]
+        struct Id {
+            id: uuid::Uuid,
+        }
+        let mut errors = Vec::new();
+        let list = self
+            .passwords_list_with(endpoint, data, |index, element, _, _, source| {
+                log::warn!("Skipping element {} of the list: {}", index, source);
+                let id = serde_json::from_str::<Id>(element.get())
+                    .ok()
+                    .map(|id| id.id);
+                errors.push(ItemError { index, id, source });
+                Ok(())
+            })
+            .await?;
+        Ok((list, errors))
+    }
+    /// Read the elements of a list one by one, `on_error` decides what to do with the elements
+    /// that can't be read
+    async fn passwords_list_with<R, D, F>(
+        &self,
+        endpoint: impl AsRef<str>,
+        data: D,
+        mut on_error: F,
+    ) -> Result<Vec<R>, Error>
+    where
+        R: serde::de::DeserializeOwned,
+        D: serde::Serialize,
+        F: FnMut(
+            usize,
+            &serde_json::value::RawValue,
+            u16,
+            Option<u64>,
+            serde_json::Error,
+        ) -> Result<(), Error>,
+    {
+        let endpoint = endpoint.as_ref();
+        let (text, status, capture) = self
+            .passwords_text(endpoint, reqwest::Method::POST, data, true, true)
+            .await?;
+        match serde_json::from_str::<Vec<&serde_json::value::RawValue>>(&text) {
+            Ok(elements) => {
+                let mut list = Vec::with_capacity(elements.len());
+                for (chunk_index, chunk) in elements.chunks(utils::YIELD_EVERY).enumerate() {
+                    for (offset, element) in chunk.iter().enumerate() {
+                        match serde_json::from_str(element.get()) {
+                            Ok(value) => list.push(value),
+                            Err(source) => on_error(
+                                chunk_index * utils::YIELD_EVERY + offset,
+                                element,
+                                status,
+                                capture,
+                                source,
+                            )?,
+                        }
+                    }
+                    runtime::yield_now().await;
+                }
+                Ok(list)
+            }
+            // Not a list, this is an error
+            Err(_) => Self::decode_response(endpoint, &text, status, capture),
+        }
+    }
     pub(crate) async fn passwords_delete<R: serde::de::DeserializeOwned, D: serde::Serialize>(
         &self,
         endpoint: impl AsRef<str>,
         data: D,
     ) -> Result<R, Error> {
-        self.passwords_request(endpoint, reqwest::Method::DELETE, data)
+        self.passwords_request(endpoint, reqwest::Method::DELETE, data, false, false)
             .await
     }
     pub(crate) async fn passwords_patch<R: serde::de::DeserializeOwned, D: serde::Serialize>(
@@ -322,7 +1945,7 @@ impl AuthenticatedApi {
         endpoint: impl AsRef<str>,
         data: D,
     ) -> Result<R, Error> {
-        self.passwords_request(endpoint, reqwest::Method::PATCH, data)
+        self.passwords_request(endpoint, reqwest::Method::PATCH, data, false, false)
             .await
     }
 
@@ -341,6 +1964,11 @@ impl AuthenticatedApi {
     pub fn folder(&self) -> folder::FolderApi<'_> {
         folder::FolderApi { api: self }
     }
+    /// Access the Tag API
+    #[inline]
+    pub fn tag(&self) -> tag::TagApi<'_> {
+        tag::TagApi { api: self }
+    }
     /// Access the Share API
     #[inline]
     pub fn share(&self) -> share::ShareApi<'_> {
@@ -350,91 +1978,503 @@ impl AuthenticatedApi {
     pub fn service(&self) -> service::ServiceApi<'_> {
         service::ServiceApi { api: self }
     }
+    /// Access the Token API
+    #[inline]
+    pub fn token(&self) -> token::TokenApi<'_> {
+        token::TokenApi { api: self }
+    }
+
+    /// Fetch the parts selected by the options concurrently. A part that fails is reported in
+    /// [errors](initial_load::InitialData::errors) without failing the others, this only fails
+    /// if all the requested parts failed.
+    pub async fn initial_load(
+        &self,
+        options: initial_load::InitialLoadOptions,
+    ) -> Result<initial_load::InitialData, Error> {
+        initial_load::load(self, options).await
+    }
+
+    /// Delete all the passwords, tags and folders in the trash, see the `empty_trash` method of
+    /// each API
+    pub async fn empty_trash(&self) -> Result<EmptyTrashReport, Error> {
+        let mut report = self.password().empty_trash().await?;
+        report.merge(self.tag().empty_trash().await?);
+        // Last, as deleting a folder deletes its content
+        report.merge(self.folder().empty_trash().await?);
+        Ok(report)
+    }
+
+    /// Fetch what the client is allowed to do on this server, derived from the server and user
+    /// settings. The result is cached, use [refresh_capabilities](Self::refresh_capabilities) to
+    /// query the server again.
+    pub async fn capabilities(&self) -> Result<settings::ClientCapabilities, Error> {
+        if let Some(capabilities) = self.cached_capabilities() {
+            return Ok(capabilities);
+        }
+        self.refresh_capabilities().await
+    }
+    /// Fetch the capabilities from the server, replacing the cached value
+    pub async fn refresh_capabilities(&self) -> Result<settings::ClientCapabilities, Error> {
+        let capabilities = settings::ClientCapabilities::fetch(self).await?;
+        *self.capabilities.write().unwrap() = Some(capabilities.clone());
+        Ok(capabilities)
+    }
+    /// The length of the password hashes chosen by the user, see
+    /// [hash_length](settings::ClientCapabilities::hash_length). It is read from the cached
+    /// capabilities, or only the `user.password.security.hash` setting is fetched. Servers
+    /// without the setting store the full hash.
+    pub async fn hash_length(&self) -> Result<usize, Error> {
+        if let Some(capabilities) = self.cached_capabilities() {
+            return Ok(capabilities.hash_length);
+        }
+        settings::ClientCapabilities::fetch_hash_length(self).await
+    }
+    pub(crate) fn cached_capabilities(&self) -> Option<settings::ClientCapabilities> {
+        self.capabilities.read().unwrap().clone()
+    }
+    pub(crate) fn invalidate_capabilities(&self) {
+        *self.capabilities.write().unwrap() = None;
+    }
+    /// Fail with [Error::UnsupportedServer] if the passwords app is older than `version`. The
+    /// servers which do not advertise their version are assumed to support everything.
+    pub(crate) async fn requires(
+        &self,
+        feature: impl Into<String>,
+        version: settings::Version,
+    ) -> Result<(), Error> {
+        match self.capabilities().await?.app_version {
+            Some(actual) if actual < version => Err(Error::UnsupportedServer {
+                feature: feature.into(),
+                required: version,
+                actual,
+            }),
+            _ => Ok(()),
+        }
+    }
+    /// A request to an OCS endpoint of Nextcloud, outside of the passwords API
+    fn ocs_request(&self, method: reqwest::Method, endpoint: &str) -> reqwest::RequestBuilder {
+        let request = self
+            .client
+            .request(
+                method,
+                join_url(&self.server_url, &format!("ocs/v2.php/{}", endpoint)),
+            )
+            .header("OCS-APIRequest", "true")
+            .basic_auth(&self.login, Some(self.password.as_str()));
+        with_client_name(self.cookies.add_to(request), self.client_name.as_deref())
+    }
+    pub(crate) async fn ocs_capabilities(&self) -> Result<settings::OcsCapabilities, Error> {
+        #[derive(Deserialize)]
+        struct Ocs {
+            data: settings::OcsCapabilities,
+        }
+        #[derive(Deserialize)]
+        struct Envelope {
+            ocs: Ocs,
+        }
+        let resp = self
+            .ocs_request(reqwest::Method::GET, "cloud/capabilities")
+            .query(&[("format", "json")])
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(resp.json::<Envelope>().await?.ocs.data)
+    }
+
+    /// Check step by step that a session can be opened with these details: the server is
+    /// reachable, nextcloud and the passwords app are installed, the credentials are accepted and
+    /// the clocks are synchronized. Each step has its own timeout
+    /// ([PROBE_TIMEOUT](diagnostics::PROBE_TIMEOUT)).
+    pub async fn diagnose(login: &LoginDetails) -> diagnostics::DiagnosticReport {
+        diagnostics::diagnose(login).await
+    }
+
+    /// Difference in seconds between the clock of the server and the local clock, positive if
+    /// the server is ahead. It is estimated when the session is opened and updated by the
+    /// following responses, with a precision of one second
+    pub fn server_time_offset(&self) -> i64 {
+        self.server_time_offset
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
 
     /// Resume a connection to the API using the state. Also gives the session ID
+    ///
+    /// Notes
+    ///  - The master password is not part of the state: if the session expired on an account
+    ///    protected by a master password, this fails with [Error::MasterPasswordRequired] and a
+    ///    new session must be opened with [new_session](Self::new_session)
+    ///  - The keychain is not part of the state either, a resumed session has no
+    ///    [keychain](Self::keychain)
+    ///  - If the server closed the session before its expiry (restart, sessions cleared by an
+    ///    admin), a new session is opened unless the state is [strict](ResumeState::strict)
     pub async fn resume_session(resume_state: ResumeState) -> Result<(Self, String), Error> {
-        if resume_state.shutdown_time.elapsed()?.as_secs() > resume_state.keepalive {
-            log::debug!("Session was too old, creating new session");
-            AuthenticatedApi::new_session(LoginDetails {
-                server: resume_state.server_url,
-                login_name: resume_state.login,
-                app_password: resume_state.password,
-            })
-            .await
-        } else {
-            log::debug!("Calling keepalive");
-            #[derive(Deserialize)]
-            struct Keepalive {
-                success: bool,
+        Self::resume_session_with_client(resume_state, Client::new()).await
+    }
+
+    /// Like [resume_session](Self::resume_session), making the requests with `client`
+    pub async fn resume_session_with_client(
+        mut resume_state: ResumeState,
+        client: Client,
+    ) -> Result<(Self, String), Error> {
+        if resume_state.password.is_empty() {
+            return Err(Error::MissingSecret);
+        }
+        // The server expires the session with its own clock
+        let server_now = shift(
+            std::time::SystemTime::now(),
+            resume_state.server_time_offset,
+        );
+        let expired = match server_now.duration_since(resume_state.shutdown_time) {
+            Ok(elapsed) => elapsed.as_secs() > resume_state.keepalive,
+            Err(e) => {
+                log::warn!(
+                    "Shutdown time is {:?} in the future, the clock changed",
+                    e.duration()
+                );
+                true
             }
-            let client = Client::new();
-            let api = AuthenticatedApi {
-                server_url: resume_state.server_url,
-                client,
-                passwords_url: resume_state.password_url,
-                session_id: resume_state.session_id,
+        };
+        if !expired {
+            log::debug!("Calling keepalive");
+            let mut api = AuthenticatedApi {
+                server_url: resume_state.server_url.clone(),
+                client: client.clone(),
+                passwords_url: resume_state.password_url.clone(),
+                session_id: resume_state.session_id.clone().into(),
                 keepalive: resume_state.keepalive,
-                login: resume_state.login,
-                password: resume_state.password,
+                last_request: std::time::SystemTime::now().into(),
+                server_time_offset: resume_state.server_time_offset.into(),
+                reopening: Default::default(),
+                auto_reopen: !resume_state.strict,
+                api_base_override: resume_state.api_base_override.clone(),
+                api_version: resume_state.api_version.clone(),
+                login: resume_state.login.clone(),
+                password: Zeroizing::new(resume_state.password.expose().to_owned()),
+                client_name: resume_state.client_name.clone(),
+                cookies: cookies::CookieJar::new(cookies::persisted(resume_state.cookies.clone())),
+                capabilities: Default::default(),
+                slow_request_threshold: None,
+                timeout: None,
+                media_timeout: None,
+                retry: None,
+                limits: Default::default(),
+                stats: Default::default(),
+                capture: Default::default(),
+                keychain: None,
             };
-            let s: Keepalive = api.passwords_get("1.0/session/keepalive", ()).await?;
-            assert!(s.success);
-            let session_id = api.session_id.clone();
-            Ok((api, session_id))
+            match api.keep_alive().await {
+                Ok(()) => {
+                    api.auto_reopen = true;
+                    let session_id = api.session_id();
+                    return Ok((api, session_id));
+                }
+                Err(e) if resume_state.strict => return Err(e),
+                Err(e) => log::warn!("Could not resume the session ({}), creating new session", e),
+            }
+        } else {
+            log::debug!("Session was too old, creating new session");
         }
+        let login_details = LoginDetails {
+            server: resume_state.server_url,
+            login_name: resume_state.login,
+            app_password: std::mem::take(&mut resume_state.password.0),
+            api_base_override: resume_state.api_base_override,
+            client_name: resume_state.client_name,
+            master_password: None,
+        };
+        let (mut api, session_id) =
+            AuthenticatedApi::new_session_with_client(login_details, client).await?;
+        api.set_api_version(resume_state.api_version);
+        Ok((api, session_id))
     }
     /// Create a new session to the API, returns the session ID
+    ///
+    /// If the bruteforce protection of the server delays or refuses the login, an
+    /// [Error::Throttled] is returned and further calls for the same server fail without
+    /// contacting it until the end of the cooldown.
+    ///
+    /// If the account is protected by a master password, the challenge of the server is solved
+    /// with [master_password](LoginDetails::master_password). It fails with
+    /// [Error::MasterPasswordRequired] if none was given, and with [Error::ChallengeFailed] if the
+    /// server refused it.
+    ///
+    /// If the account requires a second factor, this fails with [Error::TokenRequired] listing
+    /// the providers configured on the account, use
+    /// [new_session_with_token](Self::new_session_with_token) instead.
     pub async fn new_session(login_details: LoginDetails) -> Result<(Self, String), Error> {
+        Self::new_session_with_client(login_details, Client::new()).await
+    }
+
+    /// Like [new_session](Self::new_session), making the requests with `client`, for example to
+    /// use a proxy, a custom root certificate or timeouts
+    pub async fn new_session_with_client(
+        login_details: LoginDetails,
+        client: Client,
+    ) -> Result<(Self, String), Error> {
+        Self::open_session(login_details, None, client).await
+    }
+
+    /// Like [new_session](Self::new_session), sending the second factor `token` of `provider`.
+    /// If the server refuses the token, this fails with [Error::TokenRejected].
+    ///
+    /// The providers that [need a request](token::ProviderInfo::needs_request) only send the
+    /// token after [request_token](Self::request_token).
+    pub async fn new_session_with_token(
+        login_details: LoginDetails,
+        provider: &token::TokenProvider,
+        token: &str,
+    ) -> Result<(Self, String), Error> {
+        Self::new_session_with_token_and_client(login_details, provider, token, Client::new()).await
+    }
+
+    /// Like [new_session_with_token](Self::new_session_with_token), making the requests with
+    /// `client`
+    pub async fn new_session_with_token_and_client(
+        login_details: LoginDetails,
+        provider: &token::TokenProvider,
+        token: &str,
+        client: Client,
+    ) -> Result<(Self, String), Error> {
+        Self::open_session(login_details, Some((provider.id(), token)), client).await
+    }
+
+    /// Ask `provider` to send its token to the user, before opening the session with
+    /// [new_session_with_token](Self::new_session_with_token). This is the same as
+    /// [TokenApi::request](token::TokenApi::request) without a session, for the providers
+    /// listed by [Error::TokenRequired] that [need a request](token::ProviderInfo::needs_request).
+    pub async fn request_token(
+        login_details: &LoginDetails,
+        provider: &token::TokenProvider,
+    ) -> Result<token::TokenRequest, Error> {
+        Self::request_token_with_client(login_details, provider, Client::new()).await
+    }
+
+    /// Like [request_token](Self::request_token), making the request with `client`
+    pub async fn request_token_with_client(
+        login_details: &LoginDetails,
+        provider: &token::TokenProvider,
+        client: Client,
+    ) -> Result<token::TokenRequest, Error> {
+        let endpoint = format!("{}/token/{}/request", DEFAULT_API_VERSION, provider.id());
+        let start = std::time::Instant::now();
+        let request = client
+            .get(endpoint_url(&login_details.passwords_url(), &endpoint))
+            .basic_auth(&login_details.login_name, Some(&login_details.app_password));
+        let response = with_client_name(request, login_details.client_name.as_deref())
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(session_failure(response, start, login_details, false, false).await);
+        }
+        let response: token::RequestResponse = response.json().await?;
+        response.into_request(provider)
+    }
+
+    async fn open_session(
+        login_details: LoginDetails,
+        token: Option<(&str, &str)>,
+        client: Client,
+    ) -> Result<(Self, String), Error> {
+        let cookies = cookies::CookieJar::default();
+        let opened = Self::handshake(&login_details, token, client, &cookies).await?;
+        let session_id = opened.session_id;
+        let mut api = AuthenticatedApi {
+            server_url: login_details.server,
+            passwords_url: opened.passwords_url,
+            client: opened.client,
+            login: login_details.login_name,
+            password: Zeroizing::new(login_details.app_password),
+            client_name: login_details.client_name,
+            cookies,
+            session_id: session_id.clone().into(),
+            keepalive: 0,
+            last_request: std::time::SystemTime::now().into(),
+            server_time_offset: opened.server_time_offset.into(),
+            reopening: Default::default(),
+            auto_reopen: true,
+            api_base_override: login_details.api_base_override,
+            api_version: default_api_version(),
+            capabilities: Default::default(),
+            slow_request_threshold: None,
+            timeout: None,
+            media_timeout: None,
+            retry: None,
+            limits: Default::default(),
+            stats: Default::default(),
+            capture: Default::default(),
+            keychain: opened.keychain,
+        };
+        api.keepalive = api.settings().get().session_lifetime().await?;
+        log::debug!("Session keepalive is: {}", api.keepalive);
+
+        Ok((api, session_id))
+    }
+
+    /// The `session/request` and `session/open` exchange, without any request in the session
+    async fn handshake(
+        login_details: &LoginDetails,
+        token: Option<(&str, &str)>,
+        client: Client,
+        cookies: &cookies::CookieJar,
+    ) -> Result<OpenedSession, Error> {
         #[derive(Serialize, Deserialize, Debug)]
         struct OpenSession {
             success: bool,
-            keys: Vec<String>,
+            /// The encrypted keychains by encryption, or an empty array
+            keys: serde_json::Value,
+        }
+        let server = login_details.server.to_string();
+        if let Some(retry_in) = throttle::remaining(&server) {
+            return Err(Error::Throttled {
+                delay: None,
+                retry_in,
+            });
+        }
+
+        let mut passwords_url = login_details.passwords_url();
+        let start = std::time::Instant::now();
+        let client_name = login_details.client_name.as_deref();
+        let request_session = |passwords_url: &str| {
+            let request = client
+                .get(endpoint_url(
+                    passwords_url,
+                    &format!("{}/session/request", DEFAULT_API_VERSION),
+                ))
+                .basic_auth(&login_details.login_name, Some(&login_details.app_password));
+            with_client_name(cookies.add_to(request), client_name).send()
+        };
+        let mut requirements = request_session(&passwords_url).await?;
+        // Some servers rewriting the URLs only serve the API without `index.php`
+        if let Some(pretty_url) = login_details.pretty_passwords_url() {
+            if api_missing(&requirements) {
+                log::debug!("Passwords API not found, trying {}", pretty_url);
+                let pretty_requirements = request_session(&pretty_url).await?;
+                if !api_missing(&pretty_requirements) {
+                    passwords_url = pretty_url;
+                    requirements = pretty_requirements;
+                }
+            }
+        }
+        cookies.store(requirements.headers());
+        if !requirements.status().is_success() {
+            return Err(session_failure(requirements, start, login_details, false, false).await);
+        }
+        // A page of Nextcloud, like the login page when the app is missing
+        if let Some(e) = unavailable(requirements.status().as_u16(), requirements.headers()) {
+            return Err(e);
+        }
+        // The server answers an empty array when nothing is required
+        let requirements: serde_json::Value = requirements.json().await?;
+        let providers = token::providers(&requirements)?;
+        if !providers.is_empty() && token.is_none() {
+            return Err(Error::TokenRequired(providers));
         }
-        let client = Client::new();
+        let challenge = match requirements.get("challenge") {
+            Some(challenge) => {
+                let challenge: challenge::Challenge = serde_json::from_value(challenge.clone())?;
+                let master_password = login_details
+                    .master_password
+                    .clone()
+                    .ok_or(Error::MasterPasswordRequired)?;
+                log::debug!("Solving the {} challenge", challenge.ty);
+                // The key derivation takes a noticeable time, keep it out of the runtime threads
+                let secret =
+                    runtime::spawn_blocking(move || challenge.solve(&master_password)).await??;
+                Some(secret)
+            }
+            None => None,
+        };
 
-        let passwords_url = format!("{}index.php/apps/passwords/api/", login_details.server);
+        #[derive(Serialize)]
+        struct OpenRequest<'t> {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            challenge: Option<String>,
+            /// The token by provider
+            #[serde(skip_serializing_if = "Option::is_none")]
+            token: Option<std::collections::HashMap<&'t str, &'t str>>,
+        }
+        let challenged = challenge.is_some();
+        let token_sent = token.is_some();
+        let token = token.map(|token| std::iter::once(token).collect());
+        let start = std::time::Instant::now();
         let session_request = client
             .request(
                 reqwest::Method::POST,
-                &format!("{}/1.0/session/open", passwords_url),
+                endpoint_url(
+                    &passwords_url,
+                    &format!("{}/session/open", DEFAULT_API_VERSION),
+                ),
             )
             .basic_auth(&login_details.login_name, Some(&login_details.app_password))
+            .json(&OpenRequest { challenge, token });
+        let session_request = with_client_name(cookies.add_to(session_request), client_name)
             .send()
             .await?;
+        cookies.store(session_request.headers());
+        if !session_request.status().is_success() {
+            let failure = session_failure(
+                session_request,
+                start,
+                login_details,
+                challenged,
+                token_sent,
+            );
+            return Err(failure.await);
+        }
+        throttle::clear(&server);
         let session_id: String = session_request
             .headers()
             .get("X-API-SESSION")
-            .expect("no api session header")
-            .to_str()
-            .expect("api session is not ascii")
+            .and_then(|header| header.to_str().ok())
+            .ok_or(Error::MissingSessionHeader)?
             .into();
+        let server_time_offset = server_time_offset(session_request.headers()).unwrap_or(0);
+        log::debug!("Server clock offset is {}s", server_time_offset);
         let session: OpenSession = session_request.json().await?;
         if !session.success {
             Err(Error::ConnectionFailed)?
         }
+        let encrypted_keychain = session.keys.get(cse::CSE_V1R1).and_then(|k| k.as_str());
+        let keychain = match (encrypted_keychain, login_details.master_password.clone()) {
+            (Some(encrypted), Some(master_password)) => {
+                let encrypted = encrypted.to_owned();
+                let keychain = runtime::spawn_blocking(move || {
+                    cse::Keychain::decrypt(&encrypted, &master_password)
+                })
+                .await??;
+                Some(keychain)
+            }
+            _ => None,
+        };
 
-        let mut api = AuthenticatedApi {
-            server_url: login_details.server,
-            passwords_url,
+        Ok(OpenedSession {
             client,
-            login: login_details.login_name,
-            password: login_details.app_password,
-            session_id: session_id.clone(),
-            keepalive: 0,
-        };
-        api.keepalive = api.settings().get().session_lifetime().await?;
-        log::debug!("Session keepalive is: {}", api.keepalive);
+            passwords_url,
+            session_id,
+            server_time_offset,
+            keychain,
+        })
+    }
 
-        Ok((api, session_id))
+    /// The keychain of the client side encryption, available if the session was opened with the
+    /// master password of an account using it
+    pub fn keychain(&self) -> Option<&cse::Keychain> {
+        self.keychain.as_ref()
     }
 
     /// Disconnect from the session
     pub async fn disconnect(self) -> Result<(), Error> {
+        self.close_session().await
+    }
+
+    async fn close_session(&self) -> Result<(), Error> {
         #[derive(Deserialize)]
         struct CloseSession {
             success: bool,
         }
-        let s: CloseSession = self.passwords_get("1.0/session/close", ()).await.unwrap();
+        let s: CloseSession = self
+            .passwords_get(self.endpoint("session", "close"), ())
+            .await?;
 
         if !s.success {
             Err(Error::DisconnectionFailed)
@@ -443,22 +2483,686 @@ impl AuthenticatedApi {
         }
     }
 
-    /// Get the state to be able to resume this session
+    /// Disconnect from the session and revoke the app password on the server, to sign out of
+    /// the account. The credentials can't be used anymore, even to resume the session.
+    ///
+    /// This succeeds if the app password was revoked, even if the session could not be closed.
+    /// It fails with [Error::AppPasswordNotRevoked] if the server refused to revoke it.
+    pub async fn destroy_app_password(self) -> Result<(), Error> {
+        if let Err(e) = self.close_session().await {
+            log::warn!("Could not close the session: {}", e);
+        }
+        let resp = self
+            .ocs_request(reqwest::Method::DELETE, "core/apppassword")
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(Error::AppPasswordNotRevoked(resp.status().as_u16()));
+        }
+        Ok(())
+    }
+
+    /// Keep the session open, it is closed by the server after
+    /// [session_lifetime](settings::SettingsFetcher::session_lifetime) seconds without request
+    pub async fn keep_alive(&self) -> Result<(), Error> {
+        #[derive(Deserialize)]
+        struct Keepalive {
+            success: bool,
+        }
+        let s: Keepalive = self
+            .passwords_get(self.endpoint("session", "keepalive"), ())
+            .await?;
+        if s.success {
+            Ok(())
+        } else {
+            Err(Error::ConnectionFailed)
+        }
+    }
+
+    /// Call [keep_alive](Self::keep_alive) in a background task shortly before the session
+    /// expires, until the returned handle is dropped. Must be called in the
+    /// [runtime](crate::runtime) of the crate.
+    pub fn spawn_keepalive(self: std::sync::Arc<Self>) -> keepalive::KeepaliveHandle {
+        keepalive::spawn(self)
+    }
+
+    /// Get the state to be able to resume this session, the session is considered idle since
+    /// the [last request](Self::last_request)
     pub fn get_state(&self) -> ResumeState {
         ResumeState {
             server_url: self.server_url.clone(),
             password_url: self.passwords_url.clone(),
 
             keepalive: self.keepalive,
-            session_id: self.session_id.clone(),
+            session_id: self.session_id(),
+            server_time_offset: self.server_time_offset(),
+            api_base_override: self.api_base_override.clone(),
+            api_version: self.api_version.clone(),
 
             login: self.login.clone(),
-            password: self.password.clone(),
+            password: ResumeSecret::new(self.password.to_string()),
+            client_name: self.client_name.clone(),
+            cookies: self.cookies.to_persisted(),
 
-            shutdown_time: std::time::SystemTime::now(),
+            shutdown_time: shift(self.last_request(), self.server_time_offset()),
+            strict: false,
         }
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    fn endpoint_error(http_status: Option<u16>) -> Error {
+        Error::EndpointError(EndpointError {
+            status: "error".into(),
+            id: 0,
+            message: "message".into(),
+            http_status,
+        })
+    }
+
+    #[test]
+    fn error_codes() {
+        let serde = || serde_json::from_str::<u8>("x").unwrap_err();
+        let password: password::Password =
+            serde_json::from_value(password::tests::password_json()).unwrap();
+        let builder = || Client::new().get("not an url").build().unwrap_err();
+        let time = std::time::SystemTime::UNIX_EPOCH
+            .duration_since(std::time::SystemTime::now())
+            .unwrap_err();
+
+        let cases = vec![
+            (Error::ApiError(builder()), ErrorCode::InvalidInput),
+            (Error::ConnectionFailed, ErrorCode::AuthFailed),
+            (Error::DisconnectionFailed, ErrorCode::ServerError),
+            (Error::TimeError(time), ErrorCode::Clock),
+            (Error::InvalidSetting, ErrorCode::InvalidInput),
+            (
+                Error::SettingTooLarge {
+                    name: "client.cursor".into(),
+                    length: 200,
+                    limit: 128,
+                },
+                ErrorCode::InvalidInput,
+            ),
+            (
+                Error::MissingSetting("server.theme.label".into()),
+                ErrorCode::Unsupported,
+            ),
+            (Error::Serde(serde()), ErrorCode::ParseError),
+            (endpoint_error(None), ErrorCode::ServerError),
+            (endpoint_error(Some(401)), ErrorCode::AuthFailed),
+            (endpoint_error(Some(404)), ErrorCode::NotFound),
+            (endpoint_error(Some(429)), ErrorCode::RateLimited),
+            (endpoint_error(Some(503)), ErrorCode::ServerMaintenance),
+            (endpoint_error(Some(400)), ErrorCode::InvalidInput),
+            (Error::LoginFlowError(404), ErrorCode::NotFound),
+            (Error::LoginFlowTimedOut, ErrorCode::AuthFailed),
+            (Error::AppPasswordError(401), ErrorCode::AuthFailed),
+            (Error::AppPasswordNotRevoked(500), ErrorCode::ServerError),
+            (Error::InvalidLoginUrl("".into()), ErrorCode::InvalidInput),
+            (
+                Error::CustomFieldsTooLarge {
+                    count: 21,
+                    length: 0,
+                },
+                ErrorCode::InvalidInput,
+            ),
+            (
+                Error::InvalidDetails {
+                    segment: "x".into(),
+                    allowed: "a",
+                },
+                ErrorCode::InvalidInput,
+            ),
+            (
+                Error::Validation(ValidationError {
+                    field: "label",
+                    limit: 64,
+                    actual: 65,
+                }),
+                ErrorCode::InvalidInput,
+            ),
+            (Error::InvalidVersion("x".into()), ErrorCode::ParseError),
+            (
+                Error::RateLimited {
+                    retry_after: std::time::Duration::from_secs(60),
+                },
+                ErrorCode::RateLimited,
+            ),
+            (
+                Error::RetriesExhausted {
+                    endpoint: "1.0/password/list".into(),
+                    attempts: 3,
+                    source: Box::new(Error::LoginFlowError(503)),
+                },
+                ErrorCode::ServerMaintenance,
+            ),
+            (
+                Error::UnsupportedServer {
+                    feature: "searching folders by parent".into(),
+                    required: settings::Version::new(2019, 5, 0),
+                    actual: settings::Version::new(2019, 1, 0),
+                },
+                ErrorCode::Unsupported,
+            ),
+            (
+                Error::UnexpectedResponse {
+                    endpoint: "1.0/password/list".into(),
+                    status: 200,
+                    body: "x".into(),
+                    source: serde(),
+                    capture: None,
+                },
+                ErrorCode::ParseError,
+            ),
+            (
+                Error::UnsupportedShareType {
+                    requested: share::ShareType::User,
+                    supported: vec![],
+                },
+                ErrorCode::Unsupported,
+            ),
+            (
+                Error::Conflict {
+                    current: password.into(),
+                },
+                ErrorCode::Conflict,
+            ),
+            (
+                Error::RevisionNotFound {
+                    id: uuid::Uuid::nil(),
+                    revision: uuid::Uuid::nil(),
+                },
+                ErrorCode::NotFound,
+            ),
+            (
+                Error::AmbiguousPath {
+                    path: "/Work/Mail".into(),
+                    label: "Work".into(),
+                    folders: vec![uuid::Uuid::from_u128(1), uuid::Uuid::from_u128(2)],
+                },
+                ErrorCode::InvalidInput,
+            ),
+            (
+                Error::FolderCycle {
+                    folders: vec![uuid::Uuid::from_u128(1), uuid::Uuid::from_u128(2)],
+                },
+                ErrorCode::ParseError,
+            ),
+            (
+                Error::InvalidApiBase {
+                    url: "https://example.com/pw/".parse().unwrap(),
+                    status: 404,
+                },
+                ErrorCode::InvalidInput,
+            ),
+            (
+                Error::Throttled {
+                    delay: None,
+                    retry_in: std::time::Duration::from_secs(30),
+                },
+                ErrorCode::RateLimited,
+            ),
+            (
+                Error::Timeout {
+                    endpoint: "1.0/password/list".into(),
+                },
+                ErrorCode::NetworkTimeout,
+            ),
+            (
+                Error::Interrupted {
+                    endpoint: "1.0/password/create".into(),
+                    source: builder(),
+                },
+                ErrorCode::Interrupted,
+            ),
+            (
+                Error::ResponseTooLarge {
+                    endpoint: "1.0/password/list".into(),
+                    exceeded: LimitExceeded::Size(64),
+                },
+                ErrorCode::ResponseTooLarge,
+            ),
+            (Error::MasterPasswordRequired, ErrorCode::AuthFailed),
+            (Error::ChallengeFailed, ErrorCode::AuthFailed),
+            (Error::MissingSessionHeader, ErrorCode::ServerError),
+            (
+                Error::AppNotInstalled { status: 404 },
+                ErrorCode::Unsupported,
+            ),
+            (
+                Error::MaintenanceMode { status: 503 },
+                ErrorCode::ServerMaintenance,
+            ),
+            (Error::TokenRequired(Vec::new()), ErrorCode::AuthFailed),
+            (Error::TokenRejected, ErrorCode::AuthFailed),
+            (
+                Error::TokenRequestFailed(token::TokenProvider::Email),
+                ErrorCode::AuthFailed,
+            ),
+            (
+                Error::UnsupportedChallenge("PWDv2".into()),
+                ErrorCode::Unsupported,
+            ),
+            (
+                cse::DecryptError::UnsupportedType("CSEv2".into()).into(),
+                ErrorCode::Unsupported,
+            ),
+            (cse::DecryptError::WrongKey.into(), ErrorCode::ParseError),
+            (Error::TaskFailed("panicked".into()), ErrorCode::Unknown),
+            (Error::MissingSecret, ErrorCode::InvalidInput),
+            (
+                Error::HiddenFolder {
+                    object: uuid::Uuid::nil(),
+                    folder: uuid::Uuid::nil(),
+                },
+                ErrorCode::InvalidInput,
+            ),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.code(), code, "{:?}", error);
+            assert_eq!(error.user_message(), code.user_message());
+        }
+    }
+
+    #[test]
+    fn json_depth() {
+        assert!(!json_deeper_than(r#"[{"a": [1, 2]}, {"b": {}}]"#, 3));
+        assert!(json_deeper_than(r#"[{"a": [[1]]}]"#, 3));
+        assert!(!json_deeper_than(r#"["[[[[", "\\\"{{{{"]"#, 1));
+        assert!(json_deeper_than(&"[".repeat(1000), 64));
+    }
+
+    #[test]
+    fn connection_errors() {
+        let builder = Client::new().get("not an url").build().unwrap_err();
+        assert!(!is_connection_error(&builder));
+    }
+
+    #[test]
+    fn api_base() {
+        let login = LoginDetails {
+            server: "https://cloud.example.com/".parse().unwrap(),
+            login_name: "alice".into(),
+            app_password: "secret".into(),
+            api_base_override: None,
+            client_name: None,
+            master_password: None,
+        };
+        assert_eq!(
+            login.passwords_url(),
+            "https://cloud.example.com/index.php/apps/passwords/api/"
+        );
+        let login = login.with_api_base("https://pw.example.com/pw".parse().unwrap());
+        assert_eq!(login.passwords_url(), "https://pw.example.com/pw/");
+    }
+
+    fn offline_api() -> AuthenticatedApi {
+        AuthenticatedApi {
+            server_url: "https://cloud.example.com/".parse().unwrap(),
+            client: Client::new(),
+            passwords_url: "https://cloud.example.com/index.php/apps/passwords/api/".into(),
+            api_base_override: None,
+            api_version: default_api_version(),
+            session_id: String::from("session").into(),
+            keepalive: 600,
+            last_request: std::time::SystemTime::now().into(),
+            server_time_offset: 0.into(),
+            reopening: Default::default(),
+            auto_reopen: true,
+            login: "alice".into(),
+            password: Zeroizing::new("secret".into()),
+            client_name: None,
+            cookies: Default::default(),
+            capabilities: Default::default(),
+            slow_request_threshold: None,
+            timeout: None,
+            media_timeout: None,
+            retry: None,
+            limits: Default::default(),
+            stats: Default::default(),
+            capture: Default::default(),
+            keychain: None,
+        }
+    }
+
+    #[test]
+    fn api_version() {
+        let mut api = offline_api();
+        assert_eq!(api.api_version(), "1.0");
+        assert_eq!(api.endpoint("password", "list"), "1.0/password/list");
+        assert_eq!(
+            api.endpoint("service", "avatar/alice/32"),
+            "1.0/service/avatar/alice/32"
+        );
+
+        api.set_api_version("1.1");
+        assert_eq!(api.endpoint("password", "list"), "1.1/password/list");
+        let state = api.get_state();
+        assert_eq!(state.api_version, "1.1");
+
+        let mut state = serde_json::to_value(&state).unwrap();
+        state.as_object_mut().unwrap().remove("api_version");
+        let state: ResumeState = serde_json::from_value(state).unwrap();
+        assert_eq!(state.api_version, DEFAULT_API_VERSION);
+    }
+
+    #[test]
+    fn endpoint_url() {
+        let api = offline_api();
+        let partners = "https://cloud.example.com/index.php/apps/passwords/api/1.0/share/partners";
+        assert_eq!(
+            api.endpoint_url(&api.endpoint("share", "partners")),
+            partners
+        );
+        assert_eq!(api.endpoint_url("/1.0/share/partners"), partners);
+
+        let login = LoginDetails {
+            server: "https://cloud.example.com/".parse().unwrap(),
+            login_name: "alice".into(),
+            app_password: "secret".into(),
+            api_base_override: None,
+            client_name: None,
+            master_password: None,
+        }
+        .with_api_base("https://proxy.example.com/pw".parse().unwrap());
+        assert_eq!(
+            super::endpoint_url(&login.passwords_url(), "1.0/session/open"),
+            "https://proxy.example.com/pw/1.0/session/open"
+        );
+        assert_eq!(login.pretty_passwords_url(), None);
+
+        for server in &["https://example.com/cloud", "https://example.com/cloud/"] {
+            let login = LoginDetails {
+                server: server.parse().unwrap(),
+                api_base_override: None,
+                ..login.clone()
+            };
+            assert_eq!(
+                super::endpoint_url(&login.passwords_url(), "/1.0/session/open"),
+                "https://example.com/cloud/index.php/apps/passwords/api/1.0/session/open"
+            );
+            assert_eq!(
+                login.pretty_passwords_url().unwrap(),
+                "https://example.com/cloud/apps/passwords/api/"
+            );
+        }
+    }
+
+    #[test]
+    fn retry_delays() {
+        let policy = RetryPolicy {
+            base_delay: std::time::Duration::from_millis(100),
+            jitter: std::time::Duration::from_millis(10),
+            ..RetryPolicy::default()
+        };
+        for (attempt, backoff) in [(1, 100), (2, 200), (3, 400)].iter() {
+            let delay = policy.delay(*attempt).as_millis();
+            assert!(delay >= *backoff && delay < backoff + 10, "{}", delay);
+        }
+    }
+
+    #[test]
+    fn error_code_values() {
+        let codes = [
+            (ErrorCode::Unknown, 0),
+            (ErrorCode::Network, 1),
+            (ErrorCode::NetworkTimeout, 2),
+            (ErrorCode::AuthFailed, 3),
+            (ErrorCode::NotFound, 4),
+            (ErrorCode::Conflict, 5),
+            (ErrorCode::RateLimited, 6),
+            (ErrorCode::ServerMaintenance, 7),
+            (ErrorCode::ServerError, 8),
+            (ErrorCode::ParseError, 9),
+            (ErrorCode::InvalidInput, 10),
+            (ErrorCode::Unsupported, 11),
+            (ErrorCode::Clock, 12),
+            (ErrorCode::Interrupted, 13),
+            (ErrorCode::ResponseTooLarge, 14),
+        ];
+        for (code, value) in &codes {
+            assert_eq!(code.value(), *value);
+        }
+    }
+
+    #[test]
+    fn endpoint_error_kinds() {
+        let cases = [
+            (
+                r#"{"status":"error","id":400,"message":"Outdated revision id"}"#,
+                EndpointErrorKind::OutdatedRevision,
+            ),
+            (
+                r#"{"status":"error","id":400,"message":"Invalid revision id"}"#,
+                EndpointErrorKind::InvalidRevision,
+            ),
+            (
+                r#"{"status":"error","id":400,"message":"Invalid request"}"#,
+                EndpointErrorKind::Other,
+            ),
+            (
+                r#"{"status":"error","id":404,"message":"Entity not found"}"#,
+                EndpointErrorKind::EntityNotFound,
+            ),
+            (
+                r#"{"status":"error","id":404,"message":"Not found"}"#,
+                EndpointErrorKind::EntityNotFound,
+            ),
+            (
+                r#"{"status":"error","id":403,"message":"Access denied"}"#,
+                EndpointErrorKind::AccessDenied,
+            ),
+            (
+                r#"{"status":"error","id":401,"message":"Unauthorized"}"#,
+                EndpointErrorKind::AccessDenied,
+            ),
+            // The message alone does not decide the kind
+            (
+                r#"{"status":"error","id":500,"message":"Access denied"}"#,
+                EndpointErrorKind::Other,
+            ),
+        ];
+        for (payload, kind) in &cases {
+            let error: EndpointError = serde_json::from_str(payload).unwrap();
+            assert_eq!(error.kind(), *kind, "{}", payload);
+        }
+    }
+
+    #[test]
+    fn ambiguous_responses() {
+        #[derive(Deserialize, Debug)]
+        struct Message {
+            status: String,
+            id: u64,
+            message: String,
+        }
+        let payload = r#"{"status":"error","id":404,"message":"Entity not found"}"#;
+
+        let message: Message =
+            AuthenticatedApi::decode_response("message", payload, 200, None).unwrap();
+        assert_eq!(message.status, "error");
+        assert_eq!(message.id, 404);
+        assert_eq!(message.message, "Entity not found");
+        let value: serde_json::Value =
+            AuthenticatedApi::decode_response("value", payload, 200, None).unwrap();
+        assert_eq!(value["id"], 404);
+
+        let error =
+            AuthenticatedApi::decode_response::<serde_json::Value>("value", payload, 404, None);
+        match error {
+            Err(Error::EndpointError(e)) => {
+                assert_eq!(e.kind(), EndpointErrorKind::EntityNotFound);
+                assert_eq!(e.http_status, Some(404));
+            }
+            r => panic!("expected an endpoint error, got {:?}", r),
+        }
+        let extra = r#"{"status":"error","id":404,"message":"Entity not found","extra":[]}"#;
+        assert!(matches!(
+            AuthenticatedApi::decode_response::<serde_json::Value>("value", extra, 404, None),
+            Err(Error::EndpointError(_))
+        ));
+
+        // Errors answered with a success status are still recognized
+        assert!(matches!(
+            AuthenticatedApi::decode_response::<Vec<u8>>("list", payload, 200, None),
+            Err(Error::EndpointError(_))
+        ));
+    }
+
+    #[test]
+    fn timestamps() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let timestamp: Timestamp = serde_json::from_str("1600000000").unwrap();
+        assert_eq!(timestamp, Timestamp(1_600_000_000));
+        assert_eq!(serde_json::to_string(&timestamp).unwrap(), "1600000000");
+        let time = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        assert_eq!(timestamp.as_system_time(), time);
+        assert_eq!(
+            Timestamp::from(time + Duration::from_millis(999)),
+            timestamp
+        );
+        assert_eq!(
+            Timestamp::from(UNIX_EPOCH - Duration::from_millis(1)),
+            Timestamp(-1)
+        );
+        assert_eq!(
+            Timestamp(-5).as_system_time(),
+            UNIX_EPOCH - Duration::from_secs(5)
+        );
+
+        let create =
+            password::CreatePassword::new("Example", "hunter2").edited(Timestamp::from(time));
+        let seconds = password::CreatePassword::new("Example", "hunter2").edited(1_600_000_000);
+        assert_eq!(
+            serde_json::to_value(&create).unwrap()["edited"],
+            serde_json::to_value(&seconds).unwrap()["edited"],
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_timestamps() {
+        use chrono::TimeZone;
+
+        let time = chrono::Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+        assert_eq!(Timestamp::from_datetime(time), Timestamp(1_600_000_000));
+        assert_eq!(Timestamp(1_600_000_000).as_datetime(), Some(time));
+        assert_eq!(Timestamp(i64::MAX).as_datetime(), None);
+
+        let create = password::CreatePassword::new("Example", "hunter2").edited(time);
+        assert_eq!(
+            serde_json::to_value(&create).unwrap()["edited"],
+            1_600_000_000
+        );
+    }
+
+    #[test]
+    fn details_round_trip() {
+        macro_rules! round_trip {
+            ($details:ty, $all:expr) => {
+                let all = <$details>::all();
+                assert_eq!(all.to_string(), $all);
+                for details in &[<$details>::new(), all] {
+                    assert_eq!(details.to_string().parse::<$details>().unwrap(), *details);
+                }
+            };
+        }
+        round_trip!(password::Details, "model+revisions+folder+tags+shares");
+        round_trip!(
+            folder::Details,
+            "model+revisions+parent+folders+passwords+tags"
+        );
+        round_trip!(tag::Details, "model+revisions+passwords");
+        round_trip!(share::Details, "model+password");
+
+        let details: password::Details = "folder + tags".parse().unwrap();
+        assert_eq!(details, password::Details::new().folder().tags());
+        match "model+folders".parse::<password::Details>() {
+            Err(Error::InvalidDetails { segment, allowed }) => {
+                assert_eq!(segment, "folders");
+                assert_eq!(allowed, "revisions+folder+tags+shares");
+            }
+            r => panic!("parsed unknown details: {:?}", r),
+        }
+        let error = "model+x".parse::<share::Details>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown details `x`, expected `model` followed by any of password"
+        );
+    }
+
+    #[test]
+    fn nc_login_url() {
+        let details = LoginDetails::from_nc_login_url(
+            "nc://login/user:alice&password:a%26b%3Ac&server:https://cloud.example.com/nextcloud",
+        )
+        .unwrap();
+        assert_eq!(details.login_name, "alice");
+        assert_eq!(details.app_password, "a&b:c");
+        assert_eq!(
+            details.server.as_str(),
+            "https://cloud.example.com/nextcloud/"
+        );
+
+        let message = |url| {
+            LoginDetails::from_nc_login_url(url)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            message("nc://login/user:alice"),
+            "invalid nc://login URL: missing password, server"
+        );
+        assert!(message("https://cloud.example.com").contains("nc://login/"));
+        assert!(message("nc://login/user:a&password:b&server:cloud").contains("absolute"));
+    }
+
+    #[test]
+    fn resume_secret() {
+        let state = offline_api().get_state();
+        assert_eq!(state.server().as_str(), "https://cloud.example.com/");
+        assert_eq!(state.login(), "alice");
+        assert_eq!(state.keepalive(), 600);
+        let full = serde_json::to_value(&state).unwrap();
+        assert_eq!(full["password"], "secret");
+
+        let mut state = state;
+        let secret = state.take_secret();
+        assert_eq!(secret.expose(), "secret");
+        let public = serde_json::to_string(&state).unwrap();
+        assert!(!public.contains("secret"));
+
+        let state: ResumeState = serde_json::from_str(&public).unwrap();
+        let state = state.with_secret(secret);
+        assert_eq!(serde_json::to_value(&state).unwrap(), full);
+    }
+
+    #[tokio::test]
+    async fn missing_secret() {
+        let mut state = offline_api().get_state();
+        let _secret = state.take_secret();
+        let resumed = AuthenticatedApi::resume_session(state).await;
+        assert!(matches!(resumed, Err(Error::MissingSecret)));
+    }
+
+    #[test]
+    fn login_details_debug() {
+        let details = LoginDetails {
+            server: Url::parse("https://cloud.example.com").unwrap(),
+            login_name: "user".into(),
+            app_password: "app-secret".into(),
+            api_base_override: None,
+            client_name: None,
+            master_password: Some("master-secret".into()),
+        };
+        let debug = format!("{:?}", details);
+        assert!(debug.contains("user"));
+        assert!(!debug.contains("secret"));
+        assert!(debug.contains(r#"master_password: Some("<redacted>")"#));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{Error, LoginDetails};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// A login flow v2 in progress, for applications polling the server themselves (for example
+/// from the event loop of a GUI, to let the user cancel). See
+/// [register_login_flow_2](LoginDetails::register_login_flow_2) to wait for the end of the flow
+/// instead.
+///
+/// The token of the flow expires after 20 minutes on the server.
+#[derive(Clone)]
+pub struct LoginFlow2 {
+    client: Client,
+    endpoint: Url,
+    token: String,
+    client_name: Option<String>,
+}
+
+impl std::fmt::Debug for LoginFlow2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoginFlow2")
+            .field("client", &self.client)
+            .field("endpoint", &self.endpoint)
+            .field("token", &crate::capture::REDACTED)
+            .field("client_name", &self.client_name)
+            .finish()
+    }
+}
+
+impl LoginFlow2 {
+    /// Start a login flow on `server`, returns the URL where the user grants the access
+    pub async fn start(server: Url) -> Result<(Url, Self), Error> {
+        Self::start_with_client(server, Client::new()).await
+    }
+
+    /// Like [start](Self::start), making the requests with `client`
+    pub async fn start_with_client(server: Url, client: Client) -> Result<(Url, Self), Error> {
+        Self::start_with_client_name(server, client, None).await
+    }
+
+    /// Like [start_with_client](Self::start_with_client), sending `client_name` as the
+    /// `User-Agent` of the requests. The server names the app password after it, see
+    /// [client_name](LoginDetails::client_name)
+    pub async fn start_with_client_name(
+        server: Url,
+        client: Client,
+        client_name: Option<&str>,
+    ) -> Result<(Url, Self), Error> {
+        #[derive(Deserialize)]
+        struct Poll {
+            token: String,
+            endpoint: Url,
+        }
+        #[derive(Deserialize)]
+        struct PollRequest {
+            poll: Poll,
+            login: Url,
+        }
+        let request = client.post(crate::join_url(&server, "index.php/login/v2"));
+        let resp = crate::with_client_name(request, client_name).send().await?;
+        if !resp.status().is_success() {
+            return Err(Error::LoginFlowError(resp.status().as_u16()));
+        }
+        let resp: PollRequest = resp.json().await?;
+        let flow = LoginFlow2 {
+            client,
+            endpoint: resp.poll.endpoint,
+            token: resp.poll.token,
+            client_name: client_name.map(Into::into),
+        };
+        Ok((resp.login, flow))
+    }
+
+    /// Poll the server once, `None` if the user has not granted the access yet
+    pub async fn poll_once(&self) -> Result<Option<LoginDetails>, Error> {
+        #[derive(Serialize)]
+        struct Token<'a> {
+            token: &'a str,
+        }
+        let request = self
+            .client
+            .post(self.endpoint.as_str())
+            .form(&Token { token: &self.token });
+        let poll = crate::with_client_name(request, self.client_name.as_deref())
+            .send()
+            .await?;
+        log::debug!("Polled endpoint");
+        match poll.status().as_u16() {
+            404 => Ok(None),
+            200 => {
+                let details: LoginDetails = poll.json().await?;
+                Ok(Some(LoginDetails {
+                    client_name: self.client_name.clone(),
+                    ..details
+                }))
+            }
+            code => Err(Error::LoginFlowError(code)),
+        }
+    }
+}
@@ -1,10 +1,15 @@
-use crate::{create_binding, create_calls, create_details, Error};
+use crate::{
+    create_binding, create_calls, create_details,
+    cse::{DecryptError, Keychain},
+    service::{GeneratePassword, GenerateResponse, MiniatureSize},
+    utils::check_length,
+    Error, Filter, Validate, ValidationError,
+};
 use serde::{Deserialize, Serialize};
-use serde_repr::{Deserialize_repr, Serialize_repr};
 
 create_calls! {
     PasswordApi where
-        Endpoint = "1.0/password",
+        Resource = "password",
         Details: Details,
         Type: Password,
         Create: CreatePassword,
@@ -41,14 +46,21 @@ create_calls! {
         ///
         /// Notes
         ///  - The property trashed will be set to false if not present
-        ///  - The list will not include hidden passwords
+        ///  - The list will not include hidden passwords, they can not be searched either: they are
+        ///    only reachable by their id with [get](Self::get)
         ///  - The list will not include suspended passwords where the folder or a parent folder is in the trash
+        ///  - Only the created, updated, edited, cseType, sseType, status, trashed and favorite
+        ///    properties can be searched. The server flags the passwords sharing a hash with the
+        ///    [UserRulesViolated](SecurityStatus::UserRulesViolated) status and the
+        ///    [Duplicate](StatusCode::Duplicate) status code, search them by status.
         pub async fn find(&self, criteria: Criteria, details: Option<Details>) -> Result<Vec<Type>, Error>;
 
         Create;
         /// The create action creates a new password with the given attributes.
         ///
         /// Notes
+        ///  - Without a folder, the password is created in the base folder
+        ///    ([BASE_FOLDER](crate::folder::BASE_FOLDER))
         ///  - If the password is not hidden and should be created in a hidden folder, it will be created in the base folder instead
         ///  - If the folder uuid is invalid or does not exist, the base folder uuid will be used instead
         ///  - If the edited argument is "0" or missing, the current time will be used
@@ -57,6 +69,8 @@ create_calls! {
         ///  - If the tags argument contains invalid tag ids, they will be ignored
         ///  - You can assign hidden tags to a not hidden password, but they will not be visible.
         ///  - Therefore another client might remove the tag by accident
+        ///  - On accounts using client side encryption, encrypt the password first with
+        ///    [encrypt](crate::cse::Encrypt::encrypt)
         pub async fn create(&self, value: Create) -> Result<Identifier, Error>;
 
         Update;
@@ -77,6 +91,8 @@ create_calls! {
         ///  - If the tags argument contains invalid tag ids, they will be ignored
         ///  - You can assign hidden tags to a not hidden password, but they will not be visible.
         ///  - Therefore another client might remove the tag by accident
+        ///  - On accounts using client side encryption, encrypt the update first with
+        ///    [encrypt](crate::cse::Encrypt::encrypt)
         pub async fn update(&self, value: Update) -> Result<Identifier, Error>;
 
         Delete;
@@ -104,6 +120,25 @@ create_calls! {
         ///  - Tag relations can not be restored
         ///  - Deleted passwords can not be restored
         pub async fn restore(&self, id: uuid::Uuid, revision: Option<uuid::Uuid>) -> Result<Identifier, Error>;
+
+        Patches: PasswordPatch;
+        /// Apply the changes of the patch, see [PasswordPatch]. This is the recommended way to modify
+        /// a password.
+        ///
+        /// Notes
+        ///  - An empty patch sends nothing and returns `None`
+        ///  - If the password changed on the server since the patch was created, this fails with
+        ///    [Conflict](crate::Error::Conflict)
+        pub async fn apply_patch(&self, patch: Patch) -> Result<Option<Identifier>, Error>;
+
+        Summaries: PasswordSummary;
+        /// List all the passwords like [list](PasswordApi::list), but only read the fields of
+        /// [PasswordSummary]. This is faster and uses less memory on large lists.
+        pub async fn list_summaries(&self) -> Result<Vec<Summary>, Error>;
+
+        Trash;
+        /// List the passwords in the trash, that [list](PasswordApi::list) leaves out
+        pub async fn trashed(&self, details: Option<Details>) -> Result<Vec<Type>, Error>;
     }
 }
 
@@ -116,39 +151,333 @@ create_details! {
     }
 }
 
+/// The fields of a password needed to display it in a list, see
+/// [list_summaries](PasswordApi::list_summaries)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PasswordSummary {
+    pub id: uuid::Uuid,
+    pub revision: uuid::Uuid,
+    pub label: String,
+    pub username: String,
+    pub url: String,
+    pub favorite: bool,
+    pub status: SecurityStatus,
+}
+
 /// Identifies a password by it's id and revision
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct PasswordIdentifier {
     pub id: uuid::Uuid,
     pub revision: uuid::Uuid,
 }
 
 /// Identifies a trashed Password if the revision in `Some`, else identifies a deleted Password
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct TrashedIdentifier {
     pub id: uuid::Uuid,
     pub revision: Option<uuid::Uuid>,
 }
 
 /// The security status of the password
-#[derive(Serialize_repr, Deserialize_repr, Debug)]
-#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SecurityStatus {
-    Ok = 0,
-    UserRulesViolated = 1,
-    Breached = 2,
+    Ok,
+    UserRulesViolated,
+    Breached,
+    /// A status unknown to this crate (refused with the `strict` feature)
+    Unknown(u8),
+}
+
+impl SecurityStatus {
+    pub fn value(self) -> u8 {
+        match self {
+            SecurityStatus::Ok => 0,
+            SecurityStatus::UserRulesViolated => 1,
+            SecurityStatus::Breached => 2,
+            SecurityStatus::Unknown(value) => value,
+        }
+    }
+}
+
+impl Serialize for SecurityStatus {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_u8(self.value())
+    }
+}
+
+impl<'de> Deserialize<'de> for SecurityStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(match u8::deserialize(deserializer)? {
+            0 => SecurityStatus::Ok,
+            1 => SecurityStatus::UserRulesViolated,
+            2 => SecurityStatus::Breached,
+            other => crate::utils::unknown_value("status", other, SecurityStatus::Unknown(other))?,
+        })
+    }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+/// Specific code of the [SecurityStatus]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StatusCode {
-    #[serde(rename = "GOOD")]
     Good,
-    #[serde(rename = "OUTDATED")]
     Outdated,
-    #[serde(rename = "DUPLICATE")]
     Duplicate,
-    #[serde(rename = "BREACHED")]
     Breached,
+    /// A code unknown to this crate (refused with the `strict` feature)
+    Other(String),
+}
+
+impl StatusCode {
+    pub fn as_str(&self) -> &str {
+        match self {
+            StatusCode::Good => "GOOD",
+            StatusCode::Outdated => "OUTDATED",
+            StatusCode::Duplicate => "DUPLICATE",
+            StatusCode::Breached => "BREACHED",
+            StatusCode::Other(code) => code,
+        }
+    }
+}
+
+impl Serialize for StatusCode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for StatusCode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let code = String::deserialize(deserializer)?;
+        Ok(match code.as_str() {
+            "GOOD" => StatusCode::Good,
+            "OUTDATED" => StatusCode::Outdated,
+            "DUPLICATE" => StatusCode::Duplicate,
+            "BREACHED" => StatusCode::Breached,
+            _ => crate::utils::unknown_value("statusCode", &code, StatusCode::Other(code.clone()))?,
+        })
+    }
+}
+
+/// Maximum length of the label of a password
+pub const MAX_LABEL_LENGTH: usize = 64;
+/// Maximum length of the username of a password
+pub const MAX_USERNAME_LENGTH: usize = 64;
+/// Maximum length of a password
+pub const MAX_PASSWORD_LENGTH: usize = 256;
+/// Maximum length of the url of a password
+pub const MAX_URL_LENGTH: usize = 2048;
+/// Maximum length of the notes of a password
+pub const MAX_NOTES_LENGTH: usize = 4096;
+/// Maximum number of [CustomFields] of a password
+pub const MAX_CUSTOM_FIELDS: usize = 20;
+/// Maximum length of the JSON of the [CustomFields] of a password
+pub const MAX_CUSTOM_FIELDS_LENGTH: usize = 8192;
+
+/// The kind of value of a [CustomField]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CustomFieldType {
+    Text,
+    /// A value hidden by default, like a password
+    Secret,
+    Email,
+    Url,
+    /// The path of a file stored in nextcloud
+    File,
+    /// A value for other applications, not shown to the user
+    Data,
+    /// A type unknown to this crate (refused with the `strict` feature)
+    Other(String),
+}
+
+impl CustomFieldType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            CustomFieldType::Text => "text",
+            CustomFieldType::Secret => "secret",
+            CustomFieldType::Email => "email",
+            CustomFieldType::Url => "url",
+            CustomFieldType::File => "file",
+            CustomFieldType::Data => "data",
+            CustomFieldType::Other(kind) => kind,
+        }
+    }
+}
+
+impl Serialize for CustomFieldType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for CustomFieldType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let kind = String::deserialize(deserializer)?;
+        Ok(match kind.as_str() {
+            "text" => CustomFieldType::Text,
+            "secret" => CustomFieldType::Secret,
+            "email" => CustomFieldType::Email,
+            "url" => CustomFieldType::Url,
+            "file" => CustomFieldType::File,
+            "data" => CustomFieldType::Data,
+            _ => crate::utils::unknown_value("type", &kind, CustomFieldType::Other(kind.clone()))?,
+        })
+    }
+}
+
+/// A field added to a password by the user
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CustomField {
+    pub label: String,
+    #[serde(rename = "type")]
+    pub kind: CustomFieldType,
+    pub value: String,
+}
+
+impl CustomField {
+    pub fn new(label: impl Into<String>, kind: CustomFieldType, value: impl Into<String>) -> Self {
+        CustomField {
+            label: label.into(),
+            kind,
+            value: value.into(),
+        }
+    }
+}
+
+/// The custom fields of a password. The API sends them as a string containing a JSON array, it
+/// is parsed when reading the password.
+///
+/// Custom fields which can't be parsed (encrypted on the client, see
+/// [decrypt](Password::decrypt), or in a format unknown to this crate) are kept as they were
+/// received, see [raw](CustomFields::raw).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomFields(CustomFieldsRepr);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CustomFieldsRepr {
+    Fields(Vec<CustomField>),
+    Raw(String),
+}
+
+impl Default for CustomFields {
+    fn default() -> Self {
+        CustomFields(CustomFieldsRepr::Fields(Vec::new()))
+    }
+}
+
+impl CustomFields {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Use the string sent to the server as is, it is parsed if possible
+    pub fn from_raw(raw: impl Into<String>) -> Self {
+        let raw = raw.into();
+        if raw.trim().is_empty() {
+            return Self::new();
+        }
+        match serde_json::from_str(&raw) {
+            Ok(fields) => CustomFields(CustomFieldsRepr::Fields(fields)),
+            Err(_) => CustomFields(CustomFieldsRepr::Raw(raw)),
+        }
+    }
+
+    /// The string sent to the server
+    pub fn raw(&self) -> std::borrow::Cow<'_, str> {
+        match &self.0 {
+            CustomFieldsRepr::Fields(fields) => serde_json::to_string(fields)
+                .expect("custom fields are always serializable")
+                .into(),
+            CustomFieldsRepr::Raw(raw) => raw.as_str().into(),
+        }
+    }
+
+    /// The fields, `None` if they could not be parsed
+    pub fn fields(&self) -> Option<&[CustomField]> {
+        match &self.0 {
+            CustomFieldsRepr::Fields(fields) => Some(fields),
+            CustomFieldsRepr::Raw(_) => None,
+        }
+    }
+
+    /// The first field with this label
+    pub fn get(&self, label: &str) -> Option<&CustomField> {
+        self.fields()?.iter().find(|field| field.label == label)
+    }
+
+    /// Add a field, failing if the fields could not be parsed or if the fields would exceed the
+    /// limits of the server
+    pub fn with(self, field: CustomField) -> Result<Self, Error> {
+        let mut fields = self.into_fields()?;
+        fields.push(field);
+        let fields = CustomFields(CustomFieldsRepr::Fields(fields));
+        fields.validate()?;
+        Ok(fields)
+    }
+
+    /// Remove the fields with this label, fields that could not be parsed are kept unchanged
+    pub fn without(self, label: &str) -> Self {
+        match self.0 {
+            CustomFieldsRepr::Fields(mut fields) => {
+                fields.retain(|field| field.label != label);
+                CustomFields(CustomFieldsRepr::Fields(fields))
+            }
+            raw => CustomFields(raw),
+        }
+    }
+
+    /// Check the limits of the server: at most [MAX_CUSTOM_FIELDS] fields, and
+    /// [MAX_CUSTOM_FIELDS_LENGTH] characters of JSON
+    pub fn validate(&self) -> Result<(), Error> {
+        let count = self.fields().map(<[_]>::len).unwrap_or(0);
+        let length = self.raw().chars().count();
+        if count > MAX_CUSTOM_FIELDS || length > MAX_CUSTOM_FIELDS_LENGTH {
+            return Err(Error::CustomFieldsTooLarge { count, length });
+        }
+        Ok(())
+    }
+
+    fn into_fields(self) -> Result<Vec<CustomField>, Error> {
+        match self.0 {
+            CustomFieldsRepr::Fields(fields) => Ok(fields),
+            CustomFieldsRepr::Raw(raw) => Ok(serde_json::from_str(&raw)?),
+        }
+    }
+}
+
+impl Serialize for CustomFields {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.raw())
+    }
+}
+
+impl<'de> Deserialize<'de> for CustomFields {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(CustomFields::from_raw(String::deserialize(deserializer)?))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -166,6 +495,13 @@ impl FolderInfo {
     pub fn get(&self) -> &FolderInfoKind {
         &self.0
     }
+    /// The id of the folder
+    pub fn id(&self) -> uuid::Uuid {
+        match &self.0 {
+            FolderInfoKind::Id(id) => *id,
+            FolderInfoKind::Data(folder) => folder.id,
+        }
+    }
     pub fn new(id: uuid::Uuid) -> Self {
         Self(FolderInfoKind::Id(id))
     }
@@ -174,12 +510,13 @@ impl FolderInfo {
 
 create_binding! {
     #[derive(Serialize, Deserialize, Debug)]
-    pub struct Password {
+    pub struct Password [extra] {
         /// User defined label of the password
         pub label: String [create(required) versioned(true) update(required)],
         /// Username associated with the password
         pub username: String [create(optional) versioned(true) update(optional)],
-        /// The actual password
+        /// The actual password, use [Password::reveal] or [Password::password_masked] to access it
+        #[doc(hidden)]
         pub password: String [create(required) versioned(true) update(required)],
         /// Url of the website
         pub url: String [create(optional) versioned(true) update(optional)],
@@ -187,9 +524,11 @@ create_binding! {
         pub notes: String [create(optional) versioned(true) update(optional)],
         /// Custom fields created by the user.
         #[serde(rename = "customFields")]
-        pub custom_fields: String [create(optional) versioned(true) update(optional)],
-        /// SHA1 hash of the password
-        pub hash: String [create(required) versioned(true) update(required)],
+        pub custom_fields: CustomFields [create(optional) versioned(true) update(optional)],
+        /// SHA1 hash of the password, see [hash_password]. It is computed when creating or
+        /// updating a password if it is not set, with the length of the
+        /// [user setting](crate::settings::ClientCapabilities::hash_length).
+        pub hash: String [create(optional) versioned(true) update(optional)],
         /// Type of the used client side encryption
         #[serde(rename = "cseType")]
         pub cse_type: String [create(optional) versioned(true) update(optional) search],
@@ -204,12 +543,12 @@ create_binding! {
         /// True if the user has marked the password as favorite
         pub favorite: bool [create(optional) versioned(true) update(optional) search],
         /// Unix timestamp when the user last changed the password
-        pub edited: i64 [create(optional) versioned(true) update(optional) search],
+        pub edited: crate::UnixTime [create(optional) versioned(true) update(optional) search],
 
         /// True if the password is in the trash
         pub trashed: bool [ versioned(true) search],
         /// Unix timestamp when the password was updated
-        pub updated: i64 [versioned(true) search],
+        pub updated: crate::UnixTime [versioned(true) search],
         /// Name of the client which created this revision
         pub client: String [ versioned(true)],
         /// Security status level of the password
@@ -222,22 +561,1395 @@ create_binding! {
         pub id: uuid::Uuid [update(required) versioned(false)],
         /// UUID of the current revision
         pub revision: uuid::Uuid [versioned(false)],
-        /// UUID of the share if the password was shared by someone else with the user
-        pub share: Option<uuid::Uuid> [versioned(false)],
+        /// UUID of the share if the password was shared by someone else with the user, or the
+        /// share itself with the shares details
+        pub share: Option<crate::share::ShareInfo> [versioned(false)],
         /// True if the password is shared with other users
         pub shared: bool [versioned(false)],
         /// Specifies if the encrypted properties can be changed. Might be false for shared passwords
         pub editable: bool [versioned(false)],
         /// Unix timestamp when the password was created
-        pub created: i64 [search versioned(false)],
-        /// Either the UUID of the current folder of the password or the folder model
+        pub created: crate::UnixTime [search versioned(false)],
+        /// Either the UUID of the current folder of the password or the folder model, the
+        /// [base folder](crate::folder::BASE_FOLDER) by default
         pub folder: FolderInfo [create(optional) update(optional) versioned(false)],
 
         /// Adds the tags property filled with the base model of all tags. Hidden tags are not included in this list if the password is not hidden
-        pub tags: Option<Vec<()>> [versioned(false)],
+        pub tags: Option<Vec<crate::tag::Tag>> [versioned(false)],
         /// Adds the shares property filled with the base model of all shares with other users. Fills the share property with the base model of the original share if available
-        pub shares: Option<Vec<()>> [versioned(false)],
+        pub shares: Option<Vec<crate::share::Share>> [versioned(false)],
         /// Adds the revisions property which contains all revisions. A revision consists of all properties marked as versioned and its own created property
         pub revisions: Option<Vec<VersionedPassword>> [versioned(false)],
     }
 }
+
+/// Prefix of the label of the custom fields holding external identifiers, see
+/// [external_id](Password::external_id)
+pub const EXTERNAL_ID_PREFIX: &str = "ext:";
+
+/// Label of the custom field holding the external identifier of `namespace`. `%` and `:` are
+/// percent-escaped in the namespace so that namespaces can't collide
+pub fn external_id_label(namespace: &str) -> String {
+    let mut label = EXTERNAL_ID_PREFIX.to_string();
+    for c in namespace.chars() {
+        match c {
+            '%' => label.push_str("%25"),
+            ':' => label.push_str("%3A"),
+            c => label.push(c),
+        }
+    }
+    label
+}
+
+fn find_external_id(custom_fields: &CustomFields, namespace: &str) -> Option<String> {
+    let label = external_id_label(namespace);
+    custom_fields.get(&label).map(|field| field.value.clone())
+}
+
+fn with_external_id(
+    custom_fields: Option<CustomFields>,
+    namespace: &str,
+    id: &str,
+) -> Result<CustomFields, Error> {
+    let label = external_id_label(namespace);
+    custom_fields
+        .unwrap_or_default()
+        .without(&label)
+        .with(CustomField::new(label, CustomFieldType::Data, id))
+}
+
+impl Password {
+    /// The identifier stored by [CreatePassword::external_id] for this namespace
+    pub fn external_id(&self, namespace: &str) -> Option<String> {
+        find_external_id(&self.versioned.custom_fields, namespace)
+    }
+
+    /// A placeholder for the password. It is always [MASK_LENGTH] bullets long so it does not
+    /// leak the length of the password
+    pub fn password_masked(&self) -> String {
+        "\u{2022}".repeat(MASK_LENGTH)
+    }
+
+    /// Access the password, this is the only way to access it that does not show up in `Debug`
+    /// output
+    pub fn reveal(&self) -> Secret<'_> {
+        Secret(&self.versioned.password)
+    }
+
+    /// Decrypt the fields encrypted on the client (label, username, password, url, notes and
+    /// custom fields) with the keychain. Passwords that are not encrypted are returned unchanged.
+    ///
+    /// Notes
+    ///  - The decrypted password keeps its `cse_type` and `cse_key`, so it must not be sent back
+    ///    to the server as is
+    pub fn decrypt(mut self, keychain: &Keychain) -> Result<Password, DecryptError> {
+        match self.versioned.cse_type.as_str() {
+            crate::cse::CSE_NONE => return Ok(self),
+            crate::cse::CSE_V1R1 => (),
+            other => return Err(DecryptError::UnsupportedType(other.into())),
+        }
+        let versioned = &mut self.versioned;
+        let key = &versioned.cse_key;
+        for field in [
+            &mut versioned.label,
+            &mut versioned.username,
+            &mut versioned.password,
+            &mut versioned.url,
+            &mut versioned.notes,
+        ] {
+            *field = keychain.decrypt_field(key, field)?;
+        }
+        versioned.custom_fields =
+            CustomFields::from_raw(keychain.decrypt_field(key, &versioned.custom_fields.raw())?);
+        Ok(self)
+    }
+}
+
+/// Length of [Password::password_masked]
+pub const MASK_LENGTH: usize = 8;
+
+/// A secret borrowed from an object. It can be displayed to reveal it, but it does not implement
+/// `Debug` so it can't end up in logs by accident.
+pub struct Secret<'a>(&'a str);
+
+impl<'a> Secret<'a> {
+    pub fn expose(&self) -> &'a str {
+        self.0
+    }
+
+    /// Copy the secret, the copy is zeroed when it is dropped
+    pub fn to_zeroizing(&self) -> OwnedSecret {
+        OwnedSecret(self.0.to_owned())
+    }
+}
+
+impl std::fmt::Display for Secret<'_> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.write_str(self.0)
+    }
+}
+
+/// A copy of a [Secret], zeroed when dropped. Like [Secret] it does not implement `Debug`
+pub struct OwnedSecret(String);
+
+impl OwnedSecret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for OwnedSecret {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.write_str(&self.0)
+    }
+}
+
+impl Drop for OwnedSecret {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.0);
+    }
+}
+
+/// Length of the full [hash_password] of a password
+pub const HASH_LENGTH: usize = 40;
+
+/// The hash of a password expected by the API: the hexadecimal SHA1 of the password, truncated
+/// to `length` characters if the user reduced the length of the hashes in the
+/// `user.password.security.hash` setting (0 keeps no hash).
+pub fn hash_password(password: &str, length: usize) -> String {
+    let mut hash = hex::encode(crate::sha1::sha1(password.as_bytes()));
+    hash.truncate(length);
+    hash
+}
+
+impl CreatePassword {
+    /// Use the first `length` characters of the hash, see [hash_password]. Without it the hash
+    /// has the length of the [user setting](crate::settings::ClientCapabilities::hash_length).
+    ///
+    /// On accounts using client side encryption, the hash is computed before the encryption. Set
+    /// it with [hash](Self::hash) when encrypting the password without this crate.
+    pub fn with_hash_length(self, length: usize) -> Self {
+        let hash = hash_password(&self.password, length);
+        self.hash(hash)
+    }
+
+    pub(crate) fn with_computed_hash(self, length: usize) -> Self {
+        match self.hash {
+            Some(_) => self,
+            None => self.with_hash_length(length),
+        }
+    }
+
+    /// Store a stable identifier for the password in the custom fields, this allows tools to
+    /// recognize the passwords they created (see [find_by_external_id](PasswordApi::find_by_external_id)).
+    ///
+    /// Fails if the custom fields are not valid JSON
+    pub fn external_id(self, namespace: &str, id: &str) -> Result<Self, Error> {
+        let custom_fields = with_external_id(self.custom_fields, namespace, id)?;
+        Ok(Self {
+            custom_fields: Some(custom_fields),
+            ..self
+        })
+    }
+}
+
+impl UpdatePassword {
+    /// See [CreatePassword::with_hash_length]
+    pub fn with_hash_length(self, length: usize) -> Self {
+        let hash = hash_password(&self.password, length);
+        self.hash(hash)
+    }
+
+    pub(crate) fn with_computed_hash(self, length: usize) -> Self {
+        match self.hash {
+            Some(_) => self,
+            None => self.with_hash_length(length),
+        }
+    }
+
+    /// Set the tags of the password. The server keeps the current tags if the list is empty, see
+    /// [remove_tag](PasswordApi::remove_tag) to remove the last one.
+    pub fn tags(mut self, tags: impl IntoIterator<Item = impl std::fmt::Display>) -> Self {
+        let tags: Vec<_> = tags.into_iter().map(|tag| tag.to_string()).collect();
+        self.extra.insert("tags".into(), tags.into());
+        self
+    }
+
+    /// Store a stable identifier for the password in the custom fields, see
+    /// [CreatePassword::external_id].
+    ///
+    /// The custom fields must be set before calling this method, or the other custom fields will
+    /// be erased
+    pub fn external_id(self, namespace: &str, id: &str) -> Result<Self, Error> {
+        let custom_fields = with_external_id(self.custom_fields, namespace, id)?;
+        Ok(Self {
+            custom_fields: Some(custom_fields),
+            ..self
+        })
+    }
+}
+
+/// An update that keeps all the current values of the password
+pub(crate) fn update_from(password: &Password) -> UpdatePassword {
+    let versioned = &password.versioned;
+    UpdatePassword::new(
+        versioned.label.clone(),
+        versioned.password.clone(),
+        password.id,
+    )
+    .hash(versioned.hash.clone())
+    .username(versioned.username.clone())
+    .url(versioned.url.clone())
+    .notes(versioned.notes.clone())
+    .custom_fields(versioned.custom_fields.clone())
+    .cse_type(versioned.cse_type.clone())
+    .cse_key(versioned.cse_key.clone())
+    .hidden(versioned.hidden)
+    .favorite(versioned.favorite)
+    .edited(versioned.edited)
+    .folder(FolderInfo::new(password.folder.id()))
+    .extra(password.extra.clone())
+}
+
+/// Keep all the current values of the password, including its tags if they were fetched with
+/// [Details::tags]
+impl From<Password> for UpdatePassword {
+    fn from(password: Password) -> Self {
+        let update = update_from(&password);
+        match &password.tags {
+            Some(tags) => update.tags(tags.iter().map(|tag| &tag.id)),
+            None => update,
+        }
+    }
+}
+
+impl PasswordPatch {
+    /// Start a patch of this password. Applying the patch fails if the password was modified
+    /// on the server in the meantime.
+    pub fn new(original: &Password) -> Self {
+        Self::from_update(update_from(original).expected_revision(original.revision))
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.update.label = label.into();
+        self.mark("label");
+        self
+    }
+
+    /// Change the password, its hash is computed with the length of the current hash
+    pub fn password(self, password: impl Into<String>) -> Self {
+        let password = password.into();
+        let length = self.update.hash.as_deref().map_or(HASH_LENGTH, str::len);
+        let hash = hash_password(&password, length);
+        self.password_with_hash(password, hash)
+    }
+
+    /// Change the password with a hash computed by the caller, for passwords encrypted on the
+    /// client. The hash must be the SHA1 of the plain password, see [hash_password].
+    pub fn password_with_hash(
+        mut self,
+        password: impl Into<String>,
+        hash: impl Into<String>,
+    ) -> Self {
+        self.update.password = password.into();
+        self.update.hash = Some(hash.into());
+        self.mark("password");
+        self.mark("hash");
+        self
+    }
+}
+
+/// Options of [move_matching](PasswordApi::move_matching)
+#[derive(Debug, Clone)]
+pub struct MoveOptions {
+    /// Maximum number of updates running at the same time
+    pub concurrency: usize,
+    /// Only report what would be moved
+    pub dry_run: bool,
+}
+
+impl Default for MoveOptions {
+    fn default() -> Self {
+        MoveOptions {
+            concurrency: 4,
+            dry_run: false,
+        }
+    }
+}
+
+/// Something the server will do differently than asked by
+/// [move_matching](PasswordApi::move_matching)
+#[derive(Debug)]
+pub enum MoveWarning {
+    /// The password is not hidden but the folder is, the server will move it to the base folder
+    HiddenFolder { password: uuid::Uuid },
+}
+
+/// A versioned property that differs between two revisions, see [diff]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    /// Name of the property in the API, like `customFields`
+    pub field: String,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+/// The versioned properties that changed between two revisions, sorted by name
+pub fn diff(before: &VersionedPassword, after: &VersionedPassword) -> Vec<FieldChange> {
+    let to_map = |versioned| match serde_json::to_value(versioned) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => unreachable!("versioned properties are always serialized as a map"),
+    };
+    let before = to_map(before);
+    let mut after = to_map(after);
+    before
+        .into_iter()
+        .filter_map(|(field, before)| {
+            let after = after.remove(&field)?;
+            if before == after {
+                None
+            } else {
+                Some(FieldChange {
+                    field,
+                    before,
+                    after,
+                })
+            }
+        })
+        .collect()
+}
+
+/// Check the limits of the fields shared by [CreatePassword] and [UpdatePassword]
+fn validate_password(
+    label: &str,
+    password: &str,
+    optional: [(&'static str, &Option<String>, usize); 3],
+    custom_fields: &Option<CustomFields>,
+) -> Result<(), ValidationError> {
+    check_length("label", label, MAX_LABEL_LENGTH)?;
+    check_length("password", password, MAX_PASSWORD_LENGTH)?;
+    for (field, value, limit) in optional.iter() {
+        if let Some(value) = value {
+            check_length(field, value, *limit)?;
+        }
+    }
+    if let Some(custom_fields) = custom_fields {
+        check_length(
+            "customFields",
+            &custom_fields.raw(),
+            MAX_CUSTOM_FIELDS_LENGTH,
+        )?;
+        let count = custom_fields.fields().map(<[_]>::len).unwrap_or(0);
+        if count > MAX_CUSTOM_FIELDS {
+            return Err(ValidationError {
+                field: "customFields",
+                limit: MAX_CUSTOM_FIELDS,
+                actual: count,
+            });
+        }
+    }
+    Ok(())
+}
+
+impl Validate for CreatePassword {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_password(
+            &self.label,
+            &self.password,
+            [
+                ("username", &self.username, MAX_USERNAME_LENGTH),
+                ("url", &self.url, MAX_URL_LENGTH),
+                ("notes", &self.notes, MAX_NOTES_LENGTH),
+            ],
+            &self.custom_fields,
+        )
+    }
+
+    fn cse_type(&self) -> Option<&str> {
+        self.cse_type.as_deref()
+    }
+}
+
+impl Validate for UpdatePassword {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_password(
+            &self.label,
+            &self.password,
+            [
+                ("username", &self.username, MAX_USERNAME_LENGTH),
+                ("url", &self.url, MAX_URL_LENGTH),
+                ("notes", &self.notes, MAX_NOTES_LENGTH),
+            ],
+            &self.custom_fields,
+        )
+    }
+
+    fn cse_type(&self) -> Option<&str> {
+        self.cse_type.as_deref()
+    }
+}
+
+/// A password listed in a [SecurityReport]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportEntry {
+    pub id: uuid::Uuid,
+    pub label: String,
+}
+
+impl From<&Password> for ReportEntry {
+    fn from(password: &Password) -> Self {
+        ReportEntry {
+            id: password.id,
+            label: password.versioned.label.clone(),
+        }
+    }
+}
+
+/// The passwords that should be changed, see [security_report](PasswordApi::security_report)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SecurityReport {
+    /// Groups of at least two passwords with the same hash
+    pub duplicates: Vec<Vec<ReportEntry>>,
+    /// Passwords found in a breach
+    pub breached: Vec<ReportEntry>,
+    /// Passwords violating the security rules of the user
+    pub weak: Vec<ReportEntry>,
+    /// Passwords not changed since more than the
+    /// [check_for_old_passwords](crate::settings::SettingsFetcher::check_for_old_passwords) days
+    pub old: Vec<ReportEntry>,
+}
+
+impl SecurityReport {
+    /// Build the report from the passwords, `max_age` is the number of days after which a
+    /// password is old (0 to disable), and `now` a unix timestamp.
+    pub fn new(passwords: &[Password], max_age: i64, now: i64) -> Self {
+        let mut report = SecurityReport::default();
+        let mut by_hash: std::collections::HashMap<&str, Vec<ReportEntry>> = Default::default();
+        let mut hashes = Vec::new();
+        for password in passwords {
+            let hash = password.versioned.hash.as_str();
+            if !hash.is_empty() {
+                let group = by_hash.entry(hash).or_default();
+                if group.is_empty() {
+                    hashes.push(hash);
+                }
+                group.push(password.into());
+            }
+            match password.versioned.status {
+                SecurityStatus::Breached => report.breached.push(password.into()),
+                SecurityStatus::UserRulesViolated => report.weak.push(password.into()),
+                _ => (),
+            }
+            if max_age > 0 && password.versioned.edited < now - max_age * 24 * 60 * 60 {
+                report.old.push(password.into());
+            }
+        }
+        report.duplicates = hashes
+            .into_iter()
+            .filter_map(|hash| by_hash.remove(hash))
+            .filter(|group| group.len() > 1)
+            .collect();
+        report
+    }
+
+    /// True if no password needs to be changed
+    pub fn is_empty(&self) -> bool {
+        self.duplicates.is_empty()
+            && self.breached.is_empty()
+            && self.weak.is_empty()
+            && self.old.is_empty()
+    }
+}
+
+/// A password matching a url, see [find_for_url](PasswordApi::find_for_url)
+#[derive(Debug)]
+pub struct ScoredPassword {
+    pub password: Password,
+    pub score: crate::UrlMatch,
+}
+
+/// Result of [move_matching](PasswordApi::move_matching)
+#[derive(Debug, Default)]
+pub struct MoveReport {
+    /// Passwords that were moved (or would be in a dry run)
+    pub moved: Vec<PasswordIdentifier>,
+    pub failed: Vec<(uuid::Uuid, Error)>,
+    pub warnings: Vec<MoveWarning>,
+}
+
+/// Result of [delete_with_shares](PasswordApi::delete_with_shares)
+#[derive(Debug, Default)]
+pub struct DeleteWithSharesReport {
+    /// Shares of the password owned by the user that were deleted
+    pub deleted_shares: Vec<uuid::Uuid>,
+    pub failed_shares: Vec<(uuid::Uuid, Error)>,
+    /// The share through which the password was shared with the user. Only its owner can
+    /// delete it, so it is left alone.
+    pub received_share: Option<uuid::Uuid>,
+    /// The deleted password, `None` if a share could not be deleted (the password is then kept)
+    pub password: Option<TrashedIdentifier>,
+}
+
+impl<'a> PasswordApi<'a> {
+    /// List all the passwords like [list](PasswordApi::list), and decrypt them with the
+    /// [keychain](crate::AuthenticatedApi::keychain) of the session (see [Password::decrypt])
+    ///
+    /// Notes
+    ///  - Encrypted passwords fail with [DecryptError::NoKeychain] if the session has no keychain
+    pub async fn list_decrypted(&self, details: Option<Details>) -> Result<Vec<Password>, Error> {
+        let keychain = self.api.keychain();
+        let mut decrypted = Vec::new();
+        for password in self.list(details).await? {
+            let password = match keychain {
+                Some(keychain) => password.decrypt(keychain)?,
+                None if password.versioned.cse_type == crate::cse::CSE_NONE => password,
+                None => return Err(DecryptError::NoKeychain.into()),
+            };
+            decrypted.push(password);
+        }
+        Ok(decrypted)
+    }
+
+    /// Add a tag to the password, keeping its other properties
+    ///
+    /// Notes
+    ///  - The hidden tags of a password that is not hidden are not visible, the server keeps them
+    pub async fn add_tag(
+        &self,
+        password: uuid::Uuid,
+        tag: uuid::Uuid,
+    ) -> Result<PasswordIdentifier, Error> {
+        let tag = tag.to_string();
+        self.change_tags(password, |tags| {
+            if !tags.contains(&tag) {
+                tags.push(tag)
+            }
+        })
+        .await
+    }
+
+    /// Remove a tag from the password, keeping its other properties
+    ///
+    /// Notes
+    ///  - The hidden tags of a password that is not hidden are not visible, the server keeps them
+    pub async fn remove_tag(
+        &self,
+        password: uuid::Uuid,
+        tag: uuid::Uuid,
+    ) -> Result<PasswordIdentifier, Error> {
+        let tag = tag.to_string();
+        self.change_tags(password, |tags| tags.retain(|id| *id != tag))
+            .await
+    }
+
+    async fn change_tags(
+        &self,
+        id: uuid::Uuid,
+        change: impl FnOnce(&mut Vec<String>),
+    ) -> Result<PasswordIdentifier, Error> {
+        let password = self.get(Some(Details::new().tags()), id).await?;
+        let mut tags: Vec<_> = (password.tags.iter().flatten())
+            .map(|tag| tag.id.clone())
+            .collect();
+        change(&mut tags);
+        if tags.is_empty() {
+            // An empty list keeps the tags, the server ignores the invalid ids instead
+            tags.push(uuid::Uuid::nil().to_string());
+        }
+        self.update(update_from(&password).tags(tags)).await
+    }
+
+    /// Delete the shares of the password owned by the user, then delete the password (see
+    /// [delete](PasswordApi::delete)).
+    ///
+    /// Notes
+    ///  - If a share can't be deleted the password is not deleted, check
+    ///    [failed_shares](DeleteWithSharesReport::failed_shares)
+    pub async fn delete_with_shares(
+        &self,
+        id: uuid::Uuid,
+        revision: Option<uuid::Uuid>,
+    ) -> Result<DeleteWithSharesReport, Error> {
+        use crate::share::{PasswordInfo, ShareInfo, ShareSearch};
+
+        let password = self.get(None, id).await?;
+        let mut report = DeleteWithSharesReport {
+            received_share: password.share.as_ref().map(ShareInfo::id),
+            ..Default::default()
+        };
+        if password.shared || password.share.is_some() {
+            let search = ShareSearch::new().and_password(crate::SearchQuery::new(
+                PasswordInfo::new(id),
+                crate::QueryKind::Exact,
+            ))?;
+            for share in self.api.share().find(search, None).await? {
+                if Some(share.id) == report.received_share {
+                    continue;
+                }
+                match self.api.share().delete(share.id).await {
+                    Ok(_) => report.deleted_shares.push(share.id),
+                    Err(e) => report.failed_shares.push((share.id, e)),
+                }
+            }
+        }
+        if report.failed_shares.is_empty() {
+            report.password = Some(self.delete(id, revision).await?);
+        }
+        Ok(report)
+    }
+
+    /// Fetch a revision of the password, see [Details::revisions]. It can be compared to another
+    /// one with [diff], and restored with [restore](PasswordApi::restore).
+    pub async fn get_revision(
+        &self,
+        password_id: uuid::Uuid,
+        revision_id: uuid::Uuid,
+    ) -> Result<VersionedPassword, Error> {
+        #[derive(Serialize)]
+        struct Show {
+            id: uuid::Uuid,
+            details: String,
+        }
+        #[derive(Deserialize)]
+        struct Revision {
+            id: uuid::Uuid,
+            #[serde(flatten)]
+            versioned: VersionedPassword,
+        }
+        #[derive(Deserialize)]
+        struct WithRevisions {
+            revisions: Vec<Revision>,
+        }
+        let request = Show {
+            id: password_id,
+            details: Details::new().revisions().to_string(),
+        };
+        let password: WithRevisions = self
+            .api
+            .passwords_post_read(self.api.endpoint("password", "show"), request)
+            .await?;
+        password
+            .revisions
+            .into_iter()
+            .find(|revision| revision.id == revision_id)
+            .map(|revision| revision.versioned)
+            .ok_or(Error::RevisionNotFound {
+                id: password_id,
+                revision: revision_id,
+            })
+    }
+
+    /// Move the password to the folder, keeping its other properties
+    ///
+    /// Notes
+    ///  - The folder is fetched first, to fail if it does not exist instead of letting the server
+    ///    move the password to the base folder
+    ///  - A password that is not hidden can't be moved to a hidden folder, the server would move
+    ///    it to the base folder instead. This fails with [Error::HiddenFolder].
+    pub async fn move_to_folder(
+        &self,
+        password_id: uuid::Uuid,
+        folder_id: uuid::Uuid,
+    ) -> Result<PasswordIdentifier, Error> {
+        let folder = self.api.folder().get(None, folder_id).await?;
+        let password = self.get(None, password_id).await?;
+        if folder.versioned.hidden && !password.versioned.hidden {
+            return Err(Error::HiddenFolder {
+                object: password_id,
+                folder: folder_id,
+            });
+        }
+        self.update(update_from(&password).folder(FolderInfo::new(folder_id)))
+            .await
+    }
+
+    /// Move all the passwords matching the filter to the folder. All the other properties of the
+    /// passwords are kept.
+    ///
+    /// Notes
+    ///  - Passwords already in the folder are not updated
+    ///  - Not hidden passwords can't be moved to hidden folders, the server moves them to the
+    ///    base folder instead. This is reported as a warning.
+    pub async fn move_matching(
+        &self,
+        filter: &Filter,
+        target_folder: uuid::Uuid,
+        options: MoveOptions,
+    ) -> Result<MoveReport, Error> {
+        use futures::stream::StreamExt;
+
+        let target = self.api.folder().get(None, target_folder).await?;
+        let passwords: Vec<_> = filter
+            .apply_async(self.list(None).await?)
+            .await
+            .into_iter()
+            .filter(|password| password.folder.id() != target_folder)
+            .collect();
+
+        let mut report = MoveReport::default();
+        for password in &passwords {
+            if target.versioned.hidden && !password.versioned.hidden {
+                report.warnings.push(MoveWarning::HiddenFolder {
+                    password: password.id,
+                });
+            }
+        }
+        if options.dry_run {
+            report.moved = passwords
+                .iter()
+                .map(|password| PasswordIdentifier {
+                    id: password.id,
+                    revision: password.revision,
+                })
+                .collect();
+            return Ok(report);
+        }
+
+        let mut results = futures::stream::iter(passwords)
+            .map(|password| async move {
+                let update = update_from(&password).folder(FolderInfo::new(target_folder));
+                (password.id, self.update(update).await)
+            })
+            .buffer_unordered(options.concurrency.max(1));
+        while let Some((id, result)) = results.next().await {
+            match result {
+                Ok(identifier) => report.moved.push(identifier),
+                Err(e) => report.failed.push((id, e)),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Generate a password with the [service API](crate::service::ServiceApi) and save it. The
+    /// generated password is returned to be shown to the user.
+    ///
+    /// Notes
+    ///  - Without `settings` the password is generated with the settings of the user
+    ///  - The hash has the length chosen by the user, see
+    ///    [hash_length](crate::settings::ClientCapabilities::hash_length)
+    pub async fn create_generated(
+        &self,
+        label: String,
+        url: Option<String>,
+        settings: Option<GeneratePassword>,
+    ) -> Result<(PasswordIdentifier, GenerateResponse), Error> {
+        let generated = match settings {
+            Some(settings) => self.api.service().generate_password(settings).await?,
+            None => {
+                self.api
+                    .service()
+                    .generate_password_with_user_settings()
+                    .await?
+            }
+        };
+        let mut create = CreatePassword::new(label, generated.password.clone());
+        if let Some(url) = url {
+            create = create.url(url);
+        }
+        let identifier = self.create(create).await?;
+        Ok((identifier, generated))
+    }
+
+    /// Report the duplicated, breached, weak and old passwords, see [SecurityReport]. Unlike the
+    /// status computed by the server, this is up to date with the last changes.
+    ///
+    /// Notes
+    ///  - This lists all the passwords and checks them locally, the hidden and trashed ones are
+    ///    not included
+    pub async fn security_report(&self) -> Result<SecurityReport, Error> {
+        let max_age = self.api.settings().get().check_for_old_passwords().await?;
+        let passwords = self.list(Some(Details::new())).await?;
+        let now = crate::Timestamp::now().seconds();
+        Ok(SecurityReport::new(&passwords, max_age, now))
+    }
+
+    /// Find the passwords matching the url, like the browser extension does to fill a login
+    /// form. The best matches come first, see [UrlMatch](crate::UrlMatch).
+    ///
+    /// Notes
+    ///  - This lists all the passwords and filters them locally, the hidden and trashed ones are
+    ///    not included
+    ///  - The registrable domains are guessed without the public suffix list, some domains
+    ///    sharing a public suffix (like `github.io`) are considered the same
+    pub async fn find_for_url(&self, url: &url::Url) -> Result<Vec<ScoredPassword>, Error> {
+        let mut found = Vec::new();
+        let mut passwords = self.list(None).await?.into_iter().peekable();
+        while passwords.peek().is_some() {
+            found.extend(
+                passwords
+                    .by_ref()
+                    .take(crate::utils::YIELD_EVERY)
+                    .filter(|password| !password.versioned.hidden && !password.versioned.trashed)
+                    .filter_map(|password| {
+                        let score = crate::filter::url_match(&password, url)?;
+                        Some(ScoredPassword { password, score })
+                    }),
+            );
+            crate::runtime::yield_now().await;
+        }
+        found.sort_by_key(|found| std::cmp::Reverse(found.score));
+        Ok(found)
+    }
+
+    /// The favicon of the password, from the registrable domain of its url (`example.com` for
+    /// `https://login.example.com/path`), or from its label when the url is empty or has no host
+    ///
+    /// Notes
+    ///  - See [ServiceApi::favicon](crate::service::ServiceApi::favicon), a default image is
+    ///    generated when no favicon is found
+    pub async fn favicon(
+        &self,
+        password: &Password,
+        size: MiniatureSize,
+    ) -> Result<bytes::Bytes, Error> {
+        let domain = crate::filter::password_domain(password)
+            .unwrap_or_else(|| password.versioned.label.clone());
+        self.api.service().favicon(domain, size).await
+    }
+
+    /// Find the password tagged with this external identifier (see
+    /// [CreatePassword::external_id]).
+    ///
+    /// Notes
+    ///  - This lists all the passwords and filters them locally, yielding to the executor
+    ///    regularly as this is CPU heavy on large vaults
+    pub async fn find_by_external_id(
+        &self,
+        namespace: &str,
+        id: &str,
+    ) -> Result<Option<Password>, Error> {
+        let mut passwords = self.list(None).await?.into_iter().peekable();
+        while passwords.peek().is_some() {
+            let found = passwords
+                .by_ref()
+                .take(crate::utils::YIELD_EVERY)
+                .find(|password| password.external_id(namespace).as_deref() == Some(id));
+            if found.is_some() {
+                return Ok(found);
+            }
+            crate::runtime::yield_now().await;
+        }
+        Ok(None)
+    }
+}
+
+impl crate::utils::Revisioned for Password {
+    fn current_revision(&self) -> uuid::Uuid {
+        self.revision
+    }
+}
+
+impl crate::Sortable for Password {
+    fn label(&self) -> &str {
+        &self.versioned.label
+    }
+
+    fn updated(&self) -> crate::UnixTime {
+        self.versioned.updated
+    }
+
+    fn created(&self) -> crate::UnixTime {
+        self.created
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{QueryKind, SearchQuery};
+    use serde_json::json;
+
+    pub(crate) fn password_json() -> serde_json::Value {
+        json!({
+            "id": "a2e0e74b-2ee1-4b4c-9f8b-a3d8b0bb2d27",
+            "label": "Example",
+            "username": "alice",
+            "password": "hunter2",
+            "url": "https://example.com",
+            "notes": "",
+            "customFields": "[]",
+            "status": 0,
+            "statusCode": "GOOD",
+            "hash": "f3bbbd66a63d4bf1747940578ec3d0103530e21d",
+            "folder": "00000000-0000-0000-0000-000000000000",
+            "revision": "4a0a9fbf-6ca5-4d3f-a2bb-0a9f1d0e1c55",
+            "share": null,
+            "shared": false,
+            "cseType": "none",
+            "cseKey": "",
+            "sseType": "SSEv1r2",
+            "client": "CLIENT::UNKNOWN",
+            "hidden": false,
+            "trashed": false,
+            "favorite": false,
+            "editable": true,
+            "edited": 1_600_000_000,
+            "created": 1_600_000_000,
+            "updated": 1_600_000_000,
+        })
+    }
+
+    #[test]
+    fn extra_survives_update() {
+        let mut json = password_json();
+        json["otherApp"] = json!({"key": "value"});
+        let password: Password = serde_json::from_value(json).unwrap();
+        assert_eq!(password.extra.len(), 1);
+        assert_eq!(password.extra["otherApp"], json!({"key": "value"}));
+
+        let update = UpdatePassword::new(
+            password.versioned.label.clone(),
+            password.versioned.password.clone(),
+            password.id,
+        )
+        .hash(password.versioned.hash.clone())
+        .extra(password.extra);
+        let update = serde_json::to_value(&update).unwrap();
+        assert_eq!(update["otherApp"], json!({"key": "value"}));
+        assert_eq!(update["label"], "Example");
+    }
+
+    #[test]
+    fn into_update() {
+        let mut json = password_json();
+        json["notes"] = json!("Some notes");
+        json["customFields"] = json!(r#"[{"label":"PIN","type":"secret","value":"1234"}]"#);
+        json["folder"] = json!("8ed3e9b2-5a49-43c9-8d7e-5b1f8e1fd5d4");
+        json["cseKey"] = json!("7d0e1c55-4a0a-4d3f-a2bb-0a9f1d0e1c55");
+        json["hidden"] = json!(true);
+        json["favorite"] = json!(true);
+        json["edited"] = json!(1_500_000_000);
+        json["otherApp"] = json!({"key": "value"});
+        let mut password: Password = serde_json::from_value(json.clone()).unwrap();
+        password.tags = Some(vec![
+            serde_json::from_value(crate::tag::tests::tag_json()).unwrap()
+        ]);
+
+        let update = serde_json::to_value(UpdatePassword::from(password)).unwrap();
+        for field in &[
+            "id",
+            "label",
+            "username",
+            "password",
+            "url",
+            "notes",
+            "customFields",
+            "hash",
+            "folder",
+            "cseType",
+            "cseKey",
+            "hidden",
+            "favorite",
+            "edited",
+            "otherApp",
+        ] {
+            assert_eq!(update[field], json[field], "{}", field);
+        }
+        assert_eq!(
+            update["tags"],
+            json!(["9c8b7a6d-5e4f-4a3b-8c2d-1e0f9a8b7c6d"])
+        );
+    }
+
+    #[test]
+    fn security_report() {
+        let day = 24 * 60 * 60;
+        let password = |id: u128, hash: &str, status: u8, edited: i64| {
+            let mut json = password_json();
+            json["id"] = uuid::Uuid::from_u128(id).to_string().into();
+            json["label"] = format!("Password {}", id).into();
+            json["hash"] = hash.into();
+            json["status"] = status.into();
+            json["edited"] = edited.into();
+            serde_json::from_value::<Password>(json).unwrap()
+        };
+        let now = 1_600_000_000;
+        let passwords = [
+            password(1, "aaaa", 0, now),
+            password(2, "bbbb", 2, now - 10 * day),
+            password(3, "aaaa", 1, now),
+            password(4, "", 0, now - 100 * day),
+            password(5, "", 0, now),
+            password(6, "aaaa", 0, now),
+        ];
+        let entry = |id: u128| ReportEntry {
+            id: uuid::Uuid::from_u128(id),
+            label: format!("Password {}", id),
+        };
+
+        let report = SecurityReport::new(&passwords, 30, now);
+        assert_eq!(report.duplicates, [vec![entry(1), entry(3), entry(6)]]);
+        assert_eq!(report.breached, [entry(2)]);
+        assert_eq!(report.weak, [entry(3)]);
+        assert_eq!(report.old, [entry(4)]);
+
+        let report = SecurityReport::new(&passwords, 0, now);
+        assert!(report.old.is_empty());
+        assert!(SecurityReport::new(&passwords[4..5], 30, now).is_empty());
+    }
+
+    #[test]
+    fn detailed() {
+        let share = json!({
+            "id": "0f2d8c4b-6a1e-4b3d-9c5f-7e8a9b0c1d2e",
+            "created": 1_600_000_000,
+            "updated": 1_600_000_000,
+            "expires": null,
+            "editable": true,
+            "shareable": false,
+            "updatePending": false,
+            "password": "a2e0e74b-2ee1-4b4c-9f8b-a3d8b0bb2d27",
+            "owner": {"id": "bob", "name": "Bob"},
+            "receiver": {"id": "alice", "name": "Alice"},
+        });
+        let mut json = password_json();
+        json["tags"] = json!([{
+            "id": "9c8b7a6d-5e4f-4a3b-8c2d-1e0f9a8b7c6d",
+            "label": "Work",
+            "color": "#745bca",
+            "created": 1_600_000_000,
+            "updated": 1_600_000_000,
+            "edited": 1_600_000_000,
+            "revision": "4a0a9fbf-6ca5-4d3f-a2bb-0a9f1d0e1c55",
+            "cseType": "none",
+            "cseKey": "",
+            "sseType": "SSEv1r2",
+            "client": "CLIENT::UNKNOWN",
+            "hidden": false,
+            "trashed": false,
+            "favorite": false,
+        }]);
+        json["shares"] = json!([share.clone()]);
+        json["share"] = share;
+
+        let password: Password = serde_json::from_value(json).unwrap();
+        assert_eq!(password.tags.unwrap()[0].versioned.label, "Work");
+        let shares = password.shares.unwrap();
+        assert_eq!(shares[0].receiver.id, "alice");
+        let share = password.share.unwrap();
+        assert_eq!(share.id(), shares[0].id);
+        assert!(matches!(share.get(), crate::share::ShareInfoKind::Data(_)));
+    }
+
+    #[test]
+    fn hashes() {
+        let full = "f3bbbd66a63d4bf1747940578ec3d0103530e21d";
+        assert_eq!(hash_password("hunter2", HASH_LENGTH), full);
+        assert_eq!(hash_password("hunter2", 20), &full[..20]);
+
+        let hash = |create: CreatePassword| create.with_computed_hash(HASH_LENGTH).hash.unwrap();
+        assert_eq!(hash(CreatePassword::new("Example", "hunter2")), full);
+        let short = CreatePassword::new("Example", "hunter2").with_computed_hash(20);
+        assert_eq!(short.hash.unwrap(), &full[..20]);
+        let update = UpdatePassword::new("Example", "hunter2", uuid::Uuid::nil());
+        assert_eq!(update.with_computed_hash(20).hash.unwrap(), &full[..20]);
+        let reduced = CreatePassword::new("Example", "hunter2").with_hash_length(30);
+        assert_eq!(hash(reduced), &full[..30]);
+        assert_eq!(hash(CreatePassword::new("Example", "x").hash("abc")), "abc");
+
+        let mut json = password_json();
+        json["hash"] = full[..20].into();
+        let password: Password = serde_json::from_value(json).unwrap();
+        let patch = PasswordPatch::new(&password).password("hunter2");
+        assert_eq!(patch.as_update().hash.as_deref(), Some(&full[..20]));
+    }
+
+    #[test]
+    fn borrowed_builders() {
+        let owned = CreatePassword::new(String::from("Example"), String::from("hunter2"))
+            .username(String::from("alice"))
+            .favorite(true);
+        let borrowed = CreatePassword::new("Example", "hunter2")
+            .username("alice")
+            .favorite(true);
+        assert_eq!(
+            serde_json::to_value(&owned).unwrap(),
+            serde_json::to_value(&borrowed).unwrap()
+        );
+    }
+
+    #[test]
+    fn patch() {
+        let password: Password = serde_json::from_value(password_json()).unwrap();
+        let patch = PasswordPatch::new(&password);
+        assert!(patch.is_empty());
+
+        let patch = patch.username("bob").favorite(true).username("carol");
+        assert_eq!(patch.changed(), &["username", "favorite"]);
+        let update = serde_json::to_value(patch.as_update()).unwrap();
+        assert_eq!(update["username"], "carol");
+        assert_eq!(update["favorite"], true);
+        assert_eq!(update["label"], "Example");
+        assert_eq!(update["password"], "hunter2");
+        assert_eq!(update["url"], "https://example.com");
+        assert_eq!(update["folder"], "00000000-0000-0000-0000-000000000000");
+        assert_eq!(patch.as_update().expected_revision, Some(password.revision));
+    }
+
+    #[test]
+    fn secret_access() {
+        let password: Password = serde_json::from_value(password_json()).unwrap();
+        assert_eq!(password.password_masked().chars().count(), MASK_LENGTH);
+        assert_eq!(password.reveal().to_string(), "hunter2");
+        assert_eq!(password.reveal().to_zeroizing().expose(), "hunter2");
+    }
+
+    #[test]
+    fn unknown_status() {
+        let mut json = password_json();
+        json["status"] = 7.into();
+        json["statusCode"] = "COMPROMISED".into();
+        let password = serde_json::from_value::<Password>(json);
+        if cfg!(feature = "strict") {
+            let error = password.unwrap_err().to_string();
+            assert!(error.contains("`7` for `status`"), "{}", error);
+        } else {
+            let password = password.unwrap();
+            assert_eq!(password.versioned.status, SecurityStatus::Unknown(7));
+            assert_eq!(
+                password.versioned.status_code,
+                StatusCode::Other("COMPROMISED".into())
+            );
+            let json = serde_json::to_value(&password).unwrap();
+            assert_eq!(json["status"], 7);
+            assert_eq!(json["statusCode"], "COMPROMISED");
+        }
+    }
+
+    #[test]
+    fn summary() {
+        let summary: PasswordSummary = serde_json::from_value(password_json()).unwrap();
+        assert_eq!(summary.label, "Example");
+        assert_eq!(summary.username, "alice");
+        assert_eq!(summary.status, SecurityStatus::Ok);
+    }
+
+    #[test]
+    fn search_body() {
+        let search = PasswordSearch::new()
+            .and_status(SearchQuery::new(SecurityStatus::Breached, QueryKind::Exact))
+            .unwrap()
+            .and_favorite(SearchQuery::new(true, QueryKind::Equals))
+            .unwrap()
+            .and_edited(SearchQuery::new(
+                crate::utils::unix_time(1_600_000_000),
+                QueryKind::GreaterThan,
+            ))
+            .unwrap()
+            .and_sse_type(SearchQuery::new("SSEv1r1".into(), QueryKind::Exact))
+            .unwrap();
+        assert_eq!(
+            search.to_json(),
+            json!({
+                "status": 2,
+                "favorite": ["eq", true],
+                "edited": ["gt", 1_600_000_000],
+                "sseType": "SSEv1r1",
+            })
+        );
+    }
+
+    #[test]
+    fn sorted() {
+        let passwords = [("b", 3), ("B", 1), ("a", 2), ("b", 2)];
+        let mut passwords: Vec<Password> = passwords
+            .iter()
+            .map(|(label, created)| {
+                let mut json = password_json();
+                json["label"] = (*label).into();
+                json["created"] = (*created).into();
+                serde_json::from_value(json).unwrap()
+            })
+            .collect();
+        let keys = |passwords: &[Password]| {
+            passwords
+                .iter()
+                .map(|p| (p.versioned.label.clone(), p.created))
+                .collect::<Vec<_>>()
+        };
+        let key = |label: &str, created| (label.to_string(), crate::utils::unix_time(created));
+
+        crate::sort(&mut passwords, crate::SortBy::Label, crate::Order::Asc);
+        assert_eq!(
+            keys(&passwords),
+            [key("B", 1), key("a", 2), key("b", 3), key("b", 2)]
+        );
+        crate::sort(
+            &mut passwords,
+            crate::SortBy::LabelIgnoreCase,
+            crate::Order::Desc,
+        );
+        assert_eq!(
+            keys(&passwords),
+            [key("B", 1), key("b", 3), key("b", 2), key("a", 2)]
+        );
+        crate::sort(&mut passwords, crate::SortBy::Created, crate::Order::Asc);
+        assert_eq!(
+            keys(&passwords),
+            [key("B", 1), key("b", 2), key("a", 2), key("b", 3)]
+        );
+
+        let page = crate::paginate(passwords, 1, 3);
+        assert_eq!(keys(&page.items), [key("b", 3)]);
+        assert_eq!(page.total, 4);
+        assert_eq!(page.page_count(), 2);
+        assert!(!page.has_next());
+    }
+
+    #[test]
+    fn limits() {
+        let at = |limit: usize| "a".repeat(limit);
+        let over = |field, limit| {
+            Err(ValidationError {
+                field,
+                limit,
+                actual: limit + 1,
+            })
+        };
+
+        let create = CreatePassword::new(at(MAX_LABEL_LENGTH), at(MAX_PASSWORD_LENGTH))
+            .username(at(MAX_USERNAME_LENGTH))
+            .url(at(MAX_URL_LENGTH))
+            .notes(at(MAX_NOTES_LENGTH));
+        assert_eq!(create.validate(), Ok(()));
+        let create = CreatePassword::new(at(MAX_LABEL_LENGTH + 1), "hunter2");
+        assert_eq!(create.validate(), over("label", MAX_LABEL_LENGTH));
+        let create = CreatePassword::new("Example", at(MAX_PASSWORD_LENGTH + 1));
+        assert_eq!(create.validate(), over("password", MAX_PASSWORD_LENGTH));
+        let create = CreatePassword::new("Example", "hunter2").url(at(MAX_URL_LENGTH + 1));
+        assert_eq!(create.validate(), over("url", MAX_URL_LENGTH));
+        let create = CreatePassword::new("Example", "hunter2")
+            .custom_fields(CustomFields::from_raw(at(MAX_CUSTOM_FIELDS_LENGTH + 1)));
+        assert_eq!(
+            create.validate(),
+            over("customFields", MAX_CUSTOM_FIELDS_LENGTH)
+        );
+        // Characters are counted, not bytes
+        let create = CreatePassword::new("é".repeat(MAX_LABEL_LENGTH), "hunter2");
+        assert_eq!(create.validate(), Ok(()));
+
+        let password: Password = serde_json::from_value(password_json()).unwrap();
+        let update = UpdatePassword::from(password);
+        assert_eq!(update.validate(), Ok(()));
+        let update = update.notes(at(MAX_NOTES_LENGTH + 1));
+        assert_eq!(update.validate(), over("notes", MAX_NOTES_LENGTH));
+
+        let folder = crate::folder::CreateFolder::new(at(crate::folder::MAX_LABEL_LENGTH));
+        assert_eq!(folder.validate(), Ok(()));
+        let folder = crate::folder::CreateFolder::new(at(crate::folder::MAX_LABEL_LENGTH + 1));
+        assert_eq!(
+            folder.validate(),
+            over("label", crate::folder::MAX_LABEL_LENGTH)
+        );
+        let color = crate::Color {
+            red: 0,
+            green: 0,
+            blue: 0,
+        };
+        let tag = crate::tag::CreateTag::new(at(crate::tag::MAX_LABEL_LENGTH + 1), color);
+        assert_eq!(tag.validate(), over("label", crate::tag::MAX_LABEL_LENGTH));
+        let id = uuid::Uuid::nil();
+        let share = crate::share::CreateShare::new(id, at(crate::share::MAX_RECEIVER_LENGTH));
+        assert_eq!(share.validate(), Ok(()));
+        let share = crate::share::CreateShare::new(id, at(crate::share::MAX_RECEIVER_LENGTH + 1));
+        assert_eq!(
+            share.validate(),
+            over("receiver", crate::share::MAX_RECEIVER_LENGTH)
+        );
+    }
+
+    #[test]
+    fn custom_fields() {
+        let mut json = password_json();
+        json["customFields"] =
+            r#"[{"label":"pin","type":"secret","value":"1234"},{"label":"x","type":"qr","value":""}]"#
+                .into();
+        let password = serde_json::from_value::<Password>(json.clone());
+        if cfg!(feature = "strict") {
+            // The unknown type makes the fields unreadable, they are kept as received
+            let fields = password.unwrap().versioned.custom_fields;
+            assert_eq!(fields.fields(), None);
+            assert_eq!(fields.raw(), json["customFields"].as_str().unwrap());
+        } else {
+            let fields = password.unwrap().versioned.custom_fields;
+            assert_eq!(
+                fields.get("pin"),
+                Some(&CustomField::new("pin", CustomFieldType::Secret, "1234"))
+            );
+            assert_eq!(
+                fields.fields().unwrap()[1].kind,
+                CustomFieldType::Other("qr".into())
+            );
+            assert_eq!(serde_json::to_value(&fields).unwrap(), json["customFields"]);
+        }
+
+        let encrypted = CustomFields::from_raw("8f3a9c");
+        assert_eq!(encrypted.fields(), None);
+        assert_eq!(encrypted.raw(), "8f3a9c");
+        let field = CustomField::new("a", CustomFieldType::Text, "");
+        assert!(encrypted.with(field).is_err());
+
+        let mut fields = CustomFields::new();
+        for i in 0..MAX_CUSTOM_FIELDS {
+            let field = CustomField::new(i.to_string(), CustomFieldType::Text, "");
+            fields = fields.with(field).unwrap();
+        }
+        let field = CustomField::new("more", CustomFieldType::Text, "");
+        assert!(matches!(
+            fields.with(field),
+            Err(Error::CustomFieldsTooLarge { count: 21, .. })
+        ));
+        let long = CustomField::new("notes", CustomFieldType::Text, "a".repeat(8192));
+        assert!(matches!(
+            CustomFields::new().with(long),
+            Err(Error::CustomFieldsTooLarge { count: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn external_id_label() {
+        assert_eq!(super::external_id_label("sync"), "ext:sync");
+        assert_eq!(super::external_id_label("a:b"), "ext:a%3Ab");
+        assert_eq!(super::external_id_label("a%3Ab"), "ext:a%253Ab");
+        assert_eq!(super::external_id_label("[x]"), "ext:[x]");
+
+        let namespaces = ["a:b", "a%3Ab", "[a]:b", "a"];
+        let mut fields = None;
+        for (i, namespace) in namespaces.iter().enumerate() {
+            let id = format!("{}:[id],{}", namespace, i);
+            fields = Some(with_external_id(fields, namespace, &id).unwrap());
+        }
+        // The ids survive the serialization of the custom fields
+        let value = serde_json::to_value(fields.unwrap()).unwrap();
+        let fields = serde_json::from_value::<CustomFields>(value).unwrap();
+        for (i, namespace) in namespaces.iter().enumerate() {
+            let id = format!("{}:[id],{}", namespace, i);
+            assert_eq!(find_external_id(&fields, namespace), Some(id));
+        }
+        assert_eq!(find_external_id(&fields, "b"), None);
+
+        // Setting it again replaces the previous id
+        let fields = with_external_id(Some(fields), "a:b", "other").unwrap();
+        assert_eq!(find_external_id(&fields, "a:b").as_deref(), Some("other"));
+        assert_eq!(fields.fields().unwrap().len(), namespaces.len());
+    }
+
+    #[test]
+    fn search_between() {
+        let search = PasswordSearch::new()
+            .and_edited(SearchQuery::greater_than(crate::utils::unix_time(
+                1_600_000_000,
+            )))
+            .unwrap()
+            .and_edited(SearchQuery::less_than(crate::utils::unix_time(
+                1_700_000_000,
+            )))
+            .unwrap();
+        assert_eq!(
+            search.to_json(),
+            json!({
+                "edited": [["gt", 1_600_000_000], ["lt", 1_700_000_000]],
+            })
+        );
+    }
+}
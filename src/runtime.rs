@@ -0,0 +1,149 @@
+//! The calls to the async runtime, so that changing or abstracting the runtime only touches this
+//! module.
+//!
+//! With the `tokio` feature (enabled by default) the timers and the background tasks use tokio 1.
+//! Without it, or to use another executor, the application provides them with [set_runtime]
+//! before using the timers of the crate: the login flow poll loop, the retries, the keepalive and
+//! the diagnostics.
+
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// The timers and the background tasks of an async runtime
+pub trait Runtime: Send + Sync {
+    /// A future completing after `duration`
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+    /// Run `future` in background
+    fn spawn(&self, future: BoxFuture<'static, ()>);
+    /// Run the CPU heavy `f` out of the threads of the executor, in a new thread by default
+    fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+        std::thread::spawn(f);
+    }
+}
+
+static RUNTIME: RwLock<Option<Arc<dyn Runtime>>> = RwLock::new(None);
+
+/// Use `runtime` for the timers and the background tasks of the crate, instead of tokio
+pub fn set_runtime(runtime: Arc<dyn Runtime>) {
+    *RUNTIME.write().unwrap_or_else(|e| e.into_inner()) = Some(runtime);
+}
+
+fn runtime() -> Option<Arc<dyn Runtime>> {
+    RUNTIME.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[cfg(not(feature = "tokio"))]
+fn missing() -> ! {
+    panic!("no async runtime: enable the tokio feature or call runtime::set_runtime")
+}
+
+/// Wait for `duration`
+pub(crate) async fn sleep(duration: Duration) {
+    match runtime() {
+        Some(runtime) => runtime.sleep(duration).await,
+        #[cfg(feature = "tokio")]
+        None => tokio::time::sleep(duration).await,
+        #[cfg(not(feature = "tokio"))]
+        None => missing(),
+    }
+}
+
+/// Run `future` for at most `duration`, `None` if it did not finish in time
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    futures::pin_mut!(future);
+    let timer = sleep(duration);
+    futures::pin_mut!(timer);
+    match futures::future::select(future, timer).await {
+        futures::future::Either::Left((output, _)) => Some(output),
+        futures::future::Either::Right(_) => None,
+    }
+}
+
+/// Run `future` in background
+pub(crate) fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    match runtime() {
+        Some(runtime) => runtime.spawn(Box::pin(future)),
+        #[cfg(feature = "tokio")]
+        None => {
+            tokio::spawn(future);
+        }
+        #[cfg(not(feature = "tokio"))]
+        None => missing(),
+    }
+}
+
+/// Run the CPU heavy `f` out of the threads of the runtime, fails with
+/// [TaskFailed](crate::Error::TaskFailed) if it panicked or the runtime is shutting down
+pub(crate) async fn spawn_blocking<F, R>(f: F) -> Result<R, crate::Error>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    match runtime() {
+        Some(runtime) => {
+            // The sender is dropped without a value when `f` panics
+            let (sender, receiver) = futures::channel::oneshot::channel();
+            runtime.spawn_blocking(Box::new(move || {
+                let _ = sender.send(f());
+            }));
+            receiver
+                .await
+                .map_err(|_| crate::Error::TaskFailed("the blocking task panicked".into()))
+        }
+        #[cfg(feature = "tokio")]
+        None => tokio::task::spawn_blocking(f)
+            .await
+            .map_err(|e| crate::Error::TaskFailed(e.to_string())),
+        #[cfg(not(feature = "tokio"))]
+        None => missing(),
+    }
+}
+
+/// Let the other tasks of the executor run
+pub(crate) async fn yield_now() {
+    YieldNow(false).await
+}
+
+/// Pending once, after waking itself, so that it works on any executor
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    async fn panicking_task() {
+        assert_eq!(super::spawn_blocking(|| 42).await.unwrap(), 42);
+        let result = super::spawn_blocking(|| panic!("expected panic")).await;
+        assert!(matches!(result, Err(crate::Error::TaskFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        use std::time::Duration;
+        assert_eq!(
+            super::timeout(Duration::from_secs(1), async { 42 }).await,
+            Some(42)
+        );
+        let slow = super::sleep(Duration::from_secs(1));
+        assert_eq!(super::timeout(Duration::from_millis(1), slow).await, None);
+    }
+}
@@ -1,6 +1,22 @@
 use crate::{AuthenticatedApi, Error};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use serde::{Deserialize, Serialize};
 
+/// The characters escaped in a path segment, `/` and `%` included so that a domain can't change
+/// the endpoint
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
 /// Access the service API
 pub struct ServiceApi<'a> {
     pub(crate) api: &'a AuthenticatedApi,
@@ -9,7 +25,9 @@ pub struct ServiceApi<'a> {
 impl<'a> ServiceApi<'a> {
     /// Generates a password with the users default settings
     pub async fn generate_password_with_user_settings(&self) -> Result<GenerateResponse, Error> {
-        self.api.passwords_get("1.0/service/password", ()).await
+        self.api
+            .passwords_get(self.api.endpoint("service", "password"), ())
+            .await
     }
     /// The password action generates one password with the given settings.
     ///
@@ -21,7 +39,7 @@ impl<'a> ServiceApi<'a> {
         settings: GeneratePassword,
     ) -> Result<GenerateResponse, Error> {
         self.api
-            .passwords_post("1.0/service/password", settings)
+            .passwords_post_read(self.api.endpoint("service", "password"), settings)
             .await
     }
 
@@ -36,9 +54,13 @@ impl<'a> ServiceApi<'a> {
     ) -> Result<bytes::Bytes, Error> {
         self.api
             .bytes_request(
-                format!("1.0/service/avatar/{user}/{size}", user = user, size = size),
+                self.api.endpoint(
+                    "service",
+                    &format!("avatar/{user}/{size}", user = user, size = size),
+                ),
                 reqwest::Method::GET,
                 (),
+                false,
             )
             .await
     }
@@ -47,6 +69,8 @@ impl<'a> ServiceApi<'a> {
     ///
     /// Notes
     ///  - If no favicon can be found a default image will be generated
+    ///  - The domain is percent-encoded, see [PasswordApi::favicon](crate::password::PasswordApi::favicon)
+    ///    to get the favicon of a password
     pub async fn favicon(
         &self,
         domain: String,
@@ -54,13 +78,17 @@ impl<'a> ServiceApi<'a> {
     ) -> Result<bytes::Bytes, Error> {
         self.api
             .bytes_request(
-                format!(
-                    "1.0/service/favicon/{domain}/{size}",
-                    domain = domain,
-                    size = size
+                self.api.endpoint(
+                    "service",
+                    &format!(
+                        "favicon/{domain}/{size}",
+                        domain = utf8_percent_encode(&domain, PATH_SEGMENT),
+                        size = size
+                    ),
                 ),
                 reqwest::Method::GET,
                 (),
+                false,
             )
             .await
     }
@@ -96,15 +124,19 @@ impl<'a> ServiceApi<'a> {
 
         self.api
             .bytes_request(
-                format!(
-                    "1.0/service/preview/{domain}/{view}/{width}/{height}",
-                    domain = domain,
-                    view = view,
-                    width = width,
-                    height = height,
+                self.api.endpoint(
+                    "service",
+                    &format!(
+                        "preview/{domain}/{view}/{width}/{height}",
+                        domain = utf8_percent_encode(&domain, PATH_SEGMENT),
+                        view = view,
+                        width = width,
+                        height = height,
+                    ),
                 ),
                 reqwest::Method::GET,
                 (),
+                true,
             )
             .await
     }
@@ -177,6 +209,8 @@ pub struct GeneratePassword {
     numbers: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     special: Option<bool>,
+    #[serde(flatten)]
+    extra: std::collections::HashMap<String, serde_json::Value>,
 }
 impl GeneratePassword {
     pub fn new() -> Self {
@@ -204,13 +238,62 @@ impl GeneratePassword {
             ..self
         }
     }
+    /// Send a parameter unknown to this crate. Only strength, numbers and special are documented,
+    /// the wordlist depends on the settings of the server and can't be chosen by the client with
+    /// the current versions of the app.
+    pub fn extra(mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GenerateResponse {
     pub password: String,
+    /// The words used in the password, empty if the server did not send them
+    #[serde(default)]
     pub words: Vec<String>,
     pub strength: u8,
     pub numbers: bool,
     pub special: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn generate_request() {
+        let request = GeneratePassword::new()
+            .strength(PasswordStrength::two())
+            .numbers(true)
+            .extra("words", "en");
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"strength": 2, "numbers": true, "words": "en"})
+        );
+    }
+
+    #[test]
+    fn generate_response() {
+        let with_words: GenerateResponse = serde_json::from_value(json!({
+            "password": "CorrectHorseBattery",
+            "words": ["Correct", "Horse", "Battery"],
+            "strength": 1,
+            "numbers": false,
+            "special": false,
+        }))
+        .unwrap();
+        assert_eq!(with_words.words.len(), 3);
+
+        let without_words: GenerateResponse = serde_json::from_value(json!({
+            "password": "aT4&kd9!q",
+            "strength": 4,
+            "numbers": true,
+            "special": true,
+        }))
+        .unwrap();
+        assert!(without_words.words.is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{AuthenticatedApi, Error, Color};
+use crate::{share::ShareType, AuthenticatedApi, Color, Error};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -43,6 +43,39 @@ impl<'a> SettingsApi<'a> {
     pub fn reset(&self) -> SettingReset {
         SettingReset { api: self.api }
     }
+    /// Reset multiple user settings in one request, returning their default values
+    ///
+    /// Fails with [Error::InvalidSetting] without contacting the server if one of the settings
+    /// is a server setting, or [Client](SettingVariant::Client) (use
+    /// [delete_client](Self::delete_client))
+    pub async fn reset_multiple(
+        &self,
+        settings: &[SettingVariant],
+    ) -> Result<Vec<SettingValue>, Error> {
+        let names = settings
+            .iter()
+            .map(|setting| match setting {
+                SettingVariant::Client => Err(Error::InvalidSetting),
+                setting if SERVER_SETTING_NAMES.contains(&setting.name()) => {
+                    Err(Error::InvalidSetting)
+                }
+                setting => Ok(setting.name()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.reset_names(names).await
+    }
+    /// Reset all the user settings in one request, returning their default values
+    pub async fn reset_all_user(&self) -> Result<Vec<SettingValue>, Error> {
+        self.reset_names(USER_SETTING_NAMES.to_vec()).await
+    }
+    async fn reset_names(&self, names: Vec<&str>) -> Result<Vec<SettingValue>, Error> {
+        let settings: Settings = self
+            .api
+            .passwords_post(self.api.endpoint("settings", "reset"), names)
+            .await?;
+        self.api.invalidate_capabilities();
+        Ok(settings.to_values())
+    }
     /// Fetch multiple settings
     /// Notes
     ///  - If the setting is not defined, it will default to null
@@ -56,33 +89,69 @@ impl<'a> SettingsApi<'a> {
     }
     /// Fetch all the settings
     pub async fn get_all(&self) -> Result<AllSettings, crate::Error> {
-        Ok(self.api.passwords_get("1.0/settings/list", ()).await?)
+        self.list(None).await
+    }
+    /// Fetch all the settings in one request, or only the ones of a scope. The settings out of
+    /// the scope are `None`
+    pub async fn list(&self, scope: Option<SettingScope>) -> Result<AllSettings, crate::Error> {
+        let endpoint = self.api.endpoint("settings", "list");
+        match scope {
+            None => Ok(self.api.passwords_get(endpoint, ()).await?),
+            Some(scope) => {
+                #[derive(Serialize)]
+                struct Scopes {
+                    scopes: [SettingScope; 1],
+                }
+                let scopes = Scopes { scopes: [scope] };
+                Ok(self.api.passwords_post_read(endpoint, scopes).await?)
+            }
+        }
     }
 
-    /// Set the value of a writable setting
+    /// Set the value of writable settings, returning the values set by the server
+    ///
+    /// Fails with [Error::InvalidSetting] without contacting the server if `settings` contains
+    /// server settings
     pub async fn set(
         &self,
         settings: Settings,
     ) -> Result<Vec<SettingValue>, Error> {
+        if settings.has_server_settings() {
+            return Err(Error::InvalidSetting);
+        }
         let settings: Settings =
-            self.api.passwords_post("1.0/settings/set", settings).await?;
+            self.api.passwords_post(self.api.endpoint("settings", "set"), settings).await?;
+        self.api.invalidate_capabilities();
         Ok(settings.to_values())
     }
     /// Set the value of a client setting
     /// Note
-    ///  - If the size limitations of the client scope are exceeded, an error will be returned
+    ///  - Fails with [Error::SettingTooLarge] without contacting the server if the name (without
+    ///    `client.`) is longer than 48 characters, or the serialized value longer than 128
+    ///    characters
     pub async fn set_client<D: Serialize + serde::de::DeserializeOwned>(
         &self,
         name: ClientSettings,
         value: D,
     ) -> Result<D, Error> {
+        check_size(&name.name, name.name.chars().count(), CLIENT_NAME_LIMIT)?;
+        let length = serde_json::to_string(&value)?.chars().count();
+        check_size(&name.name, length, CLIENT_VALUE_LIMIT)?;
+
         type ClientData<D> = std::collections::HashMap<String, D>;
         let mut data = ClientData::new();
-        data.insert(name.name.clone(), value);
-        let mut data: ClientData<D> = self.api.passwords_post("1.0/settings/set", data).await?;
-        Ok(data
-            .remove(&name.name)
-            .expect("server did not set client setting"))
+        data.insert(name.name(), value);
+        let mut data: ClientData<D> = self.api.passwords_post(self.api.endpoint("settings", "set"), data).await?;
+        data.remove(&name.name())
+            .ok_or_else(|| Error::MissingSetting(name.name()))
+    }
+    /// Delete a client setting, it no longer appears in [list](Self::list)
+    pub async fn delete_client(&self, name: ClientSettings) -> Result<(), Error> {
+        let _: std::collections::HashMap<String, serde_json::Value> = self
+            .api
+            .passwords_post(self.api.endpoint("settings", "reset"), vec![name.name()])
+            .await?;
+        Ok(())
     }
 
     async fn query_settings(
@@ -90,7 +159,7 @@ impl<'a> SettingsApi<'a> {
         settings: SettingsBuilder<'_>,
     ) -> Result<Vec<SettingValue>, Error> {
         let data: Settings = self.api
-            .passwords_post("1.0/settings/get", settings.settings)
+            .passwords_post_read(self.api.endpoint("settings", "get"), settings.settings)
             .await?;
         Ok(data.to_values())
     }
@@ -199,8 +268,8 @@ macro_rules! settings {
         impl<'api> SettingReset<'api> {
             $(
                 pub async fn $user_field(&self) -> Result<$user_type, crate::Error> {
-                    let data: Settings = self.api.passwords_post("1.0/settings/reset", vec![$user_setting]).await?;
-                    Ok(data.$user_field.expect("server did not provide the asked setting"))
+                    let data: Settings = self.api.passwords_post(self.api.endpoint("settings", "reset"), vec![$user_setting]).await?;
+                    data.$user_field.ok_or_else(|| crate::Error::MissingSetting($user_setting.into()))
                 }
             )*
 
@@ -209,14 +278,14 @@ macro_rules! settings {
         impl<'api> SettingsFetcher<'api> {
             $(
                 pub async fn $user_field(&self) -> Result<$user_type, crate::Error> {
-                    let data: Settings = self.api.passwords_post("1.0/settings/get", vec![$user_setting]).await?;
-                    Ok(data.$user_field.expect("server did not provide the asked setting"))
+                    let data: Settings = self.api.passwords_post_read(self.api.endpoint("settings", "get"), vec![$user_setting]).await?;
+                    data.$user_field.ok_or_else(|| crate::Error::MissingSetting($user_setting.into()))
                 }
             )*
             $(
                 pub async fn $server_field(&self) -> Result<$server_type, crate::Error> {
-                    let data: Settings = self.api.passwords_post("1.0/settings/get", vec![$server_setting]).await?;
-                    Ok(data.$server_field.expect("server did not provide the asked setting"))
+                    let data: Settings = self.api.passwords_post_read(self.api.endpoint("settings", "get"), vec![$server_setting]).await?;
+                    data.$server_field.ok_or_else(|| crate::Error::MissingSetting($server_setting.into()))
                 }
             )*
             /// Note
@@ -224,7 +293,7 @@ macro_rules! settings {
             ///  - The client scope allows values with a maximum length of 128 characters
             ///  - The client scope is shared between all clients
             pub async fn client_setting<D: serde::de::DeserializeOwned>(&self, client_setting: ClientSettings) -> Result<Option<D>, crate::Error> {
-                let mut data: std::collections::HashMap<String, Option<D>> = self.api.passwords_post("1.0/settings/get", vec![client_setting.name()]).await?;
+                let mut data: std::collections::HashMap<String, Option<D>> = self.api.passwords_post_read(self.api.endpoint("settings", "get"), vec![client_setting.name()]).await?;
                 Ok(data.remove(&client_setting.name()).flatten())
             }
             /// Fetch setting (expected SettingVariant::Client) from it's name
@@ -232,8 +301,8 @@ macro_rules! settings {
                 match variant {
                     SettingVariant::Client => Err(crate::Error::InvalidSetting),
                     variant => {
-                        let data: Settings = self.api.passwords_post("1.0/settings/get", vec![variant.name()]).await?;
-                        Ok(data.to_values().pop().unwrap())
+                        let data: Settings = self.api.passwords_post_read(self.api.endpoint("settings", "get"), vec![variant.name()]).await?;
+                        data.to_values().pop().ok_or_else(|| crate::Error::MissingSetting(variant.name().into()))
                     }
                 }
             }
@@ -435,17 +504,21 @@ macro_rules! settings {
             )*
         }
 
-        #[derive(Serialize, Deserialize, Debug)]
-        /// The value of all settings
+        #[derive(Serialize, Deserialize, Debug, Default)]
+        /// The value of all settings, see [list](SettingsApi::list)
         pub struct AllSettings {
             $(
-                #[serde(rename = $user_setting)]
-                $user_field: $user_type,
+                #[serde(default, skip_serializing_if = "Option::is_none", rename = $user_setting)]
+                pub $user_field: Option<$user_type>,
             )*
             $(
-                #[serde(rename = $server_setting)]
-                $server_field: $server_type,
+                #[serde(default, skip_serializing_if = "Option::is_none", rename = $server_setting)]
+                pub $server_field: Option<$server_type>,
             )*
+            /// The client settings, and the settings unknown to this crate, by their full name
+            /// (`client.<name>`)
+            #[serde(flatten)]
+            pub client: std::collections::HashMap<String, serde_json::Value>,
         }
 
         impl Settings {
@@ -463,6 +536,10 @@ macro_rules! settings {
                 )*
                 settings
             }
+            /// Some of the settings are server settings, that can't be set
+            pub(crate) fn has_server_settings(&self) -> bool {
+                false $(|| self.$server_field.is_some())*
+            }
             /// Empty settings
             pub fn new() -> Self {
                 Default::default()
@@ -499,6 +576,7 @@ settings! {
         User: PasswordContainsSpecial(bool), password_contains_special => "user.password.generator.special",
         User: CheckForDuplicates(bool), check_for_duplicates => "user.password.security.duplicates",
         User: CheckForOldPasswords(i64), check_for_old_passwords => "user.password.security.age",
+        User: PasswordHashLength(u64), password_hash_length => "user.password.security.hash",
         User: NotifySecurityByMail(bool), notify_security_by_mail => "user.mail.security",
         User: NotifySharesByMail(bool), notify_shares_by_mail => "user.mail.shares",
         User: NotifySecurityByNotification(bool), notify_security_by_notification => "user.notification.security",
@@ -513,7 +591,7 @@ settings! {
         Server: Sharing(bool), sharing => "server.sharing.enabled",
         Server: Resharing(bool), resharing => "server.sharing.resharing",
         Server: AutoComplete(bool), autocomplete => "server.sharing.autocomplete",
-        Server: SharingTypes(Vec<String>), sharing_types => "server.sharing.types",
+        Server: SharingTypes(Vec<ShareType>), sharing_types => "server.sharing.types",
         Server: PrimaryColor(Color), primary_color => "server.theme.color.primary",
         Server: TextColor(Color), text_color => "server.theme.color.text",
         Server: BackgroundColor(Color), background_color => "server.theme.color.background",
@@ -526,6 +604,7 @@ settings! {
     }
 }
 impl WritableSetting for UserSettings {}
+impl WritableSetting for ClientSettings {}
 
 impl Setting for ClientSettings {
     fn name(&self) -> String {
@@ -533,7 +612,237 @@ impl Setting for ClientSettings {
     }
 }
 
+/// The scopes of the settings, see [list](SettingsApi::list)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingScope {
+    User,
+    Server,
+    Client,
+}
+
+/// Maximum length of the name of a client setting, without `client.`
+const CLIENT_NAME_LIMIT: usize = 48;
+/// Maximum length of the serialized value of a client setting
+const CLIENT_VALUE_LIMIT: usize = 128;
+
+fn check_size(name: &str, length: usize, limit: usize) -> Result<(), Error> {
+    if length > limit {
+        Err(Error::SettingTooLarge {
+            name: format!("client.{}", name),
+            length,
+            limit,
+        })
+    } else {
+        Ok(())
+    }
+}
+
 /// An arbitrary client setting
 pub struct ClientSettings {
     pub name: String,
 }
+
+/// A version of Nextcloud or of the passwords app, like `2019.5.0`, compared component by
+/// component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl std::str::FromStr for Version {
+    type Err = Error;
+
+    /// Missing components are 0, and anything after the digits of a component is ignored, so
+    /// that `20` and `2020.1.0-beta` can be read
+    fn from_str(version: &str) -> Result<Self, Error> {
+        let mut components = version.trim().split('.').map(|component| {
+            let digits = component.len()
+                - component
+                    .trim_start_matches(|c: char| c.is_ascii_digit())
+                    .len();
+            component[..digits].parse::<u32>()
+        });
+        let invalid = || Error::InvalidVersion(version.into());
+        let major = components.next().unwrap().map_err(|_| invalid())?;
+        let mut next = || components.next().unwrap_or(Ok(0)).map_err(|_| invalid());
+        Ok(Version::new(major, next()?, next()?))
+    }
+}
+
+impl Serialize for Version {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = String::deserialize(deserializer)?;
+        version.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Theme of the server, see [ClientCapabilities]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Theme {
+    pub label: String,
+    pub primary_color: Color,
+    pub text_color: Color,
+    pub background_color: Color,
+    pub background: Url,
+    pub logo: Url,
+    pub app_icon: Url,
+    pub folder_icon: Url,
+}
+
+/// What a client should offer to the user, derived from the server and user settings. See
+/// [capabilities](crate::AuthenticatedApi::capabilities)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClientCapabilities {
+    /// Passwords can be shared with other users
+    pub can_share: bool,
+    /// Passwords shared with the user can be shared again
+    pub can_reshare: bool,
+    /// The user has enabled client side encryption, new objects must be encrypted
+    pub cse_required: bool,
+    /// The length of the password hashes chosen by the user, see
+    /// [hash_password](crate::password::hash_password)
+    #[serde(default = "full_hash_length")]
+    pub hash_length: usize,
+    /// The server autocompletes the users to share with
+    pub autocomplete_enabled: bool,
+    /// The share types supported by the server
+    pub sharing_types: Vec<ShareType>,
+    pub theme: Theme,
+    /// The version of Nextcloud
+    pub server_version: Version,
+    /// The version of the passwords app, `None` if the server does not advertise it in its OCS
+    /// capabilities
+    pub app_version: Option<Version>,
+}
+
+fn full_hash_length() -> usize {
+    crate::password::HASH_LENGTH
+}
+
+/// The response of the OCS capabilities endpoint
+#[derive(Deserialize)]
+pub(crate) struct OcsCapabilities {
+    version: Option<OcsVersion>,
+    capabilities: OcsAppsCapabilities,
+}
+
+#[derive(Deserialize)]
+struct OcsVersion {
+    string: String,
+}
+
+#[derive(Deserialize)]
+struct OcsAppsCapabilities {
+    passwords: Option<PasswordsCapabilities>,
+}
+
+#[derive(Deserialize)]
+struct PasswordsCapabilities {
+    version: Option<Version>,
+}
+
+/// The length of the hashes, older servers without the setting always store the full hash
+fn hash_length(setting: Option<u64>) -> usize {
+    setting.map_or(crate::password::HASH_LENGTH, |length| length as usize)
+}
+
+impl ClientCapabilities {
+    /// Fetch only the [hash_length](Self::hash_length)
+    pub(crate) async fn fetch_hash_length(api: &AuthenticatedApi) -> Result<usize, Error> {
+        let names = vec![UserSettings::PasswordHashLength.name()];
+        let data: Settings = api
+            .passwords_post_read(api.endpoint("settings", "get"), names)
+            .await?;
+        Ok(hash_length(data.password_hash_length))
+    }
+
+    pub(crate) async fn fetch(api: &AuthenticatedApi) -> Result<Self, Error> {
+        let names = vec![
+            ServerSettings::Version.name(),
+            ServerSettings::Sharing.name(),
+            ServerSettings::Resharing.name(),
+            ServerSettings::AutoComplete.name(),
+            ServerSettings::SharingTypes.name(),
+            UserSettings::ClientSideEncryption.name(),
+            UserSettings::PasswordHashLength.name(),
+            ServerSettings::Label.name(),
+            ServerSettings::PrimaryColor.name(),
+            ServerSettings::TextColor.name(),
+            ServerSettings::BackgroundColor.name(),
+            ServerSettings::BackgroundTheme.name(),
+            ServerSettings::Logo.name(),
+            ServerSettings::AppIcon.name(),
+            ServerSettings::FolderIcon.name(),
+        ];
+        let data: Settings = api.passwords_post_read(api.endpoint("settings", "get"), names).await?;
+        fn required<T>(value: Option<T>, setting: impl Setting) -> Result<T, Error> {
+            value.ok_or_else(|| Error::MissingSetting(setting.name()))
+        }
+
+        let ocs = match api.ocs_capabilities().await {
+            Ok(ocs) => Some(ocs),
+            Err(e) => {
+                log::warn!("Could not fetch the OCS capabilities: {}", e);
+                None
+            }
+        };
+        // The setting has only the major version
+        let server_version = match ocs.as_ref().and_then(|ocs| ocs.version.as_ref()) {
+            Some(version) => version.string.parse()?,
+            None => required(data.version, ServerSettings::Version)?.parse()?,
+        };
+        let app_version = ocs
+            .and_then(|ocs| ocs.capabilities.passwords)
+            .and_then(|passwords| passwords.version);
+
+        let can_share = required(data.sharing, ServerSettings::Sharing)?;
+        Ok(ClientCapabilities {
+            server_version,
+            app_version,
+            can_share,
+            can_reshare: can_share && required(data.resharing, ServerSettings::Resharing)?,
+            cse_required: required(
+                data.client_side_encryption,
+                UserSettings::ClientSideEncryption,
+            )? != 0,
+            hash_length: hash_length(data.password_hash_length),
+            autocomplete_enabled: required(data.autocomplete, ServerSettings::AutoComplete)?,
+            sharing_types: required(data.sharing_types, ServerSettings::SharingTypes)?,
+            theme: Theme {
+                label: required(data.label, ServerSettings::Label)?,
+                primary_color: required(data.primary_color, ServerSettings::PrimaryColor)?,
+                text_color: required(data.text_color, ServerSettings::TextColor)?,
+                background_color: required(data.background_color, ServerSettings::BackgroundColor)?,
+                background: required(data.background_theme, ServerSettings::BackgroundTheme)?,
+                logo: required(data.logo, ServerSettings::Logo)?,
+                app_icon: required(data.app_icon, ServerSettings::AppIcon)?,
+                folder_icon: required(data.folder_icon, ServerSettings::FolderIcon)?,
+            },
+        })
+    }
+}
@@ -1,9 +1,12 @@
 use crate::{create_binding, create_details, password, AuthenticatedApi, Error};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 // TODO: figure out how to do (owner, receiver)
 
+/// Longest wait of [partners_throttled](ShareApi::partners_throttled) before retrying
+pub const MAX_PARTNERS_WAIT: std::time::Duration = std::time::Duration::from_secs(10);
+
 pub struct ShareApi<'a> {
     pub(crate) api: &'a AuthenticatedApi,
 }
@@ -14,7 +17,8 @@ impl<'a> ShareApi<'a> {
     /// Notes
     ///  - This command will fail if sharing is disabled
     ///  - The limit can not be less than 5 or more than 256
-    ///  - This api endpoint has a rate limit of 45 requests per minute
+    ///  - This api endpoint has a rate limit of 45 requests per minute, exceeding it fails with
+    ///    [Error::RateLimited]
     pub async fn partners(
         &self,
         search: Option<String>,
@@ -28,28 +32,34 @@ impl<'a> ShareApi<'a> {
             limit: Option<u64>,
         }
         let req = Request { search, limit };
-        let ret: Vec<HashMap<uuid::Uuid, String>> = if req.search.is_none() {
+        let ret: Partners = if req.search.is_none() {
             self.api
-                .passwords_get("/api/1.0/share/partners", req)
+                .passwords_get(self.api.endpoint("share", "partners"), req)
                 .await?
         } else {
             self.api
-                .passwords_post("/api/1.0/share/partners", req)
+                .passwords_post_read(self.api.endpoint("share", "partners"), req)
                 .await?
         };
-        Ok(ret
-            .into_iter()
-            .map(|partner| {
-                partner
-                    .into_iter()
-                    .map(|(user_id, display_name)| Partner {
-                        user_id,
-                        display_name,
-                    })
-                    .next()
-            })
-            .filter_map(std::convert::identity)
-            .collect())
+        Ok(ret.into_partners())
+    }
+
+    /// Like [partners](Self::partners), waiting and retrying once if the rate limit was
+    /// exceeded, for autocompletions requesting the partners while the user types. If the server
+    /// asks to wait longer than [MAX_PARTNERS_WAIT] this fails with [Error::RateLimited] at once.
+    pub async fn partners_throttled(
+        &self,
+        search: Option<String>,
+        limit: Option<u64>,
+    ) -> Result<Vec<Partner>, Error> {
+        match self.partners(search.clone(), limit).await {
+            Err(Error::RateLimited { retry_after }) if retry_after <= MAX_PARTNERS_WAIT => {
+                log::debug!("Partners are rate limited, retrying in {:?}", retry_after);
+                crate::runtime::sleep(retry_after).await;
+                self.partners(search, limit).await
+            }
+            result => result,
+        }
     }
 
     /// The create action creates a new share with the given attributes.
@@ -58,14 +68,30 @@ impl<'a> ShareApi<'a> {
     ///  - This action will fail if the password is hidden or the CSE does not support sharing
     ///  - You can not share a password with the same user more than once
     ///  - This command will fail if sharing is disabled
+    ///  - If the capabilities were fetched, the share type is checked against the supported ones
     pub async fn create(&self, create: CreateShare) -> Result<uuid::Uuid, Error> {
+        crate::Validate::validate(&create)?;
+        self.create_unchecked(create).await
+    }
+
+    /// Like [create](ShareApi::create), without checking the limits of the server
+    pub async fn create_unchecked(&self, create: CreateShare) -> Result<uuid::Uuid, Error> {
+        if let (Some(requested), Some(capabilities)) = (&create.ty, self.api.cached_capabilities())
+        {
+            if !capabilities.sharing_types.contains(requested) {
+                return Err(Error::UnsupportedShareType {
+                    requested: requested.clone(),
+                    supported: capabilities.sharing_types,
+                });
+            }
+        }
         #[derive(Serialize, Deserialize)]
         struct Resp {
             id: uuid::Uuid,
         }
         let resp: Resp = self
             .api
-            .passwords_post("/api/1.0/share/create", create)
+            .passwords_post(self.api.endpoint("share", "create"), create)
             .await?;
         Ok(resp.id)
     }
@@ -82,25 +108,38 @@ impl<'a> ShareApi<'a> {
         }
         let resp: Resp = self
             .api
-            .passwords_post("/api/1.0/share/update", update)
+            .passwords_post(self.api.endpoint("share", "update"), update)
             .await?;
         Ok(resp.id)
     }
 
+    /// Apply the changes of the patch, see [SharePatch].
+    ///
+    /// Notes
+    ///  - An empty patch sends nothing and returns `None`
+    ///  - Shares have no revisions, so concurrent modifications can't be detected
+    pub async fn apply_patch(&self, patch: SharePatch) -> Result<Option<uuid::Uuid>, Error> {
+        if patch.is_empty() {
+            return Ok(None);
+        }
+        self.update(patch.update).await.map(Some)
+    }
+
     /// The delete action deletes a share.
     ///
     /// Notes
     ///  - You can only delete shares owned by the user.
     ///  - If you want to delete a share where the current user is the receiver, you need to delete the password instead
     ///  - This action still works if sharing has been disabled
-    pub async fn delete(&self, share_id: uuid::Uuid) -> Result<uuid::Uuid, Error> {
+    pub async fn delete(&self, share_id: impl Into<uuid::Uuid>) -> Result<uuid::Uuid, Error> {
         #[derive(Serialize, Deserialize)]
         struct Id {
             id: uuid::Uuid,
         }
+        let id = share_id.into();
         let resp: Id = self
             .api
-            .passwords_post("/api/1.0/share/delete", Id { id: share_id })
+            .passwords_post(self.api.endpoint("share", "delete"), Id { id })
             .await?;
         Ok(resp.id)
     }
@@ -116,8 +155,28 @@ impl<'a> ShareApi<'a> {
             details: Option<String>,
         }
         self.api
-            .passwords_post(
-                "1.0/share/list",
+            .passwords_list(
+                self.api.endpoint("share", "list"),
+                DetailsStr {
+                    details: details.map(|d| d.to_string()),
+                },
+            )
+            .await
+    }
+
+    /// Like [list](Self::list), skipping the shares that can't be read instead of failing
+    pub async fn list_lossy(
+        &self,
+        details: Option<Details>,
+    ) -> Result<(Vec<Share>, Vec<crate::ItemError>), Error> {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct DetailsStr {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            details: Option<String>,
+        }
+        self.api
+            .passwords_list_lossy(
+                self.api.endpoint("share", "list"),
                 DetailsStr {
                     details: details.map(|d| d.to_string()),
                 },
@@ -129,7 +188,11 @@ impl<'a> ShareApi<'a> {
     ///
     /// Notes
     ///  - This action still works if sharing has been disabled
-    pub async fn get(&self, details: Option<Details>, id: uuid::Uuid) -> Result<Share, Error> {
+    pub async fn get(
+        &self,
+        details: Option<Details>,
+        id: impl Into<uuid::Uuid>,
+    ) -> Result<Share, Error> {
         #[derive(Serialize, Deserialize)]
         struct Show {
             id: uuid::Uuid,
@@ -137,10 +200,12 @@ impl<'a> ShareApi<'a> {
             details: Option<String>,
         }
         let request = Show {
-            id,
+            id: id.into(),
             details: details.map(|d| d.to_string()),
         };
-        self.api.passwords_post("1.0/share/show", request).await
+        self.api
+            .passwords_post_read(self.api.endpoint("share", "show"), request)
+            .await
     }
     
     /// The find action can be used to find all shares matching the given search criteria
@@ -152,39 +217,59 @@ impl<'a> ShareApi<'a> {
         criteria: ShareSearch,
         details: Option<Details>,
     ) -> Result<Vec<Share>, Error> {
-        #[derive(Serialize)]
-        struct Request {
-            criteria: ShareSearch,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            details: Option<String>,
-        }
-        let request = Request {
+        let request = crate::utils::FindRequest {
             criteria,
             details: details.map(|d| d.to_string()),
         };
-        self.api.passwords_post("1.0/share/find", request).await
+        self.api
+            .passwords_post_read(self.api.endpoint("share", "find"), request)
+            .await
+    }
+
+    /// The body that [find](ShareApi::find) would send with these arguments, without sending it
+    pub fn explain_find(
+        &self,
+        criteria: &ShareSearch,
+        details: Option<&Details>,
+    ) -> serde_json::Value {
+        serde_json::to_value(crate::utils::FindRequest {
+            criteria,
+            details: details.map(|d| d.to_string()),
+        })
+        .expect("search criteria are always serializable")
     }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CreateShare {
     password: uuid::Uuid,
-    receiver: uuid::Uuid,
+    receiver: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "type")]
-    ty: Option<String>,
+    ty: Option<ShareType>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    expires: Option<Option<u64>>,
+    expires: Option<Option<crate::UnixTime>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     editable: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     shareable: Option<bool>,
 }
+/// Maximum length of a Nextcloud user id, the receiver of a share
+pub const MAX_RECEIVER_LENGTH: usize = 64;
+
+impl crate::Validate for CreateShare {
+    fn validate(&self) -> Result<(), crate::ValidationError> {
+        crate::utils::check_length("receiver", &self.receiver, MAX_RECEIVER_LENGTH)
+    }
+}
+
 impl CreateShare {
-    pub fn new(password_id: uuid::Uuid, receiver_id: uuid::Uuid) -> Self {
+    /// Share the password with the user, the receiver is the Nextcloud user id (see
+    /// [Partner::user_id])
+    pub fn new(password_id: uuid::Uuid, receiver_id: impl Into<String>) -> Self {
         CreateShare {
             password: password_id,
-            receiver: receiver_id,
+            receiver: receiver_id.into(),
             ty: None,
             expires: None,
             editable: None,
@@ -192,14 +277,14 @@ impl CreateShare {
         }
     }
     /// The type of the share
-    pub fn share_type(self, ty: String) -> Self {
+    pub fn share_type(self, ty: ShareType) -> Self {
         Self {
             ty: Some(ty),
             ..self
         }
     }
-    /// Unix timestamp when the share will expire
-    pub fn expires(self, expires: Option<u64>) -> Self {
+    /// When the share will expire
+    pub fn expires(self, expires: Option<crate::UnixTime>) -> Self {
         Self {
             expires: Some(expires),
             ..self
@@ -221,15 +306,101 @@ impl CreateShare {
     }
 }
 
+/// The kind of receiver of a share. The types supported by the server are in
+/// [ClientCapabilities::sharing_types](crate::settings::ClientCapabilities::sharing_types)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ShareType {
+    /// Share with a user of the server
+    User,
+    /// A type unknown to this crate (refused with the `strict` feature)
+    Other(String),
+}
+
+impl ShareType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ShareType::User => "user",
+            ShareType::Other(ty) => ty,
+        }
+    }
+}
+
+impl std::str::FromStr for ShareType {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "user" => ShareType::User,
+            other => ShareType::Other(other.into()),
+        })
+    }
+}
+
+impl std::fmt::Display for ShareType {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{}", self.as_str())
+    }
+}
+
+impl Serialize for ShareType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ShareType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let ty = String::deserialize(deserializer)?;
+        match ty.parse().unwrap() {
+            ShareType::Other(other) => {
+                crate::utils::unknown_value("type", &other, ShareType::Other(other.clone()))
+            }
+            known => Ok(known),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Partner {
-    pub user_id: uuid::Uuid,
+    /// The Nextcloud user id, like `alice` or `john.doe@example.com`
+    pub user_id: String,
     pub display_name: String,
 }
 
+/// The display names by user id answered by `share/partners`, older versions of the crate
+/// expected a list of single entry objects so both are accepted
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Partners {
+    Object(BTreeMap<String, String>),
+    List(Vec<BTreeMap<String, String>>),
+}
+
+impl Partners {
+    fn into_partners(self) -> Vec<Partner> {
+        let partners: Box<dyn Iterator<Item = (String, String)>> = match self {
+            Partners::Object(partners) => Box::new(partners.into_iter()),
+            Partners::List(partners) => Box::new(partners.into_iter().flatten()),
+        };
+        partners
+            .map(|(user_id, display_name)| Partner {
+                user_id,
+                display_name,
+            })
+            .collect()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Person {
-    pub id: uuid::Uuid,
+    /// The Nextcloud user id
+    pub id: String,
     pub name: String,
 }
 
@@ -258,19 +429,140 @@ impl PasswordInfo {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ShareInfoKind {
+    Id(uuid::Uuid),
+    Data(Box<Share>),
+}
+
+/// The share a password was received from, see [Password::share](password::Password::share)
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(transparent)]
+pub struct ShareInfo(ShareInfoKind);
+impl ShareInfo {
+    pub fn get(&self) -> &ShareInfoKind {
+        &self.0
+    }
+    /// The id of the share
+    pub fn id(&self) -> uuid::Uuid {
+        match &self.0 {
+            ShareInfoKind::Id(id) => *id,
+            ShareInfoKind::Data(share) => share.id,
+        }
+    }
+    pub fn new(id: uuid::Uuid) -> Self {
+        Self(ShareInfoKind::Id(id))
+    }
+}
+
 create_binding! {
     #[derive(Serialize, Deserialize, Debug)]
-    pub struct Share {
+    pub struct Share [extra] {
         pub id: uuid::Uuid [update(required) versioned(false)],
-        pub created: u64 [search versioned(false)],
-        pub updated: u64 [search versioned(false)],
-        pub expires: Option<u64> [update(optional) search versioned(false)],
+        pub created: crate::UnixTime [search versioned(false)],
+        pub updated: crate::UnixTime [search versioned(false)],
+        pub expires: Option<crate::UnixTime> [update(optional) search versioned(false)],
         pub editable: bool [update(optional) search versioned(false)],
         pub shareable: bool [update(optional) search versioned(false)],
         #[serde(rename = "updatePending")]
         pub update_pending: bool [ versioned(false)],
-        pub password: PasswordInfo [ versioned(false)],
+        pub password: PasswordInfo [search versioned(false)],
         pub owner: Person [versioned(false)],
         pub receiver: Person [versioned(false)],
     }
 }
+
+impl SharePatch {
+    /// Start a patch of this share
+    pub fn new(original: &Share) -> Self {
+        Self::from_update(
+            UpdateShare::new(original.id)
+                .expires(original.expires)
+                .editable(original.editable)
+                .shareable(original.shareable)
+                .extra(original.extra.clone()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{QueryKind, SearchQuery};
+    use serde_json::json;
+
+    #[test]
+    #[cfg(not(feature = "strict"))]
+    fn share_types() {
+        let types: Vec<ShareType> = serde_json::from_value(json!(["user", "link"])).unwrap();
+        assert_eq!(
+            types,
+            vec![ShareType::User, ShareType::Other("link".into())]
+        );
+        assert_eq!(
+            serde_json::to_value(&types).unwrap(),
+            json!(["user", "link"])
+        );
+    }
+
+    #[test]
+    fn partners() {
+        let partners: Partners = serde_json::from_value(json!({
+            "alice": "Alice",
+            "john.doe@example.com": "John Doe",
+        }))
+        .unwrap();
+        let partners = partners.into_partners();
+        assert_eq!(partners.len(), 2);
+        assert_eq!(partners[1].user_id, "john.doe@example.com");
+        assert_eq!(partners[1].display_name, "John Doe");
+
+        let partners: Partners = serde_json::from_value(json!([{"alice": "Alice"}])).unwrap();
+        assert_eq!(partners.into_partners()[0].user_id, "alice");
+
+        let person: Person =
+            serde_json::from_value(json!({"id": "john.doe@example.com", "name": "John Doe"}))
+                .unwrap();
+        assert_eq!(person.id, "john.doe@example.com");
+        let create = CreateShare::new(uuid::Uuid::nil(), "alice");
+        assert_eq!(serde_json::to_value(&create).unwrap()["receiver"], "alice");
+    }
+
+    #[test]
+    fn search_body() {
+        let search = ShareSearch::new()
+            .and_expires(SearchQuery::new(None, QueryKind::Exact))
+            .unwrap()
+            .and_editable(SearchQuery::new(true, QueryKind::Exact))
+            .unwrap()
+            .and_created(SearchQuery::new(
+                crate::utils::unix_time(42),
+                QueryKind::LessThan,
+            ))
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&search).unwrap(),
+            json!({
+                "expires": null,
+                "editable": true,
+                "created": ["lt", 42],
+            })
+        );
+    }
+
+    #[test]
+    fn search_by_password() {
+        let password = uuid::Uuid::parse_str("a2e0e74b-2ee1-4b4c-9f8b-a3d8b0bb2d27").unwrap();
+        let search = ShareSearch::new()
+            .and_password(SearchQuery::new(
+                PasswordInfo::new(password),
+                QueryKind::Exact,
+            ))
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&search).unwrap(),
+            json!({"password": "a2e0e74b-2ee1-4b4c-9f8b-a3d8b0bb2d27"})
+        );
+    }
+}
@@ -3,7 +3,7 @@ use serde::{Serialize, Deserialize};
 
 create_calls! {
     TagApi where 
-        Endpoint = "1.0/tag",
+        Resource = "tag",
         Details: Details,
         Type: Tag,
         Create: CreateTag,
@@ -33,7 +33,10 @@ create_calls! {
         ///
         /// Notes
         ///  - The property trashed will be set to false if not present
-        ///  - The list will not include hidden tags
+        ///  - The list will not include hidden tags, they can not be searched either: they are
+        ///    only reachable by their id with [get](Self::get)
+        ///  - Only the created, updated, edited, cseType, sseType, trashed and favorite properties
+        ///    can be searched
         pub async fn find(&self, criteria: Criteria, details: Option<Details>) -> Result<Vec<Type>, Error>;
 
         Create;
@@ -74,9 +77,38 @@ create_calls! {
         ///  - The server side encryption type may change
         ///  - Deleted tags can not be restored
         pub async fn restore(&self, id: uuid::Uuid, revision: Option<uuid::Uuid>) -> Result<Identifier, Error>;
+
+        Patches: TagPatch;
+        /// Apply the changes of the patch, see [TagPatch]. This is the recommended way to modify
+        /// a tag.
+        ///
+        /// Notes
+        ///  - An empty patch sends nothing and returns `None`
+        ///  - If the tag changed on the server since the patch was created, this fails with
+        ///    [Conflict](crate::Error::Conflict)
+        pub async fn apply_patch(&self, patch: Patch) -> Result<Option<Identifier>, Error>;
+
+        Summaries: TagSummary;
+        /// List all the tags like [list](TagApi::list), but only read the fields of [TagSummary]
+        pub async fn list_summaries(&self) -> Result<Vec<Summary>, Error>;
+
+        Trash;
+        /// List the tags in the trash, that [list](TagApi::list) leaves out
+        pub async fn trashed(&self, details: Option<Details>) -> Result<Vec<Type>, Error>;
     }
 }
 
+/// The fields of a tag needed to display it in a list, see
+/// [list_summaries](TagApi::list_summaries)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TagSummary {
+    pub id: uuid::Uuid,
+    pub revision: uuid::Uuid,
+    pub label: String,
+    pub color: Color,
+    pub favorite: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TagIdentifier {
     pub id: uuid::Uuid,
@@ -98,13 +130,13 @@ create_details! {
 
 create_binding! {
     #[derive(Serialize, Deserialize, Debug)]
-    pub struct Tag {
+    pub struct Tag [extra] {
         pub id: String [update(required) versioned(false)],
         pub label: String [versioned(true) create(required) update(required)],
         pub color: Color [versioned(true) create(required) update(required)],
-        pub created: u64 [search versioned(false)],
-        pub updated: u64 [versioned(true) search],
-        pub edited: u64 [versioned(true) create(optional) update(optional) search],
+        pub created: crate::UnixTime [search versioned(false)],
+        pub updated: crate::UnixTime [versioned(true) search],
+        pub edited: crate::UnixTime [versioned(true) create(optional) update(optional) search],
         pub revision: uuid::Uuid [versioned(true)],
         #[serde(rename = "cseType")]
         pub cse_type: String [versioned(true) create(optional) update(optional) search],
@@ -122,3 +154,147 @@ create_binding! {
         pub revisions: Option<Vec<VersionedTag>> [versioned(false)],
     }
 }
+
+/// Maximum length of the label of a tag
+pub const MAX_LABEL_LENGTH: usize = 64;
+
+impl crate::Validate for CreateTag {
+    fn validate(&self) -> Result<(), crate::ValidationError> {
+        crate::utils::check_length("label", &self.label, MAX_LABEL_LENGTH)
+    }
+
+    fn cse_type(&self) -> Option<&str> {
+        self.cse_type.as_deref()
+    }
+}
+
+impl crate::Validate for UpdateTag {
+    fn validate(&self) -> Result<(), crate::ValidationError> {
+        crate::utils::check_length("label", &self.label, MAX_LABEL_LENGTH)
+    }
+
+    fn cse_type(&self) -> Option<&str> {
+        self.cse_type.as_deref()
+    }
+}
+
+/// An update that keeps all the current values of the tag
+pub(crate) fn update_from(tag: &Tag) -> UpdateTag {
+    let versioned = &tag.versioned;
+    UpdateTag::new(
+        tag.id.clone(),
+        versioned.label.clone(),
+        versioned.color.clone(),
+    )
+    .edited(versioned.edited)
+    .cse_type(versioned.cse_type.clone())
+    .cse_key(versioned.cse_key.clone())
+    .hidden(versioned.hidden)
+    .favorite(versioned.favorite)
+    .extra(tag.extra.clone())
+}
+
+/// Keep all the current values of the tag
+impl From<Tag> for UpdateTag {
+    fn from(tag: Tag) -> Self {
+        update_from(&tag)
+    }
+}
+
+impl TagPatch {
+    /// Start a patch of this tag. Applying the patch fails if the tag was modified on the server
+    /// in the meantime.
+    pub fn new(original: &Tag) -> Self {
+        Self::from_update(update_from(original).expected_revision(original.versioned.revision))
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.update.label = label.into();
+        self.mark("label");
+        self
+    }
+
+    pub fn color(mut self, color: impl Into<Color>) -> Self {
+        self.update.color = color.into();
+        self.mark("color");
+        self
+    }
+}
+
+impl crate::utils::Revisioned for Tag {
+    fn current_revision(&self) -> uuid::Uuid {
+        self.versioned.revision
+    }
+}
+
+impl crate::Sortable for Tag {
+    fn label(&self) -> &str {
+        &self.versioned.label
+    }
+
+    fn updated(&self) -> crate::UnixTime {
+        self.versioned.updated
+    }
+
+    fn created(&self) -> crate::UnixTime {
+        self.created
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{QueryKind, SearchQuery};
+    use serde_json::json;
+
+    pub(crate) fn tag_json() -> serde_json::Value {
+        json!({
+            "id": "9c8b7a6d-5e4f-4a3b-8c2d-1e0f9a8b7c6d",
+            "label": "Work",
+            "color": "#745bca",
+            "created": 1_600_000_000,
+            "updated": 1_600_000_000,
+            "edited": 1_500_000_000,
+            "revision": "4a0a9fbf-6ca5-4d3f-a2bb-0a9f1d0e1c55",
+            "cseType": "none",
+            "cseKey": "7d0e1c55-4a0a-4d3f-a2bb-0a9f1d0e1c55",
+            "sseType": "SSEv1r2",
+            "client": "CLIENT::UNKNOWN",
+            "hidden": true,
+            "trashed": false,
+            "favorite": true,
+            "otherApp": {"key": "value"},
+        })
+    }
+
+    #[test]
+    fn into_update() {
+        let json = tag_json();
+        let tag: Tag = serde_json::from_value(json.clone()).unwrap();
+        let update = serde_json::to_value(UpdateTag::from(tag)).unwrap();
+        for field in &[
+            "id", "label", "color", "edited", "cseType", "cseKey", "hidden", "favorite", "otherApp",
+        ] {
+            assert_eq!(update[field], json[field], "{}", field);
+        }
+    }
+
+    #[test]
+    fn search_body() {
+        let search = TagSearch::new()
+            .and_favorite(SearchQuery::new(true, QueryKind::Exact))
+            .unwrap()
+            .and_updated(SearchQuery::new(
+                crate::utils::unix_time(5),
+                QueryKind::GreaterOrEqual,
+            ))
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&search).unwrap(),
+            json!({
+                "favorite": true,
+                "updated": ["ge", 5],
+            })
+        );
+    }
+}
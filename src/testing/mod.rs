@@ -0,0 +1,177 @@
+pub mod fake;
+
+use serde_json::{json, Map, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// A request received by a [Handler]
+pub struct Request {
+    pub path: String,
+    /// The `X-API-SESSION` header
+    pub session: Option<String>,
+    pub user_agent: Option<String>,
+    /// The `Authorization` header
+    pub authorization: Option<String>,
+    /// The `OTP` header
+    pub otp: Option<String>,
+    /// The `Cookie` header
+    pub cookie: Option<String>,
+    pub body: Value,
+}
+
+/// Status, headers and body
+pub type Response = (u16, Vec<(&'static str, String)>, Value);
+
+/// A server started with [listen]
+pub trait Handler: Send + 'static {
+    fn handle(&mut self, request: &Request) -> Response;
+}
+
+/// Listen on localhost and answer the requests with the handler built by `handler` from the URL
+/// of the server, in background threads
+pub fn listen<H: Handler>(handler: impl FnOnce(Url) -> H) -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("could not start the server");
+    let url: Url = format!("http://{}/", listener.local_addr().unwrap())
+        .parse()
+        .unwrap();
+    let server = Arc::new(Mutex::new(handler(url.clone())));
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let server = server.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = serve(&server, stream) {
+                    eprintln!("test server: {}", e);
+                }
+            });
+        }
+    });
+    url
+}
+
+/// The answer of the API to an unknown endpoint or object
+pub fn not_found() -> Response {
+    (
+        404,
+        vec![],
+        json!({"status": "error", "id": 404, "message": "Not found"}),
+    )
+}
+
+fn serve(server: &Mutex<impl Handler>, stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let path = line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+
+    let mut length = 0;
+    let mut session = None;
+    let mut user_agent = None;
+    let mut authorization = None;
+    let mut otp = None;
+    let mut cookie = None;
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("x-api-session") {
+                session = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("user-agent") {
+                user_agent = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("otp") {
+                otp = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("cookie") {
+                cookie = Some(value.trim().to_string());
+            }
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    // The login flow polls with a form, every other request is JSON
+    let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+    let request = Request {
+        path,
+        session,
+        user_agent,
+        authorization,
+        otp,
+        cookie,
+        body,
+    };
+    let (status, mut headers, body) = server.lock().unwrap().handle(&request);
+    // A handler answering something else than JSON gives the content type and the raw body
+    let body = match body {
+        Value::String(raw) if headers.iter().any(|(name, _)| *name == "Content-Type") => raw,
+        body => {
+            headers.push(("Content-Type", "application/json".into()));
+            body.to_string()
+        }
+    };
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        if status == 200 { "OK" } else { "Error" },
+        body.len(),
+    );
+    for (name, value) in headers {
+        response += &format!("{}: {}\r\n", name, value);
+    }
+    response += "\r\n";
+    response += &body;
+    let mut stream = stream;
+    stream.write_all(response.as_bytes())
+}
+
+/// The settings of a new account on the server at `url`
+pub fn default_settings(url: &Url) -> Map<String, Value> {
+    let settings = json!({
+        "user.password.generator.strength": 1,
+        "user.password.generator.numbers": false,
+        "user.password.generator.special": false,
+        "user.password.security.duplicates": true,
+        "user.password.security.age": 0,
+        "user.password.security.hash": 40,
+        "user.mail.security": true,
+        "user.mail.shares": false,
+        "user.notification.security": true,
+        "user.notification.shares": true,
+        "user.notification.errors": true,
+        "user.encryption.sse": 1,
+        "user.encryption.cse": 0,
+        "user.session.lifetime": 600,
+        "server.version": "20.0.0",
+        "server.baseUrl": url.as_str(),
+        "server.baseUrl.webdav": format!("{}remote.php/webdav/", url),
+        "server.sharing.enabled": true,
+        "server.sharing.resharing": true,
+        "server.sharing.autocomplete": true,
+        "server.sharing.types": ["user"],
+        "server.theme.color.primary": "#0082c9",
+        "server.theme.color.text": "#ffffff",
+        "server.theme.color.background": "#ffffff",
+        "server.theme.background": format!("{}core/img/background.png", url),
+        "server.theme.logo": format!("{}core/img/logo.svg", url),
+        "server.theme.label": "Nextcloud",
+        "server.theme.app.icon": format!("{}apps/passwords/img/app.svg", url),
+        "server.theme.folder.icon": format!("{}core/img/filetypes/folder.svg", url),
+        "client.example.theme": "dark",
+    });
+    match settings {
+        Value::Object(settings) => settings,
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,86 @@
+//! Tracking of the bruteforce protection of the servers, so that a retry loop in the application
+//! does not make the lockout worse
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A failed session open slower than this is considered as delayed by the bruteforce protection
+pub(crate) const SLOW_FAILURE: Duration = Duration::from_secs(3);
+/// Minimum time without trying to open a session once the server throttles us
+pub(crate) const MIN_COOLDOWN: Duration = Duration::from_secs(30);
+
+static COOLDOWNS: Mutex<Option<HashMap<String, Instant>>> = Mutex::new(None);
+
+/// Time left before opening a session on this server is allowed again
+pub(crate) fn remaining(server: &str) -> Option<Duration> {
+    let mut cooldowns = COOLDOWNS.lock().unwrap();
+    let cooldowns = cooldowns.get_or_insert_with(HashMap::new);
+    let until = *cooldowns.get(server)?;
+    let now = Instant::now();
+    if until > now {
+        Some(until - now)
+    } else {
+        cooldowns.remove(server);
+        None
+    }
+}
+
+/// Refuse to open sessions on this server for `cooldown`
+pub(crate) fn penalize(server: &str, cooldown: Duration) {
+    COOLDOWNS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(server.to_owned(), Instant::now() + cooldown);
+}
+
+pub(crate) fn clear(server: &str) {
+    if let Some(cooldowns) = COOLDOWNS.lock().unwrap().as_mut() {
+        cooldowns.remove(server);
+    }
+}
+
+/// Whether a failed session open looks like the work of the bruteforce protection
+pub(crate) fn is_throttled(status: u16, elapsed: Duration, body: &str) -> bool {
+    status == 429
+        || (matches!(status, 401 | 403)
+            && (elapsed >= SLOW_FAILURE || body.to_lowercase().contains("brute")))
+}
+
+/// The `Retry-After` header, only in its delay form
+pub(crate) fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let seconds = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    seconds.trim().parse().ok().map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detection() {
+        let fast = Duration::from_millis(100);
+        assert!(is_throttled(429, fast, ""));
+        assert!(is_throttled(401, SLOW_FAILURE, ""));
+        assert!(is_throttled(
+            403,
+            fast,
+            "{\"message\":\"Bruteforce protection\"}"
+        ));
+        assert!(!is_throttled(401, fast, ""));
+        assert!(!is_throttled(500, SLOW_FAILURE, ""));
+    }
+
+    #[test]
+    fn cooldown() {
+        let server = "https://throttle.example.com/";
+        assert_eq!(remaining(server), None);
+        penalize(server, MIN_COOLDOWN);
+        assert!(remaining(server).unwrap() > MIN_COOLDOWN / 2);
+        clear(server);
+        assert_eq!(remaining(server), None);
+        penalize(server, Duration::from_secs(0));
+        assert_eq!(remaining(server), None);
+    }
+}
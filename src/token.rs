@@ -8,21 +8,151 @@ pub struct TokenApi<'a> {
 }
 
 impl<'a> TokenApi<'a> {
+    /// The second factors configured on the account, the ones with
+    /// [needs_request](ProviderInfo::needs_request) need a call to [request](Self::request)
+    /// before the user can enter the token.
+    pub async fn providers(&self) -> Result<Vec<ProviderInfo>, Error> {
+        let requirements: Value = self
+            .api
+            .passwords_get(self.api.endpoint("session", "request"), ())
+            .await?;
+        providers(&requirements)
+    }
+
     /// The request action is required for some token in order to send the user the token.
     /// For example the email token will send an email to the users mail account.
     /// It is recommended to only call this action if the user has chosen that token, not just trigger it for all available tokens.
-    pub async fn request(&self, provider: &str) -> Result<Response, Error> {
-        self.api
-            .passwords_get(
-                format!("1.0/token/{provider}/request", provider = provider),
-                (),
-            )
-            .await
+    ///
+    /// This needs an open session, use [request_token](AuthenticatedApi::request_token) to
+    /// request the token needed to open one.
+    ///
+    /// Fails with [Error::TokenRequestFailed] if the server could not send the token.
+    pub async fn request(&self, provider: &TokenProvider) -> Result<TokenRequest, Error> {
+        let endpoint = format!("{}/request", provider.id());
+        let response: RequestResponse = self
+            .api
+            .passwords_get(self.api.endpoint("token", &endpoint), ())
+            .await?;
+        response.into_request(provider)
+    }
+}
+
+/// The answer of the request action of a provider
+#[derive(Deserialize)]
+pub(crate) struct RequestResponse {
+    success: bool,
+    #[serde(default)]
+    data: Value,
+}
+
+impl RequestResponse {
+    /// The typed answer for `provider`, see [TokenApi::request]
+    pub(crate) fn into_request(self, provider: &TokenProvider) -> Result<TokenRequest, Error> {
+        if !self.success {
+            return Err(Error::TokenRequestFailed(provider.clone()));
+        }
+        Ok(match provider {
+            TokenProvider::Notification => {
+                #[derive(Deserialize)]
+                struct Notification {
+                    id: Value,
+                }
+                let notification: Notification = serde_json::from_value(self.data)?;
+                let id = match notification.id {
+                    Value::String(id) => id,
+                    id => id.to_string(),
+                };
+                TokenRequest::Notification { id }
+            }
+            TokenProvider::Custom(_) => TokenRequest::Custom(self.data),
+            TokenProvider::Totp | TokenProvider::Email => TokenRequest::Requested,
+        })
+    }
+}
+
+/// A second factor provider, identified by the id of the Nextcloud two-factor app
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TokenProvider {
+    /// Codes of an authenticator app
+    Totp,
+    /// Codes sent by email by [request_token](AuthenticatedApi::request_token)
+    Email,
+    /// Approval of a notification on another device logged in Nextcloud
+    Notification,
+    /// A provider unknown to this crate (refused with the `strict` feature when received from
+    /// the server)
+    Custom(String),
+}
+
+impl TokenProvider {
+    /// The id of the provider in the API
+    pub fn id(&self) -> &str {
+        match self {
+            TokenProvider::Totp => "totp",
+            TokenProvider::Email => "email",
+            TokenProvider::Notification => "twofactor_nextcloud_notification",
+            TokenProvider::Custom(id) => id,
+        }
+    }
+}
+
+impl std::fmt::Display for TokenProvider {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{}", self.id())
     }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct Response {
-    pub success: bool,
-    pub data: Value,
+impl Serialize for TokenProvider {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for TokenProvider {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let id = String::deserialize(deserializer)?;
+        Ok(match id.as_str() {
+            "totp" => TokenProvider::Totp,
+            "email" => TokenProvider::Email,
+            "twofactor_nextcloud_notification" => TokenProvider::Notification,
+            _ => crate::utils::unknown_value("token", &id, TokenProvider::Custom(id.clone()))?,
+        })
+    }
+}
+
+/// The answer of [request](TokenApi::request)
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenRequest {
+    /// The token was sent to the user, or the provider needs no request
+    Requested,
+    /// A notification was sent, identified by this id
+    Notification { id: String },
+    /// The data answered for a [Custom](TokenProvider::Custom) provider
+    Custom(Value),
+}
+
+/// A second factor configured on the account, see [providers](TokenApi::providers)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProviderInfo {
+    pub id: TokenProvider,
+    pub label: String,
+    pub description: String,
+    /// The token is sent to the user by [request_token](AuthenticatedApi::request_token)
+    #[serde(rename = "request")]
+    pub needs_request: bool,
+}
+
+/// The token providers listed in the answer of `session/request`, the server answers an empty
+/// array when nothing is required
+pub(crate) fn providers(requirements: &Value) -> Result<Vec<ProviderInfo>, Error> {
+    match requirements.get("token") {
+        Some(token) => Ok(serde_json::from_value(token.clone())?),
+        None => Ok(Vec::new()),
+    }
 }
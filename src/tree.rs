@@ -0,0 +1,410 @@
+//! The hierarchy of the folders rebuilt from the flat list of the API, see
+//! [FolderTree](crate::tree::FolderTree)
+
+use crate::{
+    folder::{Folder, FolderApi, BASE_FOLDER},
+    password::Password,
+    utils::for_each_yielding,
+    Error,
+};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// The folders of a vault arranged by parent, and optionally their passwords (see
+/// [with_passwords](FolderTree::with_passwords)). The base folder is not part of the tree, its
+/// children are the [root](FolderTree::root) folders.
+#[derive(Debug, Default)]
+pub struct FolderTree {
+    folders: HashMap<uuid::Uuid, Folder>,
+    /// The parent of each folder in the tree, which may differ from its `parent` property
+    parents: HashMap<uuid::Uuid, uuid::Uuid>,
+    /// The children of each folder, sorted by label
+    children: HashMap<uuid::Uuid, Vec<uuid::Uuid>>,
+    /// The passwords of each folder, sorted by label
+    passwords: HashMap<uuid::Uuid, Vec<Password>>,
+}
+
+/// Whether an object is shown in this folder instead of the base folder, like the server does
+/// for the objects of trashed or hidden folders
+fn placed_in(parent: Option<&Folder>, hidden: bool) -> bool {
+    match parent {
+        Some(parent) => !parent.versioned.trashed && (hidden || !parent.versioned.hidden),
+        None => false,
+    }
+}
+
+/// The parents met while looking for cycles, see [FolderTree::build]
+#[derive(Default)]
+struct Cycles {
+    /// The folders whose ancestors are known to reach the base folder or a cycle
+    done: HashSet<uuid::Uuid>,
+    /// The folders which are part of a cycle
+    found: BTreeSet<uuid::Uuid>,
+}
+
+impl Cycles {
+    /// Walk up the parents of `start`, until the base folder or an already walked folder
+    fn walk(&mut self, start: uuid::Uuid, parents: &HashMap<uuid::Uuid, uuid::Uuid>) {
+        let mut walked = Vec::new();
+        let mut current = start;
+        while current != BASE_FOLDER && !self.done.contains(&current) {
+            if let Some(position) = walked.iter().position(|&id| id == current) {
+                self.found.extend(walked[position..].iter().copied());
+                break;
+            }
+            walked.push(current);
+            current = parents[&current];
+        }
+        self.done.extend(walked);
+    }
+
+    fn check(self) -> Result<(), Error> {
+        if self.found.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::FolderCycle {
+                folders: self.found.into_iter().collect(),
+            })
+        }
+    }
+}
+
+/// The folder in which the folder is placed, see [FolderTree::build]
+fn parent_in_tree(folders: &HashMap<uuid::Uuid, Folder>, folder: &Folder) -> uuid::Uuid {
+    let parent = folder.versioned.parent.id();
+    if placed_in(folders.get(&parent), folder.versioned.hidden) {
+        parent
+    } else {
+        BASE_FOLDER
+    }
+}
+
+fn sort_folders(siblings: &mut [uuid::Uuid], folders: &HashMap<uuid::Uuid, Folder>) {
+    siblings.sort_by(|a, b| folders[a].versioned.label.cmp(&folders[b].versioned.label));
+}
+
+fn sort_passwords(passwords: &mut [Password]) {
+    passwords.sort_by(|a, b| a.versioned.label.cmp(&b.versioned.label));
+}
+
+impl FolderTree {
+    /// Arrange the folders, like the ones of [list](FolderApi::list).
+    ///
+    /// Notes
+    ///  - The folders whose parent is not in the list, or is trashed, are placed in the base
+    ///    folder. So are the folders that are not hidden in a hidden folder.
+    ///  - If parents form a cycle, this fails with [FolderCycle](Error::FolderCycle)
+    ///  - This is CPU heavy on large vaults, see [build_async](Self::build_async)
+    pub fn build(folders: Vec<Folder>) -> Result<Self, Error> {
+        let folders: HashMap<_, _> = folders
+            .into_iter()
+            .filter(|folder| folder.id != BASE_FOLDER)
+            .map(|folder| (folder.id, folder))
+            .collect();
+        let parents: HashMap<_, _> = folders
+            .values()
+            .map(|folder| (folder.id, parent_in_tree(&folders, folder)))
+            .collect();
+
+        let mut cycles = Cycles::default();
+        for &start in parents.keys() {
+            cycles.walk(start, &parents);
+        }
+        cycles.check()?;
+
+        let mut children: HashMap<_, Vec<_>> = HashMap::new();
+        for (&id, &parent) in &parents {
+            children.entry(parent).or_default().push(id);
+        }
+        for siblings in children.values_mut() {
+            sort_folders(siblings, &folders);
+        }
+        Ok(FolderTree {
+            folders,
+            parents,
+            children,
+            passwords: HashMap::new(),
+        })
+    }
+
+    /// Same as [build](Self::build), but yields to the executor regularly so that a large vault
+    /// does not block the other tasks
+    pub async fn build_async(folders: Vec<Folder>) -> Result<Self, Error> {
+        let mut indexed = HashMap::with_capacity(folders.len());
+        for_each_yielding(folders, |folder| {
+            if folder.id != BASE_FOLDER {
+                indexed.insert(folder.id, folder);
+            }
+        })
+        .await;
+        let folders = indexed;
+        let mut parents = HashMap::with_capacity(folders.len());
+        for_each_yielding(folders.values(), |folder| {
+            parents.insert(folder.id, parent_in_tree(&folders, folder));
+        })
+        .await;
+
+        let mut cycles = Cycles::default();
+        for_each_yielding(parents.keys(), |&start| cycles.walk(start, &parents)).await;
+        cycles.check()?;
+
+        let mut children: HashMap<_, Vec<_>> = HashMap::new();
+        for_each_yielding(&parents, |(&id, &parent)| {
+            children.entry(parent).or_default().push(id)
+        })
+        .await;
+        for_each_yielding(children.values_mut(), |siblings| {
+            sort_folders(siblings, &folders)
+        })
+        .await;
+        Ok(FolderTree {
+            folders,
+            parents,
+            children,
+            passwords: HashMap::new(),
+        })
+    }
+
+    fn place(&mut self, password: Password) {
+        let folder = password.folder.id();
+        let folder = if placed_in(self.folders.get(&folder), password.versioned.hidden) {
+            folder
+        } else {
+            BASE_FOLDER
+        };
+        self.passwords.entry(folder).or_default().push(password);
+    }
+
+    /// Place the passwords, like the ones of [list](crate::password::PasswordApi::list), in
+    /// their folders. They are placed in the base folder like the folders of
+    /// [build](Self::build).
+    ///
+    /// This is CPU heavy on large vaults, see [with_passwords_async](Self::with_passwords_async)
+    pub fn with_passwords(mut self, passwords: Vec<Password>) -> Self {
+        for password in passwords {
+            self.place(password);
+        }
+        for passwords in self.passwords.values_mut() {
+            sort_passwords(passwords);
+        }
+        self
+    }
+
+    /// Same as [with_passwords](Self::with_passwords), but yields to the executor regularly so
+    /// that a large vault does not block the other tasks
+    pub async fn with_passwords_async(mut self, passwords: Vec<Password>) -> Self {
+        for_each_yielding(passwords, |password| self.place(password)).await;
+        for_each_yielding(self.passwords.values_mut(), |passwords| {
+            sort_passwords(passwords)
+        })
+        .await;
+        self
+    }
+
+    pub fn get(&self, id: uuid::Uuid) -> Option<&Folder> {
+        self.folders.get(&id)
+    }
+
+    /// The folders in the base folder, sorted by label
+    pub fn root(&self) -> Vec<&Folder> {
+        self.children_of(BASE_FOLDER)
+    }
+
+    /// The folders in this folder, sorted by label
+    pub fn children_of(&self, id: uuid::Uuid) -> Vec<&Folder> {
+        self.children
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .map(|id| &self.folders[id])
+            .collect()
+    }
+
+    /// The passwords in this folder, sorted by label. This is empty unless the passwords were
+    /// added with [with_passwords](Self::with_passwords).
+    pub fn passwords_in(&self, id: uuid::Uuid) -> &[Password] {
+        self.passwords.get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// The folders from the root to this folder included, empty if the folder is not in the
+    /// tree
+    pub fn path_of(&self, id: uuid::Uuid) -> Vec<&Folder> {
+        let mut path = Vec::new();
+        let mut current = id;
+        while let Some(folder) = self.folders.get(&current) {
+            path.push(folder);
+            current = self.parents[&current];
+        }
+        path.reverse();
+        path
+    }
+}
+
+impl<'a> FolderApi<'a> {
+    /// List the folders and arrange them, see [FolderTree::build_async]
+    pub async fn tree(&self) -> Result<FolderTree, Error> {
+        FolderTree::build_async(self.list(None).await?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn uuid(n: u8) -> uuid::Uuid {
+        uuid::Uuid::from_bytes([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, n])
+    }
+
+    fn uuid_n(n: u16) -> uuid::Uuid {
+        uuid::Uuid::from_u128(n.into())
+    }
+
+    fn folder(id: u8, parent: u8, label: &str) -> Folder {
+        folder_n(id.into(), parent.into(), label)
+    }
+
+    fn folder_n(id: u16, parent: u16, label: &str) -> Folder {
+        serde_json::from_value(json!({
+            "id": uuid_n(id),
+            "label": label,
+            "parent": uuid_n(parent),
+            "created": 0,
+            "updated": 0,
+            "edited": 0,
+            "revision": uuid_n(0xffff),
+            "cseType": "none",
+            "cseKey": "",
+            "sseType": "SSEv1r1",
+            "client": "CLIENT::UNKNOWN",
+            "hidden": false,
+            "trashed": false,
+            "favorite": false,
+        }))
+        .unwrap()
+    }
+
+    fn labels(folders: Vec<&Folder>) -> Vec<&str> {
+        folders
+            .into_iter()
+            .map(|folder| folder.versioned.label.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn build() {
+        let mut trashed = folder(6, 0, "Trashed");
+        trashed.versioned.trashed = true;
+        let mut hidden = folder(8, 0, "Hidden");
+        hidden.versioned.hidden = true;
+        let mut hidden_child = folder(10, 8, "Hidden child");
+        hidden_child.versioned.hidden = true;
+        let tree = FolderTree::build(vec![
+            folder(3, 1, "Servers"),
+            folder(1, 0, "Team"),
+            folder(2, 1, "Accounts"),
+            folder(4, 2, "Bank"),
+            folder(5, 42, "Orphan"),
+            trashed,
+            folder(7, 6, "Suspended"),
+            hidden,
+            folder(9, 8, "Visible"),
+            hidden_child,
+        ])
+        .unwrap();
+
+        assert_eq!(
+            labels(tree.root()),
+            [
+                "Hidden",
+                "Orphan",
+                "Suspended",
+                "Team",
+                "Trashed",
+                "Visible"
+            ]
+        );
+        assert_eq!(labels(tree.children_of(uuid(1))), ["Accounts", "Servers"]);
+        assert_eq!(labels(tree.children_of(uuid(8))), ["Hidden child"]);
+        assert_eq!(labels(tree.children_of(uuid(4))), Vec::<&str>::new());
+        assert_eq!(labels(tree.path_of(uuid(4))), ["Team", "Accounts", "Bank"]);
+        assert_eq!(labels(tree.path_of(uuid(5))), ["Orphan"]);
+        assert!(tree.path_of(uuid(43)).is_empty());
+        assert!(tree.path_of(BASE_FOLDER).is_empty());
+    }
+
+    #[tokio::test]
+    async fn build_async() {
+        // More folders than handled between two yields
+        let folders = || (1..=600).map(|n| folder_n(n, n / 10, &format!("F{}", 600 - n)));
+        let tree = FolderTree::build(folders().collect()).unwrap();
+        let built = FolderTree::build_async(folders().collect()).await.unwrap();
+        for n in 0..=600 {
+            let id = uuid_n(n);
+            assert_eq!(labels(built.children_of(id)), labels(tree.children_of(id)));
+            assert_eq!(labels(built.path_of(id)), labels(tree.path_of(id)));
+        }
+
+        let cycle = vec![folder(1, 2, "A"), folder(2, 1, "B")];
+        let built = FolderTree::build_async(cycle).await;
+        assert!(matches!(built, Err(Error::FolderCycle { .. })));
+    }
+
+    #[test]
+    fn cycles() {
+        let folders = vec![
+            folder(1, 2, "A"),
+            folder(2, 3, "B"),
+            folder(3, 1, "C"),
+            folder(4, 1, "D"),
+            folder(5, 5, "Self"),
+            folder(6, 0, "Fine"),
+        ];
+        match FolderTree::build(folders) {
+            Err(Error::FolderCycle { folders }) => {
+                assert_eq!(folders, [uuid(1), uuid(2), uuid(3), uuid(5)])
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn passwords() {
+        let password = |label: &str, folder: u8, hidden: bool| {
+            let mut json = crate::password::tests::password_json();
+            json["label"] = label.into();
+            json["folder"] = json!(uuid(folder));
+            json["hidden"] = hidden.into();
+            serde_json::from_value::<Password>(json).unwrap()
+        };
+        let tree = || {
+            let mut hidden = folder(2, 0, "Hidden");
+            hidden.versioned.hidden = true;
+            FolderTree::build(vec![folder(1, 0, "Team"), hidden]).unwrap()
+        };
+        let passwords = || {
+            vec![
+                password("Mail", 1, false),
+                password("Bank", 1, false),
+                password("Orphan", 9, false),
+                password("Visible", 2, false),
+                password("Secret", 2, true),
+                password("Top", 0, false),
+            ]
+        };
+        let labels = |tree: &FolderTree, id| {
+            let passwords = tree.passwords_in(id).iter();
+            passwords
+                .map(|password| password.versioned.label.to_string())
+                .collect::<Vec<_>>()
+        };
+        let placed = tree().with_passwords_async(passwords()).await;
+        let tree = tree().with_passwords(passwords());
+        assert_eq!(labels(&tree, uuid(1)), ["Bank", "Mail"]);
+        assert_eq!(labels(&tree, uuid(2)), ["Secret"]);
+        assert_eq!(labels(&tree, BASE_FOLDER), ["Orphan", "Top", "Visible"]);
+        assert!(labels(&tree, uuid(3)).is_empty());
+        for id in [uuid(1), uuid(2), uuid(3), BASE_FOLDER].iter().copied() {
+            assert_eq!(labels(&placed, id), labels(&tree, id));
+        }
+    }
+}
@@ -9,7 +9,7 @@ macro_rules! create_details {
         $(,)?
     }) => {
         /// Amount of optional details requested
-        #[derive(Default, Debug)]
+        #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
         pub struct $struct {
             $(
                 pub $name: bool,
@@ -17,7 +17,7 @@ macro_rules! create_details {
         }
 
         impl $struct {
-            pub(crate) fn to_string(&self) -> String {
+            pub(crate) fn to_string(self) -> String {
                 let mut s = "model".into();
                 $(
                     if self.$name {
@@ -31,6 +31,15 @@ macro_rules! create_details {
                 Default::default()
             }
 
+            /// All the optional details
+            pub fn all() -> Self {
+                Self {
+                    $(
+                        $name: true,
+                    )*
+                }
+            }
+
             $(
                 pub fn $name(self) -> Self {
                     Self {
@@ -40,21 +49,447 @@ macro_rules! create_details {
                 }
             )*
 
+            /// Whether an object fetched with these details has all the details of `requested`
+            pub fn covers(&self, requested: &Self) -> bool {
+                true $(&& (self.$name || !requested.$name))*
+            }
+        }
+
+        /// Parse the `model+details` syntax sent to the server, `model` can be omitted
+        impl std::str::FromStr for $struct {
+            type Err = $crate::Error;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let mut details = Self::new();
+                for segment in s.split('+').map(str::trim) {
+                    match segment {
+                        "model" => (),
+                        $(
+                            stringify!($name) => details.$name = true,
+                        )*
+                        _ => {
+                            return Err($crate::Error::InvalidDetails {
+                                segment: segment.into(),
+                                // Without the leading `+`
+                                allowed: &concat!($("+", stringify!($name)),*)[1..],
+                            })
+                        }
+                    }
+                }
+                Ok(details)
+            }
         }
     };
 }
 
+/// An object with the details it was fetched with, returned by the `list_detailed` and
+/// `get_detailed` methods. It dereferences to the object.
+#[derive(Debug)]
+pub struct Detailed<T, D> {
+    pub details: D,
+    pub value: T,
+}
+
+impl<T, D> Detailed<T, D> {
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T, D> std::ops::Deref for Detailed<T, D> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, D> std::ops::DerefMut for Detailed<T, D> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+/// An element of a list that could not be read, returned by the `list_lossy` methods
+#[derive(Debug, thiserror::Error)]
+#[error("element {index} of the list could not be read: {source}")]
+pub struct ItemError {
+    /// The position of the element in the list
+    pub index: usize,
+    /// The id of the element, if it has a valid one
+    pub id: Option<uuid::Uuid>,
+    pub source: serde_json::Error,
+}
+
+/// Result of the `empty_trash` methods
+#[derive(Debug, Default)]
+pub struct EmptyTrashReport {
+    /// Objects that were deleted
+    pub deleted: Vec<uuid::Uuid>,
+    pub failed: Vec<(uuid::Uuid, crate::Error)>,
+}
+
+impl EmptyTrashReport {
+    pub(crate) fn merge(&mut self, other: EmptyTrashReport) {
+        self.deleted.extend(other.deleted);
+        self.failed.extend(other.failed);
+    }
+}
+
+/// The type of the `created`, `updated` and `edited` properties of the objects: the unix time
+/// in seconds, or a [Timestamp] with the `chrono` feature
+#[cfg(not(feature = "chrono"))]
+pub type UnixTime = i64;
+/// The type of the `created`, `updated` and `edited` properties of the objects: the unix time
+/// in seconds, or a [Timestamp] with the `chrono` feature
+#[cfg(feature = "chrono")]
+pub type UnixTime = Timestamp;
+
+/// The [UnixTime] of these seconds, to write the tests once for both types
+#[cfg(all(test, not(feature = "chrono")))]
+pub(crate) fn unix_time(seconds: i64) -> UnixTime {
+    seconds
+}
+/// The [UnixTime] of these seconds, to write the tests once for both types
+#[cfg(all(test, feature = "chrono"))]
+pub(crate) fn unix_time(seconds: i64) -> UnixTime {
+    Timestamp(seconds)
+}
+
+/// A unix timestamp in seconds, to convert the `created`, `updated` and `edited` properties of
+/// the objects from and to [SystemTime](std::time::SystemTime), and `chrono::DateTime` with the
+/// `chrono` feature (see [UnixTime]). It is sent as the number of seconds.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(transparent)]
+pub struct Timestamp(pub i64);
+
+impl Timestamp {
+    pub fn now() -> Self {
+        std::time::SystemTime::now().into()
+    }
+
+    pub fn seconds(self) -> i64 {
+        self.0
+    }
+
+    pub fn as_system_time(self) -> std::time::SystemTime {
+        let seconds = std::time::Duration::from_secs(self.0.unsigned_abs());
+        if self.0 >= 0 {
+            std::time::UNIX_EPOCH + seconds
+        } else {
+            std::time::UNIX_EPOCH - seconds
+        }
+    }
+
+    /// The time rounded down to the second
+    pub fn from_system_time(time: std::time::SystemTime) -> Self {
+        match time.duration_since(std::time::UNIX_EPOCH) {
+            Ok(since) => Timestamp(since.as_secs() as i64),
+            Err(before) => {
+                let before = before.duration();
+                let rounding = if before.subsec_nanos() > 0 { 1 } else { 0 };
+                Timestamp(-(before.as_secs() as i64) - rounding)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Timestamp {
+    /// The time in UTC, `None` if it is out of the range of `chrono`
+    pub fn as_datetime(self) -> Option<chrono::DateTime<chrono::Utc>> {
+        use chrono::TimeZone;
+        chrono::Utc.timestamp_opt(self.0, 0).single()
+    }
+
+    /// The time rounded down to the second
+    pub fn from_datetime(time: chrono::DateTime<chrono::Utc>) -> Self {
+        Timestamp(time.timestamp())
+    }
+}
+
+impl From<i64> for Timestamp {
+    fn from(seconds: i64) -> Self {
+        Timestamp(seconds)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
+    fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+        Timestamp::from_datetime(time)
+    }
+}
+
+impl From<std::time::SystemTime> for Timestamp {
+    fn from(time: std::time::SystemTime) -> Self {
+        Timestamp::from_system_time(time)
+    }
+}
+
+impl From<Timestamp> for i64 {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl From<Timestamp> for std::time::SystemTime {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.as_system_time()
+    }
+}
+
+impl PartialEq<i64> for Timestamp {
+    fn eq(&self, seconds: &i64) -> bool {
+        self.0 == *seconds
+    }
+}
+
+impl PartialOrd<i64> for Timestamp {
+    fn partial_cmp(&self, seconds: &i64) -> Option<std::cmp::Ordering> {
+        self.0.partial_cmp(seconds)
+    }
+}
+
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(fmt)
+    }
+}
+
+/// A value exceeding a limit of the server, see [Validate]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{field} exceeds the limit of the server: {actual}, at most {limit}")]
+pub struct ValidationError {
+    /// Name of the property in the API
+    pub field: &'static str,
+    /// Maximum number of characters, or of elements for a list
+    pub limit: usize,
+    pub actual: usize,
+}
+
+/// Check the limits of the server before sending an object, instead of failing with a generic
+/// error after the request. The `create` and `update` methods check the objects, the
+/// `_unchecked` methods send them as is.
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationError>;
+
+    /// The `cseType` of the values, the limits are only checked for values that are not
+    /// encrypted on the client. An object built from a fetched encrypted object holds its hex
+    /// ciphertexts, which are longer than the plain values.
+    fn cse_type(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Check that `value` has at most `limit` characters
+pub(crate) fn check_length(
+    field: &'static str,
+    value: &str,
+    limit: usize,
+) -> Result<(), ValidationError> {
+    let actual = value.chars().count();
+    if actual > limit {
+        return Err(ValidationError {
+            field,
+            limit,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Result of the `*_many` methods, one result per input in the same order
+#[derive(Debug)]
+pub struct BatchResult<T> {
+    pub results: Vec<Result<T, crate::Error>>,
+}
+
+impl<T> BatchResult<T> {
+    /// True if all the operations succeeded
+    pub fn is_ok(&self) -> bool {
+        self.results.iter().all(Result::is_ok)
+    }
+
+    pub fn succeeded(&self) -> impl Iterator<Item = &T> {
+        self.results
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+    }
+
+    /// The failed operations with the index of their input
+    pub fn failed(&self) -> impl Iterator<Item = (usize, &crate::Error)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(index, result)| Some((index, result.as_ref().err()?)))
+    }
+}
+
+/// Run `operation` on all the items, with at most `concurrency` operations at the same time.
+/// The requests still go through the throttling and retries of the session.
+pub(crate) async fn batch<I, T, F, Fut>(
+    items: impl IntoIterator<Item = I>,
+    concurrency: usize,
+    operation: F,
+) -> BatchResult<T>
+where
+    F: Fn(I) -> Fut,
+    Fut: std::future::Future<Output = Result<T, crate::Error>>,
+{
+    use futures::stream::StreamExt;
+
+    let mut results: Vec<_> = futures::stream::iter(items.into_iter().enumerate())
+        .map(|(index, item)| {
+            let operation = operation(item);
+            async move { (index, operation.await) }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    results.sort_by_key(|(index, _)| *index);
+    BatchResult {
+        results: results.into_iter().map(|(_, result)| result).collect(),
+    }
+}
+
+/// An object deleted by the `purge` methods
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Purged {
+    pub id: uuid::Uuid,
+}
+
+/// The property used to sort objects, see [sort]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    /// The labels compared by their unicode code points, independently of the locale
+    Label,
+    /// The labels compared by their lowercase code points
+    LabelIgnoreCase,
+    Updated,
+    Created,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// Objects which can be sorted with [sort]
+pub trait Sortable {
+    fn label(&self) -> &str;
+    fn updated(&self) -> UnixTime;
+    fn created(&self) -> UnixTime;
+}
+
+/// Sort `items`, the objects which compare equal keep their relative order
+pub fn sort<T: Sortable>(items: &mut [T], by: SortBy, order: Order) {
+    items.sort_by(|a, b| {
+        let ordering = match by {
+            SortBy::Label => a.label().cmp(b.label()),
+            SortBy::LabelIgnoreCase => {
+                let lowercase = |label: &str| label.chars().flat_map(char::to_lowercase).collect();
+                let (a, b): (String, String) = (lowercase(a.label()), lowercase(b.label()));
+                a.cmp(&b)
+            }
+            SortBy::Updated => a.updated().cmp(&b.updated()),
+            SortBy::Created => a.created().cmp(&b.created()),
+        };
+        match order {
+            Order::Asc => ordering,
+            Order::Desc => ordering.reverse(),
+        }
+    });
+}
+
+/// A page of a list, returned by [paginate]
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The index of the page, starting at 0
+    pub page: usize,
+    pub per_page: usize,
+    /// The number of items in the whole list
+    pub total: usize,
+}
+
+impl<T> Page<T> {
+    /// The number of pages of the whole list
+    pub fn page_count(&self) -> usize {
+        match self.per_page {
+            0 => 0,
+            per_page => self.total.div_ceil(per_page),
+        }
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.page + 1 < self.page_count()
+    }
+}
+
+/// The `page`th page of `per_page` items of the list, empty past the end of the list
+pub fn paginate<T>(items: Vec<T>, page: usize, per_page: usize) -> Page<T> {
+    let total = items.len();
+    let items = items
+        .into_iter()
+        .skip(page.saturating_mul(per_page))
+        .take(per_page)
+        .collect();
+    Page {
+        items,
+        page,
+        per_page,
+        total,
+    }
+}
+
 // Tags: versioned, create(optional | required), update(optional | required), search
 //
 
+/// A criterion on a field for the `find` methods, built with [new](SearchQuery::new).
+///
+/// The value is serialized the same way as the field of the object, so identifiers are sent as
+/// UUID strings (use [ParentInfo::new](crate::folder::ParentInfo::new) for folders) and
+/// [SecurityStatus](crate::password::SecurityStatus) as an integer
+///
+/// ```
+/// use nextcloud_passwords_client::{password::PasswordSearch, SearchQuery, Timestamp};
+///
+/// # fn main() -> Result<(), nextcloud_passwords_client::Error> {
+/// // The favorite passwords edited after the 13th of september 2020
+/// let search = PasswordSearch::new()
+///     .and_favorite(SearchQuery::exact(true))?
+///     .and_edited(SearchQuery::greater_than(Timestamp(1_600_000_000).into()))?;
+/// assert_eq!(
+///     search.to_json(),
+///     serde_json::json!({"favorite": true, "edited": ["gt", 1_600_000_000]})
+/// );
+/// # Ok(())
+/// # }
+/// ```
 #[derive(Debug)]
 pub struct SearchQuery<T: serde::Serialize> {
     value: T,
     query: QueryKind,
 }
 
+/// How the value of a [SearchQuery] is compared to the field
 #[derive(Debug)]
 pub enum QueryKind {
+    /// The field must be exactly the value
     Exact,
     Equals,
     NotEqual,
@@ -64,6 +499,36 @@ pub enum QueryKind {
     GreaterOrEqual,
 }
 
+impl QueryKind {
+    /// The operator sent to the server, `None` for [Exact](QueryKind::Exact)
+    pub fn operator(&self) -> Option<&'static str> {
+        match self {
+            QueryKind::Exact => None,
+            QueryKind::Equals => Some("eq"),
+            QueryKind::NotEqual => Some("ne"),
+            QueryKind::LessThan => Some("lt"),
+            QueryKind::GreaterThan => Some("gt"),
+            QueryKind::LessOrEqual => Some("le"),
+            QueryKind::GreaterOrEqual => Some("ge"),
+        }
+    }
+}
+
+impl std::fmt::Display for QueryKind {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{}", self.operator().unwrap_or("exact"))
+    }
+}
+
+impl serde::Serialize for QueryKind {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
 #[derive(serde::Serialize)]
 #[serde(untagged)]
 pub(crate) enum Criteria {
@@ -71,27 +536,152 @@ pub(crate) enum Criteria {
     Search(&'static str, serde_json::Value),
 }
 
+/// Send a single criterion as is, and several ones as an array, all of them must match
+pub(crate) fn serialize_criteria<S>(criteria: &[Criteria], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match criteria {
+        [criterion] => serde::Serialize::serialize(criterion, serializer),
+        criteria => serde::Serialize::serialize(criteria, serializer),
+    }
+}
+
 impl<T: serde::Serialize> SearchQuery<T> {
+    pub fn new(value: T, query: QueryKind) -> Self {
+        SearchQuery { value, query }
+    }
+
+    /// The field is exactly `value`
+    pub fn exact(value: T) -> Self {
+        Self::new(value, QueryKind::Exact)
+    }
+
+    pub fn equals(value: T) -> Self {
+        Self::new(value, QueryKind::Equals)
+    }
+
+    pub fn not(value: T) -> Self {
+        Self::new(value, QueryKind::NotEqual)
+    }
+
+    pub fn less_than(value: T) -> Self {
+        Self::new(value, QueryKind::LessThan)
+    }
+
+    pub fn greater_than(value: T) -> Self {
+        Self::new(value, QueryKind::GreaterThan)
+    }
+
+    pub fn at_most(value: T) -> Self {
+        Self::new(value, QueryKind::LessOrEqual)
+    }
+
+    pub fn at_least(value: T) -> Self {
+        Self::new(value, QueryKind::GreaterOrEqual)
+    }
+
     pub(crate) fn to_criteria(&self) -> Result<Criteria, serde_json::Error> {
         let value = serde_json::to_value(&self.value)?;
-        Ok(match self.query {
-            QueryKind::Exact => Criteria::Value(value),
-            QueryKind::Equals => Criteria::Search("eq", value),
-            QueryKind::NotEqual => Criteria::Search("ne", value),
-            QueryKind::LessThan => Criteria::Search("lt", value),
-            QueryKind::GreaterThan => Criteria::Search("gt", value),
-            QueryKind::LessOrEqual => Criteria::Search("le", value),
-            QueryKind::GreaterOrEqual => Criteria::Search("ge", value),
+        Ok(match self.query.operator() {
+            None => Criteria::Value(value),
+            Some(operator) => Criteria::Search(operator, value),
         })
     }
 }
+
+/// Handle a value of `field` unknown to this crate: use the `fallback` catch-all variant, or fail
+/// if the `strict` feature is enabled
+pub(crate) fn unknown_value<T, E: serde::de::Error>(
+    field: &'static str,
+    value: impl std::fmt::Display,
+    fallback: T,
+) -> Result<T, E> {
+    if cfg!(feature = "strict") {
+        Err(E::custom(format!(
+            "unexpected value `{}` for `{}`",
+            value, field
+        )))
+    } else {
+        Ok(fallback)
+    }
+}
+
+/// Number of elements handled by CPU heavy helpers between two yields to the executor
+pub(crate) const YIELD_EVERY: usize = 256;
+
+/// Call `f` on each element, yielding to the executor every [YIELD_EVERY] elements
+pub(crate) async fn for_each_yielding<T>(
+    elements: impl IntoIterator<Item = T>,
+    mut f: impl FnMut(T),
+) {
+    let mut elements = elements.into_iter().peekable();
+    while elements.peek().is_some() {
+        elements.by_ref().take(YIELD_EVERY).for_each(&mut f);
+        crate::runtime::yield_now().await;
+    }
+}
+
+/// Objects which have revisions, used to detect concurrent modifications
+pub(crate) trait Revisioned {
+    fn current_revision(&self) -> uuid::Uuid;
+}
+
+/// The id of an object, the tags keep the `String` given by the API
+pub(crate) trait ObjectId {
+    fn to_uuid(&self) -> Result<uuid::Uuid, crate::Error>;
+}
+
+impl ObjectId for uuid::Uuid {
+    fn to_uuid(&self) -> Result<uuid::Uuid, crate::Error> {
+        Ok(*self)
+    }
+}
+
+impl ObjectId for String {
+    fn to_uuid(&self) -> Result<uuid::Uuid, crate::Error> {
+        Ok(serde_json::from_value(self.as_str().into())?)
+    }
+}
+
+/// Body of the find requests
+#[derive(serde::Serialize)]
+pub(crate) struct FindRequest<C> {
+    pub(crate) criteria: C,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) details: Option<String>,
+}
+
+/// The search criteria supported only by recent versions of the passwords app, by resource
+const RECENT_CRITERIA: &[(&str, &str, crate::settings::Version)] = &[(
+    "folder",
+    "parent",
+    crate::settings::Version::new(2019, 5, 0),
+)];
+
+/// Fail with [UnsupportedServer](crate::Error::UnsupportedServer) instead of letting the server
+/// refuse a criterion it does not know
+pub(crate) async fn check_criteria(
+    api: &crate::AuthenticatedApi,
+    resource: &str,
+    criteria: &impl serde::Serialize,
+) -> Result<(), crate::Error> {
+    let criteria = serde_json::to_value(criteria).expect("search criteria are always serializable");
+    for (recent_resource, field, version) in RECENT_CRITERIA {
+        if *recent_resource == resource && criteria.get(field).is_some() {
+            let feature = format!("searching {}s by {}", resource, field);
+            api.requires(feature, *version).await?;
+        }
+    }
+    Ok(())
+}
 /*
 
     TEMPLATE UTILISATION
 
 create_calls! {
     PasswordApi where 
-        Endpoint = "1.0/password",
+        Resource = "password",
         Details: Details,
         Type: Password,
         Create: CreatePassword,
@@ -124,7 +714,7 @@ create_calls! {
 macro_rules! create_calls {
     (
         $base:ident where
-            Endpoint = $endpoint:expr,
+            Resource = $resource:expr,
             Details: $details:ty,
             Type: $ty:ty,
             Create: $create:ty,
@@ -175,6 +765,24 @@ macro_rules! create_calls {
             $(#[$meta_restore:meta])*
             pub async fn restore(&self, id: uuid::Uuid, revision: Option<uuid::Uuid>) -> Result<Identifier, Error>;
             )?
+
+            $(
+            Patches: $patch:ty;
+            $(#[$meta_patches:meta])*
+            pub async fn apply_patch(&self, patch: Patch) -> Result<Option<Identifier>, Error>;
+            )?
+
+            $(
+            Summaries: $summary:ty;
+            $(#[$meta_summaries:meta])*
+            pub async fn list_summaries(&self) -> Result<Vec<Summary>, Error>;
+            )?
+
+            $(
+            Trash;
+            $(#[$meta_trash:meta])*
+            pub async fn trashed(&self, details: Option<Details>) -> Result<Vec<Type>, Error>;
+            )?
         }
     ) => {
         ::doc_comment::doc_comment! { concat!("Actions on the ", stringify!($base), " API"),
@@ -192,19 +800,64 @@ macro_rules! create_calls {
                     details: Option<String>,
                 }
                 self.api
-                    .passwords_post(
-                        concat!($endpoint, "/list"),
+                    .passwords_list(
+                        self.api.endpoint($resource, "list"),
                         DetailsStr {
                             details: details.map(|d| d.to_string()),
                         },
                     )
                     .await
             }
+
+            /// Like `list`, with the objects sorted by `by`, see [sort](crate::sort)
+            pub async fn list_sorted(
+                &self,
+                details: Option<$details>,
+                by: crate::SortBy,
+                order: crate::Order,
+            ) -> Result<Vec<$ty>, $err> {
+                let mut list = self.list(details).await?;
+                crate::sort(&mut list, by, order);
+                Ok(list)
+            }
+
+            /// Like `list`, skipping the objects that can't be read instead of failing
+            pub async fn list_lossy(
+                &self,
+                details: Option<$details>,
+            ) -> Result<(Vec<$ty>, Vec<crate::ItemError>), $err> {
+                #[derive(serde::Serialize, serde::Deserialize)]
+                struct DetailsStr {
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    details: Option<String>,
+                }
+                self.api
+                    .passwords_list_lossy(
+                        self.api.endpoint($resource, "list"),
+                        DetailsStr {
+                            details: details.map(|d| d.to_string()),
+                        },
+                    )
+                    .await
+            }
+
+            /// Like `list`, keeping the details the objects were fetched with
+            pub async fn list_detailed(
+                &self,
+                details: Option<$details>,
+            ) -> Result<Vec<crate::Detailed<$ty, $details>>, $err> {
+                let fetched = details.unwrap_or_default();
+                let list = self.list(details).await?;
+                Ok(list
+                    .into_iter()
+                    .map(|value| crate::Detailed { details: fetched, value })
+                    .collect())
+            }
             )?
 
             $(
             $(#[$meta_get])*
-            pub async fn get(&self, details: Option<$details>, id: uuid::Uuid) -> Result<$ty, $err> {
+            pub async fn get(&self, details: Option<$details>, id: impl Into<uuid::Uuid>) -> Result<$ty, $err> {
                 #[derive(Serialize, Deserialize)]
                 struct Show {
                     id: uuid::Uuid,
@@ -212,13 +865,25 @@ macro_rules! create_calls {
                     details: Option<String>,
                 }
                 let request = Show {
-                    id,
+                    id: id.into(),
                     details: details.map(|d| d.to_string()),
                 };
+                // Reads are idempotent, they are retried on connection errors
                 self.api
-                    .passwords_post(concat!($endpoint, "/show"), request)
+                    .passwords_post_read(self.api.endpoint($resource, "show"), request)
                     .await
             }
+
+            /// Like `get`, keeping the details the object was fetched with
+            pub async fn get_detailed(
+                &self,
+                details: Option<$details>,
+                id: impl Into<uuid::Uuid>,
+            ) -> Result<crate::Detailed<$ty, $details>, $err> {
+                let fetched = details.unwrap_or_default();
+                let value = self.get(details, id).await?;
+                Ok(crate::Detailed { details: fetched, value })
+            }
             )?
 
             $(
@@ -228,60 +893,161 @@ macro_rules! create_calls {
                 criteria: $criteria,
                 details: Option<$details>,
             ) -> Result<Vec<$ty>, $err> {
-                #[derive(Serialize)]
-                struct Request {
-                    criteria: $criteria,
-                    #[serde(skip_serializing_if = "Option::is_none")]
-                    details: Option<String>,
-                }
-                let request = Request {
+                crate::utils::check_criteria(self.api, $resource, &criteria).await?;
+                let request = crate::utils::FindRequest {
                     criteria,
                     details: details.map(|d| d.to_string()),
                 };
                 self.api
-                    .passwords_post(concat!($endpoint, "/find"), request)
+                    .passwords_post_read(self.api.endpoint($resource, "find"), request)
                     .await
             }
+
+            /// The body that `find` would send with these arguments, without sending it
+            pub fn explain_find(
+                &self,
+                criteria: &$criteria,
+                details: Option<&$details>,
+            ) -> serde_json::Value {
+                serde_json::to_value(crate::utils::FindRequest {
+                    criteria,
+                    details: details.map(|d| d.to_string()),
+                })
+                .expect("search criteria are always serializable")
+            }
             )?
 
             $(
             $(#[$meta_create])*
-            pub async fn create(&self, value: $create) -> Result<$ident, $err> {
+            pub async fn create(
+                &self,
+                value: impl Into<crate::cse::Payload<$create>>,
+            ) -> Result<$ident, $err> {
+                let value = value.into().complete(self.api).await?;
+                value.validate()?;
+                self.create_unchecked(value).await
+            }
+
+            /// Like `create`, without checking the limits of the server
+            pub async fn create_unchecked(
+                &self,
+                value: impl Into<crate::cse::Payload<$create>>,
+            ) -> Result<$ident, $err> {
+                let value = value.into().complete(self.api).await?;
+                // Not idempotent, a retry could create a duplicate
                 self.api
-                    .passwords_post(concat!($endpoint, "/create"), value)
+                    .passwords_post(self.api.endpoint($resource, "create"), value)
                     .await
             }
+
+            /// Create all the objects, with at most `concurrency` requests at the same time, see
+            /// [BatchResult](crate::BatchResult)
+            pub async fn create_many(
+                &self,
+                values: impl IntoIterator<Item = impl Into<crate::cse::Payload<$create>>>,
+                concurrency: usize,
+            ) -> crate::BatchResult<$ident> {
+                crate::utils::batch(values, concurrency, |value| self.create(value)).await
+            }
             )?
 
             $(
             $(#[$meta_update])*
-            pub async fn update(&self, folder: $update) -> Result<$ident, $err> {
+            pub async fn update(
+                &self,
+                value: impl Into<crate::cse::Payload<$update>>,
+            ) -> Result<$ident, $err> {
+                let value = value.into().complete(self.api).await?;
+                value.validate()?;
+                self.update_unchecked(value).await
+            }
+
+            /// Like `update`, without checking the limits of the server
+            pub async fn update_unchecked(
+                &self,
+                value: impl Into<crate::cse::Payload<$update>>,
+            ) -> Result<$ident, $err> {
+                let value = value.into().complete(self.api).await?;
+                if let Some(expected) = value.object().expected_revision {
+                    let id = crate::utils::ObjectId::to_uuid(&value.object().id)?;
+                    let current = self.get(None, id).await?;
+                    if crate::utils::Revisioned::current_revision(&current) != expected {
+                        return Err(crate::Error::Conflict {
+                            current: current.into(),
+                        });
+                    }
+                }
                 self.api
-                    .passwords_patch(concat!($endpoint, "/update"), folder)
+                    .passwords_patch(self.api.endpoint($resource, "update"), value)
                     .await
             }
+
+            /// Mark the object as a favorite or not, keeping its other properties
+            pub async fn set_favorite(
+                &self,
+                id: impl Into<uuid::Uuid>,
+                favorite: bool,
+            ) -> Result<$ident, $err> {
+                let current = self.get(None, id).await?;
+                self.update(<$update>::from(current).favorite(favorite)).await
+            }
+
+            /// Update all the objects, with at most `concurrency` requests at the same time, see
+            /// [BatchResult](crate::BatchResult)
+            pub async fn update_many(
+                &self,
+                values: impl IntoIterator<Item = impl Into<crate::cse::Payload<$update>>>,
+                concurrency: usize,
+            ) -> crate::BatchResult<$ident> {
+                crate::utils::batch(values, concurrency, |value| self.update(value)).await
+            }
             )?
 
             $(
             $(#[$meta_delete])*
-            pub async fn delete(&self, id: uuid::Uuid, revision: Option<uuid::Uuid>) -> Result<$trashed, $err> {
+            pub async fn delete(&self, id: impl Into<uuid::Uuid>, revision: Option<uuid::Uuid>) -> Result<$trashed, $err> {
                 #[derive(Serialize)]
                 struct Request {
                     id: uuid::Uuid,
                     #[serde(skip_serializing_if = "Option::is_none")]
                     revision: Option<uuid::Uuid>,
                 }
+                let id = id.into();
                 self.api
-                    .passwords_delete(concat!($endpoint, "/delete"), Request { id, revision })
+                    .passwords_delete(self.api.endpoint($resource, "delete"), Request { id, revision })
                     .await
             }
+
+            /// Delete all the objects like `delete` without a revision, with at most
+            /// `concurrency` requests at the same time, see [BatchResult](crate::BatchResult)
+            pub async fn delete_many(
+                &self,
+                ids: impl IntoIterator<Item = uuid::Uuid>,
+                concurrency: usize,
+            ) -> crate::BatchResult<$trashed> {
+                crate::utils::batch(ids, concurrency, |id| self.delete(id, None)).await
+            }
+
+            /// Delete the object permanently, moving it to the trash first if needed
+            pub async fn purge(&self, id: impl Into<uuid::Uuid>) -> Result<crate::Purged, $err> {
+                let id = id.into();
+                let trashed = self.delete(id, None).await?;
+                // Without a revision the object was already in the trash and is now deleted
+                if let Some(revision) = trashed.revision {
+                    match self.delete(id, Some(revision)).await {
+                        Err(e) if e.code() != crate::ErrorCode::NotFound => return Err(e),
+                        _ => (),
+                    }
+                }
+                Ok(crate::Purged { id })
+            }
             )?
 
             $(
             $(#[$meta_restore])*
             pub async fn restore(
                 &self,
-                id: uuid::Uuid,
+                id: impl Into<uuid::Uuid>,
                 revision: Option<uuid::Uuid>,
             ) -> Result<$ident, $err> {
                 #[derive(Serialize)]
@@ -290,10 +1056,67 @@ macro_rules! create_calls {
                     #[serde(skip_serializing_if = "Option::is_none")]
                     revision: Option<uuid::Uuid>,
                 }
+                let id = id.into();
+                self.api
+                    .passwords_patch(self.api.endpoint($resource, "restore"), Request { id, revision })
+                    .await
+            }
+            )?
+
+            $(
+            $(#[$meta_patches])*
+            pub async fn apply_patch(&self, patch: $patch) -> Result<Option<$ident>, $err> {
+                if patch.is_empty() {
+                    return Ok(None);
+                }
+                self.update(patch.update).await.map(Some)
+            }
+            )?
+
+            $(
+            $(#[$meta_summaries])*
+            pub async fn list_summaries(&self) -> Result<Vec<$summary>, $err> {
                 self.api
-                    .passwords_patch(concat!($endpoint, "/restore"), Request { id, revision })
+                    .passwords_list(self.api.endpoint($resource, "list"), ())
                     .await
             }
+
+            /// Fetch the full object of a summary returned by `list_summaries`
+            pub async fn hydrate(
+                &self,
+                summary: &$summary,
+                details: Option<$details>,
+            ) -> Result<$ty, $err> {
+                self.get(details, summary.id).await
+            }
+            )?
+
+            $(
+            $(#[$meta_trash])*
+            pub async fn trashed(&self, details: Option<$details>) -> Result<Vec<$ty>, $err> {
+                let criteria =
+                    <$criteria>::new().and_trashed(crate::SearchQuery::exact(true))?;
+                self.find(criteria, details).await
+            }
+
+            /// Delete all the objects in the trash. The objects that can't be deleted are
+            /// reported without stopping the others.
+            pub async fn empty_trash(&self) -> Result<crate::EmptyTrashReport, $err> {
+                let mut report = crate::EmptyTrashReport::default();
+                for trashed in self.trashed(None).await? {
+                    let id = crate::utils::ObjectId::to_uuid(&trashed.id)?;
+                    let revision = crate::utils::Revisioned::current_revision(&trashed);
+                    match self.delete(id, Some(revision)).await {
+                        Ok(_) => report.deleted.push(id),
+                        // Already deleted with a trashed folder
+                        Err(e) if e.code() == crate::ErrorCode::NotFound => {
+                            report.deleted.push(id)
+                        }
+                        Err(e) => report.failed.push((id, e)),
+                    }
+                }
+                Ok(report)
+            }
             )?
         }
     };
@@ -305,7 +1128,7 @@ macro_rules! create_binding {
     (
         $(#[$s_attr:meta])*
         pub
-        struct $name:ident {
+        struct $name:ident $([$extra:ident])? {
             $(
                 $(#[$f_attr:meta])*
                 pub
@@ -316,6 +1139,7 @@ macro_rules! create_binding {
         create_binding! {
             @name $name
             @meta ($($s_attr)*)
+            @extra ($($extra)?)
             @create_new ()
             @create ()
             @update_new ()
@@ -337,6 +1161,7 @@ macro_rules! create_binding {
         @meta (
             $($s_attr:tt)*
         )
+        @extra ( $($extra_field:ident)? )
         @create_new (
             $(
             $(
@@ -412,6 +1237,12 @@ macro_rules! create_binding {
                 #[serde(flatten)]
                 pub
                 versioned : [<Versioned $name>],
+                $(
+                    /// Properties unknown to this crate, they are kept when updating
+                    #[serde(flatten)]
+                    pub
+                    $extra_field : std::collections::HashMap<String, serde_json::Value>,
+                )?
             }
             ::doc_comment::doc_comment! { concat!("versioned properties of [", stringify!($name), "]"),
             $(#[$s_attr])*
@@ -444,12 +1275,12 @@ macro_rules! create_binding {
 
             impl [<Create $name>] {
                 pub
-                fn new ($($cn_field: $cn_type,)*)
+                fn new ($($cn_field: impl Into<$cn_type>,)*)
                   -> Self
                 {
                     Self {
                         $(
-                            $cn_field,
+                            $cn_field: $cn_field.into(),
                         )*
                         $(
                             $c_field: None,
@@ -459,10 +1290,10 @@ macro_rules! create_binding {
 
                 $(
                     pub
-                    fn $c_field (self: Self, $c_field: $c_type)
+                    fn $c_field (self: Self, $c_field: impl Into<$c_type>)
                       -> Self
                     {
-                        Self { $c_field: Some($c_field), ..self }
+                        Self { $c_field: Some($c_field.into()), ..self }
                     }
                 )*
             }
@@ -474,9 +1305,12 @@ macro_rules! create_binding {
             #[derive(serde::Serialize, Default)]
             pub struct [<$name Search>] {
                 $(
-                    #[serde(skip_serializing_if = "Option::is_none")]
+                    #[serde(
+                        skip_serializing_if = "Vec::is_empty",
+                        serialize_with = "crate::utils::serialize_criteria"
+                    )]
                     $(#[$se_attr])?
-                    $se_field: Option<crate::utils::Criteria>,
+                    $se_field: Vec<crate::utils::Criteria>,
                 )+
             }
             }
@@ -484,12 +1318,15 @@ macro_rules! create_binding {
                 pub fn new() -> Self {
                     Default::default()
                 }
+                /// The criteria object sent to the server
+                pub fn to_json(&self) -> serde_json::Value {
+                    serde_json::to_value(self).expect("search criteria are always serializable")
+                }
                 $(
-                    pub fn [<and_ $se_field>](self, query: crate::utils::SearchQuery<$se_type>) -> Result<Self, crate::Error> {
-                        Ok(Self {
-                            $se_field: Some(query.to_criteria()?),
-                            ..self
-                        })
+                    /// Add a criterion on the field, all the criteria on the same field must match
+                    pub fn [<and_ $se_field>](mut self, query: crate::utils::SearchQuery<$se_type>) -> Result<Self, crate::Error> {
+                        self.$se_field.push(query.to_criteria()?);
+                        Ok(self)
                     }
                 )+
             }
@@ -510,30 +1347,114 @@ macro_rules! create_binding {
                     pub
                     $u_field : Option<$u_type>,
                 )*
+                $(
+                    #[serde(flatten)]
+                    pub
+                    $extra_field : std::collections::HashMap<String, serde_json::Value>,
+                )?
+                // Not read for the objects without revisions
+                #[allow(dead_code)]
+                #[serde(skip)]
+                expected_revision: Option<uuid::Uuid>,
             }
             }
 
             impl [<Update $name>] {
                 pub
-                fn new ($($un_field: $un_type,)*)
+                fn new ($($un_field: impl Into<$un_type>,)*)
                   -> Self
                 {
                     Self {
                         $(
-                            $un_field,
+                            $un_field: $un_field.into(),
                         )*
                         $(
                             $u_field: None,
                         )*
+                        $(
+                            $extra_field: Default::default(),
+                        )?
+                        expected_revision: None,
+                    }
+                }
+
+                /// Only apply the update if the object is still at this revision on the server,
+                /// else fail with [Conflict](crate::Error::Conflict).
+                ///
+                /// The API can't do this check itself, so the object is fetched before the update
+                pub fn expected_revision(self, revision: uuid::Uuid) -> Self {
+                    Self { expected_revision: Some(revision), ..self }
+                }
+
+                $(
+                    /// Properties unknown to this crate to send with the update
+                    pub
+                    fn $extra_field (self: Self, $extra_field: std::collections::HashMap<String, serde_json::Value>)
+                      -> Self
+                    {
+                        Self { $extra_field, ..self }
+                    }
+                )?
+
+                $(
+                    pub
+                    fn $u_field (self: Self, $u_field: impl Into<$u_type>)
+                      -> Self
+                    {
+                        Self { $u_field: Some($u_field.into()), ..self }
+                    }
+                )*
+            }
+
+            ::doc_comment::doc_comment! {
+                concat!(
+                    "Changes to an existing [", stringify!($name), "]. ",
+                    "Unlike [Update", stringify!($name), "], all the values not changed by the ",
+                    "patch are sent as they were in the original object."
+                ),
+            pub struct [<$name Patch>] {
+                update: [<Update $name>],
+                changed: Vec<&'static str>,
+            }
+            }
+
+            impl [<$name Patch>] {
+                fn from_update(update: [<Update $name>]) -> Self {
+                    Self {
+                        update,
+                        changed: Vec::new(),
                     }
                 }
 
+                fn mark(&mut self, field: &'static str) {
+                    if !self.changed.contains(&field) {
+                        self.changed.push(field);
+                    }
+                }
+
+                /// The fields whose setter was called, in the order of the first call
+                pub fn changed(&self) -> &[&'static str] {
+                    &self.changed
+                }
+
+                /// No setter was called, applying the patch does nothing
+                pub fn is_empty(&self) -> bool {
+                    self.changed.is_empty()
+                }
+
+                /// The update that applying the patch sends
+                pub fn as_update(&self) -> &[<Update $name>] {
+                    &self.update
+                }
+
                 $(
                     pub
-                    fn $u_field (self: Self, $u_field: $u_type)
+                    fn $u_field (mut self: Self, $u_field: impl Into<$u_type>)
                       -> Self
                     {
-                        Self { $u_field: Some($u_field), ..self }
+                        self.update.$u_field = Some($u_field.into());
+                        self.mark(stringify!($u_field));
+                        self
                     }
                 )*
             }
@@ -544,6 +1465,7 @@ macro_rules! create_binding {
     (
         @name $name:ident
         @meta $meta:tt
+        @extra $extra:tt
         @create_new ($($create_new:tt)*)
         @create $create:tt
         @update_new $update_new:tt
@@ -557,6 +1479,7 @@ macro_rules! create_binding {
         create_binding! {
             @name $name
             @meta $meta
+            @extra $extra
             @create_new ( $($create_new)* $current )
             @create $create
             @update_new $update_new
@@ -573,6 +1496,7 @@ macro_rules! create_binding {
     (
         @name $name:ident
         @meta $meta:tt
+        @extra $extra:tt
         @create_new $create_new:tt
         @create ($($create:tt)*)
         @update_new $update_new:tt
@@ -586,6 +1510,7 @@ macro_rules! create_binding {
         create_binding! {
             @name $name
             @meta $meta
+            @extra $extra
             @create_new $create_new
             @create ($($create)* $current)
             @update_new $update_new
@@ -602,6 +1527,7 @@ macro_rules! create_binding {
     (
         @name $name:ident
         @meta $meta:tt
+        @extra $extra:tt
         @create_new $create_new:tt
         @create $create:tt
         @update_new ($($update_new:tt)*)
@@ -615,6 +1541,7 @@ macro_rules! create_binding {
         create_binding! {
             @name $name
             @meta $meta
+            @extra $extra
             @create_new $create_new
             @create $create
             @update_new ($($update_new)* $current)
@@ -631,6 +1558,7 @@ macro_rules! create_binding {
     (
         @name $name:ident
         @meta $meta:tt
+        @extra $extra:tt
         @create_new $create_new:tt
         @create $create:tt
         @update_new $update_new:tt
@@ -644,6 +1572,7 @@ macro_rules! create_binding {
         create_binding! {
             @name $name
             @meta $meta
+            @extra $extra
             @create_new $create_new
             @create $create
             @update_new $update_new
@@ -660,6 +1589,7 @@ macro_rules! create_binding {
     (
         @name $name:ident
         @meta $meta:tt
+        @extra $extra:tt
         @create_new $create_new:tt
         @create $create:tt
         @update_new $update_new:tt
@@ -673,6 +1603,7 @@ macro_rules! create_binding {
         create_binding! {
             @name $name
             @meta $meta
+            @extra $extra
             @create_new $create_new
             @create $create
             @update_new $update_new
@@ -689,6 +1620,7 @@ macro_rules! create_binding {
     (
         @name $name:ident
         @meta $meta:tt
+        @extra $extra:tt
         @create_new $create_new:tt
         @create $create:tt
         @update_new $update_new:tt
@@ -702,6 +1634,7 @@ macro_rules! create_binding {
         create_binding! {
             @name $name
             @meta $meta
+            @extra $extra
             @create_new $create_new
             @create $create
             @update_new $update_new
@@ -717,6 +1650,7 @@ macro_rules! create_binding {
     (
         @name $name:ident
         @meta $meta:tt
+        @extra $extra:tt
         @create_new $create_new:tt
         @create $create:tt
         @update_new $update_new:tt
@@ -730,6 +1664,7 @@ macro_rules! create_binding {
         create_binding! {
             @name $name
             @meta $meta
+            @extra $extra
             @create_new $create_new
             @create $create
             @update_new $update_new
@@ -747,6 +1682,7 @@ macro_rules! create_binding {
     (
         @name $name:ident
         @meta $meta:tt
+        @extra $extra:tt
         @create_new $create_new:tt
         @create $create:tt
         @update_new $update_new:tt
@@ -760,6 +1696,7 @@ macro_rules! create_binding {
         create_binding! {
             @name $name
             @meta $meta
+            @extra $extra
             @create_new $create_new
             @create $create
             @update_new $update_new
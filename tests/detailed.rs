@@ -0,0 +1,28 @@
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use nextcloud_passwords_client::{password::Details, AuthenticatedApi};
+
+#[tokio::test]
+async fn detailed() {
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+
+    let list = api.password().list_detailed(None).await.unwrap();
+    assert_eq!(list[0].details, Details::new());
+    assert!(!list[0].details.covers(&Details::new().revisions()));
+
+    let details = Details::new().revisions().folder();
+    let password = api
+        .password()
+        .get_detailed(Some(details), list[0].id)
+        .await
+        .unwrap();
+    assert_eq!(password.details, details);
+    assert!(password.details.covers(&Details::new().folder()));
+    assert!(!password.details.covers(&Details::new().folder().tags()));
+    assert_eq!(password.into_inner().versioned.label, "Example");
+
+    api.disconnect().await.unwrap();
+}
@@ -0,0 +1,66 @@
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use nextcloud_passwords_client::{AuthenticatedApi, Error};
+
+#[tokio::test]
+async fn endpoint_error() {
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    match api.share().get(None, uuid::Uuid::nil()).await {
+        Err(Error::EndpointError(e)) => {
+            assert_eq!(e.id, 404);
+            assert_eq!(e.message, "Not found");
+            assert_eq!(e.http_status, Some(404));
+        }
+        _ => panic!("the share was found"),
+    }
+}
+
+#[tokio::test]
+async fn unexpected_response() {
+    let server = common::MockServer::start_with_broken_passwords();
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details_for(server))
+        .await
+        .unwrap();
+
+    match api.password().list(None).await {
+        Err(Error::UnexpectedResponse {
+            endpoint,
+            status,
+            body,
+            ..
+        }) => {
+            assert_eq!(endpoint, "1.0/password/list");
+            assert_eq!(status, 200);
+            assert_eq!(body, r#"{"password":"<redacted>","unexpected":true}"#);
+        }
+        _ => panic!("the password was read"),
+    }
+    match api.password().get(None, uuid::Uuid::nil()).await {
+        Err(Error::UnexpectedResponse { endpoint, body, .. }) => {
+            assert_eq!(endpoint, "1.0/password/show");
+            // The secrets of the response are not kept in the error
+            assert!(!body.contains("hunter2"));
+            assert_eq!(body, r#"[{"password":"<redacted>","unexpected":true}]"#);
+        }
+        _ => panic!("the password was read"),
+    }
+}
+
+#[tokio::test]
+async fn lossy_list() {
+    let server = common::MockServer::start_with_broken_passwords();
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details_for(server))
+        .await
+        .unwrap();
+
+    let (passwords, errors) = api.password().list_lossy(None).await.unwrap();
+    assert_eq!(passwords.len(), 1);
+    assert_eq!(passwords[0].versioned.label, "Example");
+    assert_eq!(errors.len(), 2);
+    assert_eq!((errors[0].index, errors[0].id), (1, None));
+    assert_eq!(errors[1].index, 2);
+    assert_eq!(errors[1].id.unwrap().to_string(), common::BROKEN_ID);
+}
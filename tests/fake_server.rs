@@ -0,0 +1,1048 @@
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use nextcloud_passwords_client::{
+    accounts::AccountManager,
+    export::{FolderNode, FolderTemplate},
+    folder::{self, CreateFolder, ParentInfo, UpdateFolder, BASE_FOLDER},
+    import::{apply_folder_template, AppliedFolder, ApplyMode},
+    password::{
+        diff, hash_password, CreatePassword, Details, FolderInfo, MoveOptions, MoveWarning,
+        PasswordPatch, UpdatePassword, HASH_LENGTH,
+    },
+    service::{GeneratePassword, MiniatureSize},
+    settings::Settings,
+    tag::CreateTag,
+    testing::fake::FakeServer,
+    AuthenticatedApi, Color, Error, ErrorCode, Filter, Url, UrlMatch,
+};
+
+async fn session() -> AuthenticatedApi {
+    let login = common::mock_login_details_for(FakeServer::start());
+    AuthenticatedApi::new_session(login).await.unwrap().0
+}
+
+fn example() -> CreatePassword {
+    CreatePassword::new("Example", "hunter2")
+}
+
+#[tokio::test]
+async fn revisions() {
+    let api = session().await;
+    let created = api.password().create(example().edited(1)).await.unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(password.revision, created.revision);
+    assert_eq!(password.versioned.edited, 1);
+
+    let patch = PasswordPatch::new(&password).label("Renamed");
+    let renamed = api.password().apply_patch(patch).await.unwrap().unwrap();
+    assert_ne!(renamed.revision, created.revision);
+    let renamed = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(renamed.versioned.edited, 1, "the hash did not change");
+
+    // The patch was made from the first revision
+    let stale = PasswordPatch::new(&password).label("Stale");
+    let result = api.password().apply_patch(stale).await;
+    assert!(matches!(result, Err(Error::Conflict { .. })));
+
+    let patch = PasswordPatch::new(&renamed).password("hunter3");
+    api.password().apply_patch(patch).await.unwrap();
+    let changed = api
+        .password()
+        .get(Some(Details::new().revisions()), created.id)
+        .await
+        .unwrap();
+    assert_eq!(
+        changed.versioned.hash,
+        "71544f76730f65cdb71a68877b02d015feb51ab1"
+    );
+    assert_eq!(changed.revisions.unwrap().len(), 3);
+
+    let future = api
+        .password()
+        .create(example().edited(i64::MAX))
+        .await
+        .unwrap();
+    let future = api.password().get(None, future.id).await.unwrap();
+    assert!(future.versioned.edited <= future.created);
+}
+
+#[tokio::test]
+async fn revision_history() {
+    let api = session().await;
+    let created = api.password().create(example()).await.unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    let patch = PasswordPatch::new(&password).notes("Some notes");
+    let changed = api.password().apply_patch(patch).await.unwrap().unwrap();
+
+    let first = api
+        .password()
+        .get_revision(created.id, created.revision)
+        .await
+        .unwrap();
+    let second = api
+        .password()
+        .get_revision(created.id, changed.revision)
+        .await
+        .unwrap();
+    assert_eq!(first.notes, "");
+    let changes = diff(&first, &second);
+    let notes = changes
+        .iter()
+        .find(|change| change.field == "notes")
+        .unwrap();
+    assert_eq!(notes.before, "");
+    assert_eq!(notes.after, "Some notes");
+    assert!(changes.iter().all(|change| change.field != "label"));
+    assert!(diff(&first, &first).is_empty());
+
+    let unknown = uuid::Uuid::from_u128(42);
+    let result = api.password().get_revision(created.id, unknown).await;
+    assert!(matches!(result, Err(Error::RevisionNotFound { .. })));
+
+    api.password()
+        .restore(created.id, Some(created.revision))
+        .await
+        .unwrap();
+    let restored = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(restored.versioned.notes, "");
+}
+
+#[tokio::test]
+async fn trash_then_delete() {
+    let api = session().await;
+    let created = api.password().create(example()).await.unwrap();
+
+    let outdated = api
+        .password()
+        .delete(created.id, Some(uuid::Uuid::nil()))
+        .await;
+    assert!(matches!(outdated, Err(Error::EndpointError(_))));
+
+    let trashed = api.password().delete(created.id, None).await.unwrap();
+    assert!(trashed.revision.is_some());
+    assert!(
+        api.password()
+            .get(None, created.id)
+            .await
+            .unwrap()
+            .versioned
+            .trashed
+    );
+    assert!(api.password().list(None).await.unwrap().is_empty());
+
+    let restored = api.password().restore(created.id, None).await.unwrap();
+    assert_eq!(api.password().list(None).await.unwrap().len(), 1);
+
+    api.password()
+        .delete(created.id, Some(restored.revision))
+        .await
+        .unwrap();
+    let deleted = api.password().delete(created.id, None).await.unwrap();
+    assert!(deleted.revision.is_none());
+    assert!(api.password().get(None, created.id).await.is_err());
+}
+
+#[tokio::test]
+async fn move_to_hidden_folder() {
+    let api = session().await;
+    let base = api.password().create(example()).await.unwrap();
+    let unknown = uuid::Uuid::from_u128(42);
+    let password = api.password().get(None, base.id).await.unwrap();
+    let created = api
+        .password()
+        .create(example().folder(FolderInfo::new(unknown)))
+        .await
+        .unwrap();
+    let fallback = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(fallback.folder.id(), password.folder.id(), "unknown folder");
+
+    let hidden = api
+        .folder()
+        .create(CreateFolder::new("Hidden").hidden(true))
+        .await
+        .unwrap();
+    let report = api
+        .password()
+        .move_matching(
+            &Filter::label_contains("Example"),
+            hidden.id,
+            MoveOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(report.moved.len(), 2);
+    assert!(matches!(
+        report.warnings[0],
+        MoveWarning::HiddenFolder { .. }
+    ));
+    for password in api.password().list(None).await.unwrap() {
+        assert_eq!(
+            password.folder.id(),
+            fallback.folder.id(),
+            "moved to the base folder"
+        );
+    }
+}
+
+#[tokio::test]
+async fn move_to_folder() {
+    let api = session().await;
+    let work = api
+        .folder()
+        .create(CreateFolder::new("Work"))
+        .await
+        .unwrap();
+    let created = api.password().create(example()).await.unwrap();
+
+    let moved = api
+        .password()
+        .move_to_folder(created.id, work.id)
+        .await
+        .unwrap();
+    assert_ne!(moved.revision, created.revision);
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(password.folder.id(), work.id);
+    assert_eq!(password.versioned.label, "Example");
+
+    let unknown = uuid::Uuid::from_u128(42);
+    let result = api.password().move_to_folder(created.id, unknown).await;
+    assert!(result.is_err());
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(
+        password.folder.id(),
+        work.id,
+        "not moved to the base folder"
+    );
+
+    let sub = api.folder().create(CreateFolder::new("Sub")).await.unwrap();
+    api.folder().move_to(sub.id, work.id).await.unwrap();
+    let folder = api.folder().get(None, sub.id).await.unwrap();
+    assert_eq!(folder.versioned.parent.id(), work.id);
+    assert_eq!(folder.versioned.label, "Sub");
+    assert!(api.folder().move_to(sub.id, unknown).await.is_err());
+
+    // Instead of letting the server move them to the base folder
+    let hidden = CreateFolder::new("Hidden").hidden(true);
+    let hidden = api.folder().create(hidden).await.unwrap();
+    let result = api.password().move_to_folder(created.id, hidden.id).await;
+    assert!(matches!(result, Err(Error::HiddenFolder { object, .. }) if object == created.id));
+    let result = api.folder().move_to(sub.id, hidden.id).await;
+    assert!(matches!(result, Err(Error::HiddenFolder { folder, .. }) if folder == hidden.id));
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(password.folder.id(), work.id);
+}
+
+#[tokio::test]
+async fn empty_patch() {
+    let api = session().await;
+    let created = api.password().create(example()).await.unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+
+    let patch = PasswordPatch::new(&password);
+    assert!(api.password().apply_patch(patch).await.unwrap().is_none());
+    let stats = api.request_stats();
+    assert!(stats.contains_key("1.0/password/show"));
+    assert!(!stats.contains_key("1.0/password/update"));
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(password.revision, created.revision);
+}
+
+#[tokio::test]
+async fn favorites() {
+    let api = session().await;
+    let created = api.password().create(example().hidden(true)).await.unwrap();
+    let favorite = api.password().set_favorite(created.id, true).await.unwrap();
+    assert_ne!(favorite.revision, created.revision);
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert!(password.versioned.favorite);
+    assert!(password.versioned.hidden);
+    assert_eq!(password.revision, favorite.revision);
+    api.password()
+        .set_favorite(created.id, false)
+        .await
+        .unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert!(!password.versioned.favorite);
+
+    let folder = api
+        .folder()
+        .create(CreateFolder::new("Work"))
+        .await
+        .unwrap();
+    api.folder().set_favorite(folder.id, true).await.unwrap();
+    let folder = api.folder().get(None, folder.id).await.unwrap();
+    assert!(folder.versioned.favorite);
+    assert_eq!(folder.versioned.label, "Work");
+
+    let color = Color {
+        red: 0,
+        green: 0,
+        blue: 0,
+    };
+    let tag = api
+        .tag()
+        .create(CreateTag::new("Home", color))
+        .await
+        .unwrap();
+    api.tag().set_favorite(tag.id, true).await.unwrap();
+    assert!(
+        api.tag()
+            .get(None, tag.id)
+            .await
+            .unwrap()
+            .versioned
+            .favorite
+    );
+}
+
+#[tokio::test]
+async fn find_for_url() {
+    let api = session().await;
+    let create = |label: &str, url: &str| CreatePassword::new(label, "hunter2").url(url);
+    let values = vec![
+        create("Example", "https://example.org"),
+        create("Accounts", "https://accounts.example.com"),
+        create("Login", "https://login.accounts.example.com/"),
+        create("Other", "https://other.net"),
+        create("Hidden", "https://login.accounts.example.com/").hidden(true),
+    ];
+    assert!(api.password().create_many(values, 2).await.is_ok());
+
+    let url = Url::parse("https://login.accounts.example.com/signin?next=/").unwrap();
+    let found = api.password().find_for_url(&url).await.unwrap();
+    let found: Vec<_> = found
+        .iter()
+        .map(|found| (found.password.versioned.label.as_str(), found.score))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("Login", UrlMatch::Host),
+            ("Accounts", UrlMatch::Domain),
+            ("Example", UrlMatch::Substring),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn external_id() {
+    let api = session().await;
+    let tagged = example().external_id("sync:[a]", "item:1,[x]").unwrap();
+    let created = api.password().create(tagged).await.unwrap();
+    api.password().create(example()).await.unwrap();
+
+    let passwords = api.password();
+    let found = passwords.find_by_external_id("sync:[a]", "item:1,[x]");
+    let found = found.await.unwrap().unwrap();
+    assert_eq!(found.id, created.id);
+    assert_eq!(found.external_id("sync:[a]").as_deref(), Some("item:1,[x]"));
+
+    let missing = passwords.find_by_external_id("sync:[a]", "item:2");
+    assert!(missing.await.unwrap().is_none());
+    let other_namespace = passwords.find_by_external_id("sync", "item:1,[x]");
+    assert!(other_namespace.await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn security_report() {
+    let api = session().await;
+    let values = vec![
+        example(),
+        CreatePassword::new("Other", "correct horse"),
+        example(),
+    ];
+    let created = api.password().create_many(values, 1).await;
+    let ids: Vec<_> = created.succeeded().map(|created| created.id).collect();
+
+    let report = api.password().security_report().await.unwrap();
+    assert_eq!(report.duplicates.len(), 1);
+    let duplicates: Vec<_> = report.duplicates[0].iter().map(|entry| entry.id).collect();
+    assert_eq!(duplicates, [ids[0], ids[2]]);
+    assert!(report.old.is_empty(), "disabled by the settings");
+}
+
+#[tokio::test]
+async fn hash_length_setting() {
+    let api = session().await;
+    let full = hash_password("hunter2", HASH_LENGTH);
+    let created = api.password().create(example()).await.unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(password.versioned.hash, full);
+
+    let settings = Settings::new().password_hash_length(20);
+    api.settings().set(settings).await.unwrap();
+    let created = api.password().create(example()).await.unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(password.versioned.hash, &full[..20]);
+
+    let update = UpdatePassword::new("Example", "hunter3", created.id);
+    api.password().update(update).await.unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(
+        password.versioned.hash,
+        &hash_password("hunter3", HASH_LENGTH)[..20]
+    );
+}
+
+#[tokio::test]
+async fn hash_length_without_setting() {
+    // An older app, without the hash length nor some theme settings
+    let missing = &["user.password.security.hash", "server.theme.label"];
+    let login = common::mock_login_details_for(FakeServer::start_without_settings(missing));
+    let api = AuthenticatedApi::new_session(login).await.unwrap().0;
+    assert_eq!(api.hash_length().await.unwrap(), HASH_LENGTH);
+    let created = api.password().create(example()).await.unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(
+        password.versioned.hash,
+        hash_password("hunter2", HASH_LENGTH)
+    );
+    let capabilities = api.capabilities().await;
+    assert!(matches!(capabilities, Err(Error::MissingSetting(_))));
+}
+
+#[tokio::test]
+async fn create_generated() {
+    let api = session().await;
+    let (created, generated) = api
+        .password()
+        .create_generated("Example".into(), Some("https://example.com".into()), None)
+        .await
+        .unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(password.reveal().expose(), generated.password);
+    assert_eq!(password.versioned.url, "https://example.com");
+    assert_eq!(
+        password.versioned.hash,
+        hash_password(&generated.password, HASH_LENGTH)
+    );
+
+    let settings = GeneratePassword::new().numbers(true);
+    let (_, generated) = api
+        .password()
+        .create_generated("Other".into(), None, Some(settings))
+        .await
+        .unwrap();
+    assert!(generated.numbers);
+
+    // The hash follows the settings of the user
+    let settings = Settings::new().password_hash_length(30);
+    api.settings().set(settings).await.unwrap();
+    let (created, generated) = api
+        .password()
+        .create_generated("Short hash".into(), None, None)
+        .await
+        .unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(
+        password.versioned.hash,
+        &hash_password(&generated.password, HASH_LENGTH)[..30]
+    );
+}
+
+#[tokio::test]
+async fn validation() {
+    let api = session().await;
+    let long = CreatePassword::new("a".repeat(65), "hunter2");
+    let result = api.password().create(long).await;
+    assert!(matches!(result, Err(Error::Validation(ref e)) if e.field == "label"));
+    assert!(api.password().list(None).await.unwrap().is_empty());
+
+    let long = example().notes("a".repeat(4097));
+    let created = api.password().create_unchecked(long).await.unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    let update = UpdatePassword::from(password).favorite(true);
+    let result = api.password().update(update).await;
+    assert!(matches!(result, Err(Error::Validation(ref e)) if e.field == "notes"));
+}
+
+#[tokio::test]
+async fn update_encrypted() {
+    let api = session().await;
+    // The values of an encrypted password are hex ciphertexts, longer than the limits
+    let ciphertext = "ab".repeat(60);
+    let encrypted = CreatePassword::new(ciphertext.clone(), ciphertext.clone())
+        .username(ciphertext.as_str())
+        .cse_type("CSEv1r1")
+        .cse_key("key");
+    let created = api.password().create(encrypted).await.unwrap();
+
+    api.password().set_favorite(created.id, true).await.unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert!(password.versioned.favorite);
+    assert_eq!(password.versioned.label, ciphertext);
+    let update = UpdatePassword::from(password).favorite(false);
+    api.password().update(update).await.unwrap();
+}
+
+#[tokio::test]
+async fn batch() {
+    let api = session().await;
+    let values = (0..10).map(|i| CreatePassword::new(format!("Item {}", i), "hunter2"));
+    let created = api.password().create_many(values, 3).await;
+    assert!(created.is_ok());
+    let ids: Vec<_> = created.succeeded().map(|created| created.id).collect();
+    for (i, id) in ids.iter().enumerate() {
+        let password = api.password().get(None, *id).await.unwrap();
+        assert_eq!(password.versioned.label, format!("Item {}", i));
+    }
+
+    let mut updates = Vec::new();
+    for id in &ids {
+        let password = api.password().get(None, *id).await.unwrap();
+        updates.push(UpdatePassword::from(password).notes("Imported"));
+    }
+    assert!(api.password().update_many(updates, 3).await.is_ok());
+    let password = api.password().get(None, ids[9]).await.unwrap();
+    assert_eq!(password.versioned.notes, "Imported");
+
+    let unknown = uuid::Uuid::from_u128(42);
+    let mut to_delete = ids.clone();
+    to_delete.insert(4, unknown);
+    let deleted = api.password().delete_many(to_delete, 3).await;
+    let failed: Vec<_> = deleted.failed().map(|(index, _)| index).collect();
+    assert_eq!(failed, [4]);
+    assert_eq!(deleted.succeeded().count(), 10);
+    assert!(api.password().list(None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn purge() {
+    let api = session().await;
+    let created = api.password().create(example()).await.unwrap();
+    let purged = api.password().purge(created.id).await.unwrap();
+    assert_eq!(purged.id, created.id);
+    assert!(api.password().get(None, created.id).await.is_err());
+
+    let trashed = api.password().create(example()).await.unwrap();
+    api.password().delete(trashed.id, None).await.unwrap();
+    api.password().purge(trashed.id).await.unwrap();
+    assert!(api.password().get(None, trashed.id).await.is_err());
+
+    let result = api.password().purge(created.id).await;
+    assert!(matches!(result, Err(ref e) if e.code() == ErrorCode::NotFound));
+
+    let folder = api.folder().create(CreateFolder::new("Old")).await.unwrap();
+    api.folder().purge(folder.id).await.unwrap();
+    assert!(api.folder().get(None, folder.id).await.is_err());
+}
+
+#[tokio::test]
+async fn empty_trash() {
+    let api = session().await;
+    let kept = api.password().create(example()).await.unwrap();
+    let trashed = api.password().create(example()).await.unwrap();
+    api.password().delete(trashed.id, None).await.unwrap();
+    let color = Color {
+        red: 0,
+        green: 0,
+        blue: 0,
+    };
+    let tag = api
+        .tag()
+        .create(CreateTag::new("Old", color))
+        .await
+        .unwrap();
+    api.tag().delete(tag.id, None).await.unwrap();
+    let parent = api.folder().create(CreateFolder::new("Old")).await.unwrap();
+    let child = api
+        .folder()
+        .create(CreateFolder::new("Child").parent(ParentInfo::new(parent.id)))
+        .await
+        .unwrap();
+    api.folder().delete(child.id, None).await.unwrap();
+    api.folder().delete(parent.id, None).await.unwrap();
+
+    let in_trash = api.password().trashed(None).await.unwrap();
+    assert_eq!(in_trash.len(), 1);
+    assert_eq!(in_trash[0].id, trashed.id);
+    // The child is suspended by its trashed parent
+    assert_eq!(api.folder().trashed(None).await.unwrap().len(), 1);
+
+    let report = api.empty_trash().await.unwrap();
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    assert_eq!(report.deleted.len(), 3);
+    assert!(api.password().trashed(None).await.unwrap().is_empty());
+    assert!(api.tag().trashed(None).await.unwrap().is_empty());
+    assert!(api.folder().trashed(None).await.unwrap().is_empty());
+    assert!(api.folder().get(None, child.id).await.is_err());
+    api.password().get(None, kept.id).await.unwrap();
+}
+
+#[tokio::test]
+async fn suspended_by_trashed_folder() {
+    let api = session().await;
+    let folder = api
+        .folder()
+        .create(CreateFolder::new("Work"))
+        .await
+        .unwrap();
+    let password = api
+        .password()
+        .create(example().folder(FolderInfo::new(folder.id)))
+        .await
+        .unwrap();
+
+    api.folder().delete(folder.id, None).await.unwrap();
+    assert!(api.password().list(None).await.unwrap().is_empty());
+    assert!(api.folder().list(None).await.unwrap().is_empty());
+    // Suspended, not trashed
+    assert!(
+        !api.password()
+            .get(None, password.id)
+            .await
+            .unwrap()
+            .versioned
+            .trashed
+    );
+
+    api.folder().restore(folder.id, None).await.unwrap();
+    assert_eq!(api.password().list(None).await.unwrap().len(), 1);
+
+    let hide = UpdateFolder::new(folder.id, "Work").hidden(true);
+    api.folder().update(hide).await.unwrap();
+    assert!(
+        api.password()
+            .get(None, password.id)
+            .await
+            .unwrap()
+            .versioned
+            .hidden
+    );
+
+    let report = api
+        .password()
+        .delete_with_shares(password.id, None)
+        .await
+        .unwrap();
+    assert!(report.deleted_shares.is_empty());
+    assert!(report.password.unwrap().revision.is_some());
+}
+
+#[tokio::test]
+async fn client_name() {
+    let login = common::mock_login_details_for(FakeServer::start()).with_client_name("my-cli/1.2");
+    let (api, _) = AuthenticatedApi::new_session(login).await.unwrap();
+    let created = api.password().create(example()).await.unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(password.versioned.client, "my-cli/1.2");
+
+    let (resumed, _) = AuthenticatedApi::resume_session(api.get_state())
+        .await
+        .unwrap();
+    let patch = PasswordPatch::new(&password).label("Renamed");
+    resumed.password().apply_patch(patch).await.unwrap();
+    let password = api.password().get(None, created.id).await.unwrap();
+    assert_eq!(password.versioned.client, "my-cli/1.2");
+}
+
+#[tokio::test]
+async fn tags() {
+    let api = session().await;
+    let color = Color {
+        red: 0,
+        green: 0,
+        blue: 0,
+    };
+    let work = api
+        .tag()
+        .create(CreateTag::new("Work", color))
+        .await
+        .unwrap();
+    let color = Color {
+        red: 0xff,
+        green: 0,
+        blue: 0,
+    };
+    let home = api
+        .tag()
+        .create(CreateTag::new("Home", color))
+        .await
+        .unwrap();
+    let created = api.password().create(example()).await.unwrap();
+    let tags = |password: nextcloud_passwords_client::password::Password| {
+        let tags = password.tags.unwrap_or_default();
+        tags.into_iter()
+            .map(|tag| tag.id.parse().unwrap())
+            .collect::<Vec<uuid::Uuid>>()
+    };
+    let details = || Some(Details::new().tags());
+
+    api.password().add_tag(created.id, work.id).await.unwrap();
+    api.password().add_tag(created.id, home.id).await.unwrap();
+    api.password().add_tag(created.id, home.id).await.unwrap();
+    let password = api.password().get(details(), created.id).await.unwrap();
+    assert_eq!(
+        password.versioned.hash,
+        "f3bbbd66a63d4bf1747940578ec3d0103530e21d"
+    );
+    assert_eq!(tags(password), [work.id, home.id]);
+
+    api.password()
+        .remove_tag(created.id, work.id)
+        .await
+        .unwrap();
+    let password = api.password().get(details(), created.id).await.unwrap();
+    assert_eq!(tags(password), [home.id]);
+    api.password()
+        .remove_tag(created.id, home.id)
+        .await
+        .unwrap();
+    let password = api.password().get(details(), created.id).await.unwrap();
+    assert_eq!(password.versioned.label, "Example");
+    assert_eq!(tags(password), []);
+}
+
+#[tokio::test]
+async fn hidden_items() {
+    let api = session().await;
+    let color = Color {
+        red: 0,
+        green: 0,
+        blue: 0,
+    };
+    let secret = api
+        .tag()
+        .create(CreateTag::new("Secret", color).hidden(true))
+        .await
+        .unwrap();
+    let visible = api.password().create(example()).await.unwrap();
+    let hidden = api.password().create(example().hidden(true)).await.unwrap();
+    api.password().add_tag(visible.id, secret.id).await.unwrap();
+    api.password().add_tag(hidden.id, secret.id).await.unwrap();
+
+    let details = || Some(Details::new().tags());
+    let visible = api.password().get(details(), visible.id).await.unwrap();
+    assert_eq!(visible.tags.unwrap().len(), 0);
+    let hidden = api.password().get(details(), hidden.id).await.unwrap();
+    assert_eq!(hidden.tags.unwrap()[0].id, secret.id.to_string());
+
+    // Hidden objects are not listed, but can still be fetched by their id
+    let passwords = api.password().list(None).await.unwrap();
+    assert_eq!(passwords.len(), 1);
+    assert_eq!(passwords[0].id, visible.id);
+    assert!(api.tag().list(None).await.unwrap().is_empty());
+    assert!(
+        api.tag()
+            .get(None, secret.id)
+            .await
+            .unwrap()
+            .versioned
+            .hidden
+    );
+
+    // Changing the visible tags keeps the hidden ones
+    let color = Color {
+        red: 0,
+        green: 0,
+        blue: 0xff,
+    };
+    let work = api
+        .tag()
+        .create(CreateTag::new("Work", color))
+        .await
+        .unwrap();
+    api.password().add_tag(visible.id, work.id).await.unwrap();
+    let update = UpdatePassword::new("Example", "hunter2", visible.id).hidden(true);
+    api.password().update(update).await.unwrap();
+    let hidden = api.password().get(details(), visible.id).await.unwrap();
+    let tags: Vec<_> = hidden.tags.unwrap().into_iter().map(|tag| tag.id).collect();
+    assert_eq!(tags, [work.id.to_string(), secret.id.to_string()]);
+}
+
+#[tokio::test]
+async fn favicon() {
+    let api = session().await;
+    let favicon = |url: &'static str, label: &'static str| {
+        let api = &api;
+        async move {
+            let created = api
+                .password()
+                .create(CreatePassword::new(label, "hunter2").url(url))
+                .await
+                .unwrap();
+            let password = api.password().get(None, created.id).await.unwrap();
+            let favicon = api
+                .password()
+                .favicon(&password, MiniatureSize::default())
+                .await
+                .unwrap();
+            serde_json::from_slice::<String>(&favicon).unwrap()
+        }
+    };
+    assert_eq!(
+        favicon("https://login.example.co.uk/a/b", "Example").await,
+        "example.co.uk"
+    );
+    assert_eq!(favicon("", "Example").await, "Example");
+    assert_eq!(favicon("", "Work/Mail 50%").await, "Work%2FMail%2050%25");
+}
+
+#[tokio::test]
+async fn folder_paths() {
+    let api = session().await;
+    let aws = api.folder().create_path("Work/Infra/AWS").await.unwrap();
+    let again = api.folder().create_path("/Work/Infra/AWS/").await.unwrap();
+    assert_eq!(again.id, aws.id);
+    assert_eq!(api.folder().list(None).await.unwrap().len(), 3);
+    let gcp = api.folder().create_path("Work//Infra/GCP").await.unwrap();
+    assert_eq!(api.folder().list(None).await.unwrap().len(), 4);
+
+    let infra = api
+        .folder()
+        .get_by_path("Work/Infra")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(infra.versioned.label, "Infra");
+    let found = api.folder().get_by_path("Work/Infra/GCP").await.unwrap();
+    assert_eq!(found.unwrap().versioned.parent.id(), infra.id);
+    assert_eq!(found_id(&api, "Work/Infra/GCP").await, Some(gcp.id));
+    assert_eq!(found_id(&api, "work/infra").await, None);
+    assert_eq!(found_id(&api, "Work/Other/AWS").await, None);
+    assert_eq!(found_id(&api, "/").await, Some(BASE_FOLDER));
+
+    api.folder()
+        .create(CreateFolder::new("Work"))
+        .await
+        .unwrap();
+    match api.folder().get_by_path("Work/Infra").await {
+        Err(Error::AmbiguousPath { label, folders, .. }) => {
+            assert_eq!(label, "Work");
+            assert_eq!(folders.len(), 2);
+        }
+        other => panic!("{:?}", other.map(|folder| folder.map(|folder| folder.id))),
+    }
+    assert!(api.folder().create_path("Work/New").await.is_err());
+}
+
+async fn found_id(api: &AuthenticatedApi, path: &str) -> Option<uuid::Uuid> {
+    let folder = api.folder().get_by_path(path).await.unwrap();
+    folder.map(|folder| folder.id)
+}
+
+#[tokio::test]
+async fn base_folder() {
+    let api = session().await;
+    let work = api
+        .folder()
+        .create(CreateFolder::new("Work"))
+        .await
+        .unwrap();
+    let nested = CreateFolder::new("Infra").parent(ParentInfo::new(work.id));
+    api.folder().create(nested).await.unwrap();
+    api.password().create(example()).await.unwrap();
+
+    let details = folder::Details::new().folders().passwords();
+    let base = api.folder().base(Some(details)).await.unwrap();
+    assert_eq!(base.id, BASE_FOLDER);
+    let folders = base.folders.unwrap();
+    assert_eq!(folders.len(), 1);
+    assert!(folders[0].versioned.parent.is_base());
+    assert_eq!(base.passwords.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn folder_tree() {
+    let api = session().await;
+    let aws = api.folder().create_path("Work/Infra/AWS").await.unwrap();
+    api.folder().create_path("Personal").await.unwrap();
+    let in_aws = example().folder(FolderInfo::new(aws.id));
+    api.password().create(in_aws).await.unwrap();
+    api.password().create(example()).await.unwrap();
+
+    let passwords = api.password().list(None).await.unwrap();
+    let tree = api.folder().tree().await.unwrap().with_passwords(passwords);
+    let labels = |folders: Vec<&folder::Folder>| {
+        let labels = folders
+            .into_iter()
+            .map(|folder| folder.versioned.label.clone());
+        labels.collect::<Vec<_>>()
+    };
+    assert_eq!(labels(tree.root()), ["Personal", "Work"]);
+    assert_eq!(labels(tree.path_of(aws.id)), ["Work", "Infra", "AWS"]);
+    assert_eq!(tree.passwords_in(aws.id).len(), 1);
+    assert_eq!(tree.passwords_in(BASE_FOLDER).len(), 1);
+}
+
+#[tokio::test]
+async fn folder_contents() {
+    let api = session().await;
+    let work = api.folder().create_path("Work").await.unwrap();
+    for label in &["Servers", "Accounts", "Hidden", "Trashed"] {
+        let folder = CreateFolder::new(*label)
+            .parent(ParentInfo::new(work.id))
+            .hidden(*label == "Hidden");
+        let created = api.folder().create(folder).await.unwrap();
+        if *label == "Trashed" {
+            api.folder().delete(created.id, None).await.unwrap();
+        }
+    }
+    for label in &["Mail", "Bank", "Hidden", "Trashed"] {
+        let password = CreatePassword::new(*label, "hunter2")
+            .folder(FolderInfo::new(work.id))
+            .hidden(*label == "Hidden");
+        let created = api.password().create(password).await.unwrap();
+        if *label == "Trashed" {
+            api.password().delete(created.id, None).await.unwrap();
+        }
+    }
+    api.password().create(example()).await.unwrap();
+
+    let contents = api.folder().contents(work.id).await.unwrap();
+    let subfolders = contents.subfolders.iter();
+    let subfolders: Vec<_> = subfolders.map(|folder| &folder.versioned.label).collect();
+    assert_eq!(subfolders, ["Accounts", "Servers"]);
+    let passwords = contents.passwords.iter();
+    let passwords: Vec<_> = passwords
+        .map(|password| &password.versioned.label)
+        .collect();
+    assert_eq!(passwords, ["Bank", "Mail"]);
+
+    let top = api.folder().contents(BASE_FOLDER).await.unwrap();
+    assert_eq!(top.subfolders.len(), 1);
+    assert_eq!(top.passwords[0].versioned.label, "Example");
+}
+
+#[tokio::test]
+async fn ancestors() {
+    let api = session().await;
+    let infra = api.folder().create_path("Work/Infra").await.unwrap();
+    let aws = api.folder().create_path("Work/Infra/AWS").await.unwrap();
+    let labels = |folders: Vec<folder::Folder>| {
+        let labels = folders.into_iter().map(|folder| folder.versioned.label);
+        labels.collect::<Vec<_>>()
+    };
+    let ancestors = api.folder().ancestors(aws.id).await.unwrap();
+    assert_eq!(ancestors[0].id, BASE_FOLDER);
+    assert_eq!(ancestors[2].id, infra.id);
+    assert_eq!(labels(ancestors), ["Home", "Work", "Infra"]);
+    let work = api.folder().get_by_path("Work").await.unwrap().unwrap();
+    assert_eq!(
+        labels(api.folder().ancestors(work.id).await.unwrap()),
+        ["Home"]
+    );
+    assert!(api
+        .folder()
+        .ancestors(BASE_FOLDER)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // The fake server does not prevent moving a folder into its own child
+    api.folder().move_to(work.id, aws.id).await.unwrap();
+    match api.folder().ancestors(infra.id).await {
+        Err(Error::FolderCycle { folders }) => assert_eq!(folders.len(), 3),
+        other => panic!("{:?}", other.map(labels)),
+    }
+}
+
+/// Work/Projects/Archive and Personal
+fn template() -> FolderTemplate {
+    let mut archive = FolderNode::new("Archive".into());
+    archive.favorite = true;
+    let mut projects = FolderNode::new("Projects".into());
+    projects.children.push(archive);
+    let mut work = FolderNode::new("Work".into());
+    work.children.push(projects);
+    FolderTemplate {
+        folders: vec![work, FolderNode::new("Personal".into())],
+    }
+}
+
+async fn parent_of(api: &AuthenticatedApi, id: uuid::Uuid) -> uuid::Uuid {
+    let folder = api.folder().get(None, id).await.unwrap();
+    folder.versioned.parent.id()
+}
+
+#[tokio::test]
+async fn apply_template_reuse() {
+    let api = session().await;
+    let work = api.folder().create_path("Work").await.unwrap();
+    let applied = apply_folder_template(&api, &template(), ApplyMode::Reuse)
+        .await
+        .unwrap();
+    let paths: Vec<_> = applied.iter().map(|folder| folder.path.join("/")).collect();
+    assert_eq!(
+        paths,
+        ["Work", "Work/Projects", "Work/Projects/Archive", "Personal"]
+    );
+    let created: Vec<_> = applied.iter().map(|folder| folder.created).collect();
+    assert_eq!(created, [false, true, true, true]);
+
+    assert_eq!(applied[0].id, work.id);
+    assert_eq!(parent_of(&api, applied[1].id).await, work.id);
+    assert_eq!(parent_of(&api, applied[2].id).await, applied[1].id);
+    assert_eq!(parent_of(&api, applied[3].id).await, BASE_FOLDER);
+    for folder in &applied {
+        let found = found_id(&api, &folder.path.join("/")).await;
+        assert_eq!(found, Some(folder.id));
+    }
+    let archive = api.folder().get(None, applied[2].id).await.unwrap();
+    assert!(archive.versioned.favorite);
+
+    // Applying it again only reuses
+    let again = apply_folder_template(&api, &template(), ApplyMode::Reuse)
+        .await
+        .unwrap();
+    assert!(again.iter().all(|folder| !folder.created));
+    let ids =
+        |folders: &[AppliedFolder]| folders.iter().map(|folder| folder.id).collect::<Vec<_>>();
+    assert_eq!(ids(&again), ids(&applied));
+}
+
+#[tokio::test]
+async fn apply_template_duplicate() {
+    let api = session().await;
+    let work = api.folder().create_path("Work").await.unwrap();
+    let applied = apply_folder_template(&api, &template(), ApplyMode::Duplicate)
+        .await
+        .unwrap();
+    assert_eq!(applied.len(), 4);
+    assert!(applied.iter().all(|folder| folder.created));
+    assert_ne!(applied[0].id, work.id);
+    assert_eq!(applied[0].path, ["Work"]);
+    assert_eq!(parent_of(&api, applied[0].id).await, BASE_FOLDER);
+    assert_eq!(applied[2].path, ["Work", "Projects", "Archive"]);
+    assert_eq!(parent_of(&api, applied[1].id).await, applied[0].id);
+    assert_eq!(parent_of(&api, applied[2].id).await, applied[1].id);
+
+    let labels = api.folder().list(None).await.unwrap();
+    let works = labels
+        .iter()
+        .filter(|folder| folder.versioned.label == "Work")
+        .count();
+    assert_eq!(works, 2);
+}
+
+#[tokio::test]
+async fn search_all() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    let healthy = session().await;
+    healthy.password().create(example()).await.unwrap();
+    let other = CreatePassword::new("Other", "hunter2");
+    healthy.password().create(other).await.unwrap();
+
+    let maintenance = std::sync::Arc::new(AtomicBool::new(false));
+    let server = common::MockServer::start_with_maintenance(maintenance.clone());
+    let login = common::mock_login_details_for(server);
+    let (failing, _) = AuthenticatedApi::new_session(login).await.unwrap();
+    maintenance.store(true, Ordering::SeqCst);
+
+    let mut accounts = AccountManager::new();
+    accounts.add("failing".into(), failing);
+    accounts.add("healthy".into(), healthy);
+    let filter = Filter::label_contains("example");
+    let search = accounts.search_all(&filter).await;
+    let matches: Vec<_> = search
+        .matches
+        .iter()
+        .map(|(id, password)| (id.0.as_str(), password.versioned.label.as_str()))
+        .collect();
+    assert_eq!(matches, [("healthy", "Example")]);
+    match &search.errors[..] {
+        [(id, Error::MaintenanceMode { .. })] => assert_eq!(id.0, "failing"),
+        other => panic!("{:?}", other),
+    }
+}
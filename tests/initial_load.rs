@@ -0,0 +1,61 @@
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use nextcloud_passwords_client::{
+    initial_load::{InitialLoadOptions, InitialPart},
+    AuthenticatedApi, Error,
+};
+
+#[tokio::test]
+async fn initial_load() {
+    let (mut api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+
+    let data = api.initial_load(InitialLoadOptions::all()).await.unwrap();
+    assert!(data.errors.is_empty());
+    assert!(data.settings.is_some());
+    assert_eq!(data.passwords.unwrap().len(), 1);
+    assert_eq!(data.folders.unwrap().len(), 1);
+    assert_eq!(data.tags.unwrap().len(), 1);
+    assert!(data.favorites.unwrap().iter().all(|p| p.versioned.favorite));
+
+    let data = api
+        .initial_load(InitialLoadOptions::new().tags())
+        .await
+        .unwrap();
+    assert!(data.tags.is_some());
+    assert!(data.settings.is_none() && data.passwords.is_none() && data.favorites.is_none());
+
+    // Every part fails on an API the server does not know
+    api.set_api_version("0.0");
+    assert!(api.initial_load(InitialLoadOptions::all()).await.is_err());
+    api.set_api_version(nextcloud_passwords_client::DEFAULT_API_VERSION);
+
+    api.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn failed_part() {
+    let server = common::MockServer::start_with_broken_passwords();
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details_for(server))
+        .await
+        .unwrap();
+
+    let options = InitialLoadOptions::new()
+        .settings()
+        .passwords()
+        .folders()
+        .tags();
+    let data = api.initial_load(options).await.unwrap();
+    assert_eq!(data.errors.len(), 1);
+    let (part, error) = &data.errors[0];
+    assert_eq!(*part, InitialPart::Passwords);
+    let list = "1.0/password/list";
+    assert!(matches!(error, Error::UnexpectedResponse { endpoint, .. } if endpoint == list));
+    assert!(data.passwords.is_none());
+    assert!(data.favorites.is_none(), "not requested");
+    assert!(data.settings.is_some());
+    assert_eq!(data.folders.unwrap().len(), 1);
+    assert_eq!(data.tags.unwrap().len(), 1);
+}
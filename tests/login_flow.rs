@@ -0,0 +1,118 @@
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use common::MockServer;
+use nextcloud_passwords_client::{login_flow::LoginFlow2, Error, LoginDetails, LoginFlow2Options};
+use std::time::Duration;
+
+fn options(timeout: Duration) -> LoginFlow2Options {
+    LoginFlow2Options {
+        poll_interval: Duration::from_millis(10),
+        timeout,
+        ..LoginFlow2Options::default()
+    }
+}
+
+#[tokio::test]
+async fn granted() {
+    let server = MockServer::start_with_pending_login(3);
+    let mut login_url = None;
+    let details = LoginDetails::register_login_flow_2_with_options(
+        server.clone(),
+        reqwest::Client::new(),
+        options(Duration::from_secs(5)),
+        |url| login_url = Some(url),
+    )
+    .await
+    .unwrap();
+    assert!(login_url.is_some());
+    assert_eq!(details.login_name, "mock");
+    assert_eq!(details.server, server);
+}
+
+#[tokio::test]
+async fn client_name() {
+    let server = MockServer::start_with_pending_login(1);
+    let mut login_url = None;
+    let options = LoginFlow2Options {
+        client_name: Some("my-cli/1.2".into()),
+        ..options(Duration::from_secs(5))
+    };
+    let details = LoginDetails::register_login_flow_2_with_options(
+        server,
+        reqwest::Client::new(),
+        options,
+        |url| login_url = Some(url),
+    )
+    .await
+    .unwrap();
+    assert_eq!(login_url.unwrap().query(), Some("client=my-cli/1.2"));
+    assert_eq!(details.client_name.as_deref(), Some("my-cli/1.2"));
+}
+
+#[tokio::test]
+async fn timed_out() {
+    let server = MockServer::start_with_pending_login(u32::MAX);
+    let result = LoginDetails::register_login_flow_2_with_options(
+        server,
+        reqwest::Client::new(),
+        options(Duration::from_millis(50)),
+        |_| (),
+    )
+    .await;
+    assert!(matches!(result, Err(Error::LoginFlowTimedOut)));
+}
+
+#[tokio::test]
+async fn poll_once() {
+    let server = MockServer::start_with_pending_login(1);
+    let (login_url, flow) = LoginFlow2::start(server).await.unwrap();
+    assert!(login_url.path().contains("login/v2/flow"));
+    // The token is enough to get the app password
+    assert!(!format!("{:?}", flow).contains("mock-token"));
+
+    // The flow can be polled from any task
+    let flow = tokio::spawn(async move {
+        assert!(flow.poll_once().await.unwrap().is_none());
+        flow
+    })
+    .await
+    .unwrap();
+    let details = flow.poll_once().await.unwrap().unwrap();
+    assert_eq!(details.login_name, "mock");
+}
+
+#[tokio::test]
+async fn user_credentials() {
+    let server = MockServer::start();
+    let details =
+        LoginDetails::from_user_credentials(server.clone(), "mock", common::MOCK_PASSWORD, None)
+            .await
+            .unwrap();
+    assert_eq!(details.app_password, "mock-app-password");
+    details.verify().await.unwrap();
+
+    // An app password is used as is
+    let details =
+        LoginDetails::from_user_credentials(server.clone(), "mock", "mock-app-password", None)
+            .await
+            .unwrap();
+    assert_eq!(details.app_password, "mock-app-password");
+
+    let refused = LoginDetails::from_user_credentials(server, "mock", "wrong", None).await;
+    assert!(matches!(refused, Err(Error::AppPasswordError(401))));
+}
+
+#[tokio::test]
+async fn user_credentials_otp() {
+    let server = MockServer::start_with_tokens(vec![serde_json::json!({"id": "twofactor_totp"})]);
+    let password = common::MOCK_PASSWORD;
+    let refused = LoginDetails::from_user_credentials(server.clone(), "mock", password, None).await;
+    assert!(matches!(refused, Err(Error::AppPasswordError(401))));
+
+    let otp = Some(common::MOCK_TOKEN);
+    let details = LoginDetails::from_user_credentials(server, "mock", password, otp)
+        .await
+        .unwrap();
+    assert_eq!(details.app_password, "mock-app-password");
+}
@@ -0,0 +1,62 @@
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use common::MockServer;
+use futures::future::BoxFuture;
+use nextcloud_passwords_client::{
+    runtime::{set_runtime, Runtime},
+    LoginDetails, LoginFlow2Options,
+};
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+use std::time::Duration;
+
+/// Sleeps in a thread, like a runtime without timers
+#[derive(Default)]
+struct ThreadTimer {
+    sleeps: AtomicU32,
+}
+
+impl Runtime for ThreadTimer {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        self.sleeps.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        std::thread::spawn(move || {
+            std::thread::sleep(duration);
+            let _ = sender.send(());
+        });
+        Box::pin(async move {
+            let _ = receiver.await;
+        })
+    }
+
+    fn spawn(&self, future: BoxFuture<'static, ()>) {
+        std::thread::spawn(move || futures::executor::block_on(future));
+    }
+}
+
+#[tokio::test]
+async fn login_flow_poll_loop() {
+    let timer = Arc::new(ThreadTimer::default());
+    set_runtime(timer.clone());
+
+    let server = MockServer::start_with_pending_login(3);
+    let options = LoginFlow2Options {
+        poll_interval: Duration::from_millis(10),
+        timeout: Duration::from_secs(5),
+        ..LoginFlow2Options::default()
+    };
+    let details = LoginDetails::register_login_flow_2_with_options(
+        server,
+        reqwest::Client::new(),
+        options,
+        |_| (),
+    )
+    .await
+    .unwrap();
+    assert_eq!(details.login_name, "mock");
+    // One wait between each pending poll
+    assert!(timer.sleeps.load(Ordering::SeqCst) >= 3);
+}
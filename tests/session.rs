@@ -0,0 +1,347 @@
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use nextcloud_passwords_client::{
+    password::CreatePassword, AuthenticatedApi, Error, ErrorCode, LoginDetails, ResumeState,
+    RetryPolicy,
+};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn keepalive() {
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    api.keep_alive().await.unwrap();
+
+    let api = Arc::new(api);
+    let mut handle = api.clone().spawn_keepalive();
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    assert!(handle.try_failed().is_none());
+    drop(handle);
+
+    // The task stops and releases the api once the handle is dropped
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    let api = Arc::try_unwrap(api)
+        .ok()
+        .expect("keepalive task still running");
+    api.disconnect().await.unwrap();
+}
+
+#[test]
+fn keepalive_runtime_shutdown() {
+    let runtime = || {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    };
+    let first = runtime();
+    let mut handle = first.block_on(async {
+        let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+            .await
+            .unwrap();
+        Arc::new(api).spawn_keepalive()
+    });
+    // The task is dropped with its runtime
+    drop(first);
+    assert!(runtime().block_on(handle.failed()).is_none());
+}
+
+/// Close the session of `api` from another handle on it, like an expiry on the server
+async fn expire(api: &AuthenticatedApi) {
+    let (other, _) = AuthenticatedApi::resume_session(api.get_state())
+        .await
+        .unwrap();
+    other.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn reopen_expired() {
+    let sessions = Arc::new(AtomicU64::new(0));
+    let server = common::MockServer::start_with_session_counter(sessions.clone());
+    let (api, session) = AuthenticatedApi::new_session(common::mock_login_details_for(server))
+        .await
+        .unwrap();
+    expire(&api).await;
+
+    let (passwords, folders) = (api.password(), api.folder());
+    let (first, second) = futures::join!(passwords.list(None), folders.list(None));
+    first.unwrap();
+    second.unwrap();
+    assert_ne!(api.session_id(), session);
+    let reopened = api.session_id();
+    api.keep_alive().await.unwrap();
+    assert_eq!(api.session_id(), reopened);
+    let opened = sessions.load(Ordering::SeqCst);
+    assert_eq!(opened, 2, "only one session is reopened");
+
+    api.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn reopen_disabled() {
+    let (mut api, session) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    api.set_auto_reopen(false);
+    expire(&api).await;
+
+    let result = api.password().list(None).await;
+    assert!(matches!(result, Err(Error::EndpointError(_))));
+    assert_eq!(api.session_id(), session);
+}
+
+#[tokio::test]
+async fn missing_session_header() {
+    let login = common::mock_login_details_for(common::MockServer::start_without_session_header());
+    let result = AuthenticatedApi::new_session(login).await;
+    assert!(matches!(result, Err(Error::MissingSessionHeader)));
+}
+
+#[tokio::test]
+async fn app_not_installed() {
+    let login = common::mock_login_details_for(common::MockServer::start_without_app());
+    let result = AuthenticatedApi::new_session(login).await;
+    assert!(matches!(
+        result,
+        Err(Error::AppNotInstalled { status: 404 })
+    ));
+}
+
+#[tokio::test]
+async fn maintenance_mode() {
+    let maintenance = Arc::new(AtomicBool::new(false));
+    let server = common::MockServer::start_with_maintenance(maintenance.clone());
+    let login = common::mock_login_details_for(server);
+    let (api, _) = AuthenticatedApi::new_session(login.clone()).await.unwrap();
+
+    maintenance.store(true, Ordering::SeqCst);
+    let result = api.password().list(None).await;
+    assert!(matches!(
+        result,
+        Err(Error::MaintenanceMode { status: 503 })
+    ));
+    let result = AuthenticatedApi::new_session(login).await;
+    assert!(matches!(
+        result,
+        Err(Error::MaintenanceMode { status: 503 })
+    ));
+}
+
+#[tokio::test]
+async fn custom_client() {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .unwrap();
+    let (api, _) = AuthenticatedApi::new_session_with_client(common::mock_login_details(), client)
+        .await
+        .unwrap();
+    let state = api.get_state();
+
+    // Every request goes through the client, here to an unreachable proxy
+    let proxy = reqwest::Proxy::all("http://127.0.0.1:1").unwrap();
+    let unreachable = reqwest::Client::builder().proxy(proxy).build().unwrap();
+    let result = AuthenticatedApi::resume_session_with_client(state, unreachable).await;
+    assert!(matches!(result, Err(Error::ApiError(_))));
+
+    api.disconnect().await.unwrap();
+}
+
+async fn preview(api: &AuthenticatedApi) -> Result<bytes::Bytes, Error> {
+    let service = api.service();
+    service
+        .preview("example.com".into(), None, None, None)
+        .await
+}
+
+#[tokio::test]
+async fn timeout() {
+    let (mut api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    api.set_timeout(Some(Duration::from_millis(100)));
+    api.password().list(None).await.unwrap();
+    let result = preview(&api).await;
+    assert!(matches!(result, Err(Error::Timeout { .. })));
+
+    api.set_media_timeout(Some(Duration::from_secs(5)));
+    preview(&api).await.unwrap();
+
+    api.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn accessors() {
+    let (api, session) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    assert_eq!(api.session_id(), session);
+    assert_eq!(api.keepalive(), 600);
+
+    let opened = api.last_request();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    api.password().list(None).await.unwrap();
+    assert!(api.last_request() > opened);
+
+    api.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn resume_closed_session() {
+    let (api, session) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    let state = api.get_state();
+    api.disconnect().await.unwrap();
+
+    let (resumed, resumed_session) = AuthenticatedApi::resume_session(state.clone())
+        .await
+        .unwrap();
+    assert_ne!(resumed_session, session);
+    resumed.password().list(None).await.unwrap();
+
+    let strict = AuthenticatedApi::resume_session(state.strict()).await;
+    assert!(matches!(strict, Err(Error::EndpointError(_))));
+}
+
+#[tokio::test]
+async fn destroy_app_password() {
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    api.destroy_app_password().await.unwrap();
+
+    // The session is already closed
+    let (mut api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    api.set_auto_reopen(false);
+    expire(&api).await;
+    api.destroy_app_password().await.unwrap();
+
+    let login = LoginDetails {
+        app_password: "unknown".into(),
+        ..common::mock_login_details()
+    };
+    let (api, _) = AuthenticatedApi::new_session(login).await.unwrap();
+    let result = api.destroy_app_password().await;
+    assert!(matches!(result, Err(Error::AppPasswordNotRevoked(401))));
+}
+
+#[tokio::test]
+async fn subdirectory() {
+    let server = common::MockServer::start();
+    for path in &["cloud", "cloud/"] {
+        let login = common::mock_login_details_for(server.join(path).unwrap());
+        let (api, _) = AuthenticatedApi::new_session(login).await.unwrap();
+        api.password().list(None).await.unwrap();
+        api.capabilities().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn pretty_urls() {
+    let server = common::MockServer::start_with_pretty_urls();
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details_for(server))
+        .await
+        .unwrap();
+    api.password().list(None).await.unwrap();
+
+    let (resumed, _) = AuthenticatedApi::resume_session(api.get_state())
+        .await
+        .unwrap();
+    resumed.password().list(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn affinity_cookies() {
+    let login = common::mock_login_details_for(common::MockServer::start_with_affinity());
+    let (api, session) = AuthenticatedApi::new_session(login).await.unwrap();
+    api.password().list(None).await.unwrap();
+
+    let state = api.get_state();
+    let (resumed, resumed_session) = AuthenticatedApi::resume_session(state.clone().strict())
+        .await
+        .unwrap();
+    assert_eq!(resumed_session, session);
+    resumed.password().list(None).await.unwrap();
+
+    // The authentication cookies are not persisted
+    let mut state = serde_json::to_value(&state).unwrap();
+    let cookies = state["cookies"].as_object().unwrap();
+    assert_eq!(cookies.keys().collect::<Vec<_>>(), ["route"]);
+
+    // Without the cookies the balancer sends the requests to another node
+    state.as_object_mut().unwrap().remove("cookies");
+    let state: ResumeState = serde_json::from_value(state).unwrap();
+    let result = AuthenticatedApi::resume_session(state.strict()).await;
+    assert!(matches!(result, Err(Error::EndpointError(_))));
+}
+
+#[tokio::test]
+async fn clock_skew() {
+    let login = common::mock_login_details_for(common::MockServer::start_with_clock_skew(3600));
+    let (api, session) = AuthenticatedApi::new_session(login).await.unwrap();
+    assert!((api.server_time_offset() - 3600).abs() <= 1);
+
+    // The state is in the clock of the server, the session is resumed
+    let state = api.get_state();
+    let ahead = state
+        .last_request()
+        .duration_since(api.last_request())
+        .unwrap();
+    assert!((ahead.as_secs() as i64 - 3600).abs() <= 1);
+    let (resumed, resumed_session) = AuthenticatedApi::resume_session(state.clone().strict())
+        .await
+        .unwrap();
+    assert_eq!(resumed_session, session);
+    assert!((resumed.server_time_offset() - 3600).abs() <= 1);
+
+    // The last request was an hour ago for the server, longer than the session lifetime
+    let mut state = serde_json::to_value(&state).unwrap();
+    state["shutdown_time"] = serde_json::to_value(std::time::SystemTime::now()).unwrap();
+    let state: ResumeState = serde_json::from_value(state).unwrap();
+    let (_, reopened_session) = AuthenticatedApi::resume_session(state).await.unwrap();
+    assert_ne!(reopened_session, session);
+}
+
+fn fast_retries(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay: Duration::from_millis(1),
+        jitter: Duration::from_millis(1),
+        ..RetryPolicy::default()
+    }
+}
+
+#[tokio::test]
+async fn retry_policy() {
+    let server = common::MockServer::start_with_transient_failures(2);
+    let (mut api, _) = AuthenticatedApi::new_session(common::mock_login_details_for(server))
+        .await
+        .unwrap();
+    assert!(api.password().list(None).await.is_err());
+    api.set_retry_policy(Some(fast_retries(3)));
+    api.password().list(None).await.unwrap();
+
+    let server = common::MockServer::start_with_transient_failures(5);
+    let (mut api, _) = AuthenticatedApi::new_session(common::mock_login_details_for(server))
+        .await
+        .unwrap();
+    api.set_retry_policy(Some(fast_retries(3)));
+    match api.password().list(None).await {
+        Err(e @ Error::RetriesExhausted { attempts: 3, .. }) => {
+            assert_eq!(e.code(), ErrorCode::ServerMaintenance)
+        }
+        _ => panic!("the request did not fail after 3 attempts"),
+    }
+
+    // The modifications are not retried by default
+    let password = CreatePassword::new("Example", "hunter2");
+    let result = api.password().create(password).await;
+    assert!(matches!(result, Err(Error::MaintenanceMode { .. })));
+}
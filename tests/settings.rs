@@ -0,0 +1,215 @@
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use nextcloud_passwords_client::{
+    folder::{FolderSearch, ParentInfo},
+    settings::{ClientSettings, SettingScope, SettingValue, SettingVariant, Settings, Version},
+    AuthenticatedApi, Error, QueryKind, SearchQuery,
+};
+
+#[tokio::test]
+async fn set() {
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+
+    let settings = Settings::new().password_strength(4).session_lifetime(600);
+    let values = api.settings().set(settings).await.unwrap();
+    assert_eq!(values.len(), 2);
+    assert!(matches!(values[0], SettingValue::PasswordStrength(4)));
+    assert!(matches!(values[1], SettingValue::SessionLifetime(600)));
+    assert_eq!(api.settings().get().password_strength().await.unwrap(), 4);
+
+    let server: Settings =
+        serde_json::from_value(serde_json::json!({"server.version": "2020.1"})).unwrap();
+    let result = api.settings().set(server).await;
+    assert!(matches!(result, Err(Error::InvalidSetting)));
+
+    api.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn list() {
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+
+    let all = api.settings().list(None).await.unwrap();
+    assert_eq!(all.session_lifetime, Some(600));
+    assert_eq!(all.version.as_deref(), Some("20.0.0"));
+    assert_eq!(all.client["client.example.theme"], "dark");
+
+    let user = api.settings().list(Some(SettingScope::User)).await.unwrap();
+    assert_eq!(user.password_strength, Some(1));
+    assert!(user.version.is_none());
+    assert!(user.client.is_empty());
+
+    let client = api
+        .settings()
+        .list(Some(SettingScope::Client))
+        .await
+        .unwrap();
+    assert!(client.session_lifetime.is_none());
+    assert_eq!(client.client.len(), 1);
+
+    api.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn client() {
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    let cursor = || ClientSettings {
+        name: "sync.cursor".into(),
+    };
+
+    let set = api.settings().set_client(cursor(), 42).await.unwrap();
+    assert_eq!(set, 42);
+    let value: Option<u32> = api.settings().get().client_setting(cursor()).await.unwrap();
+    assert_eq!(value, Some(42));
+
+    let long_name = ClientSettings {
+        name: "x".repeat(49),
+    };
+    let result = api.settings().set_client(long_name, 42).await;
+    assert!(matches!(
+        result,
+        Err(Error::SettingTooLarge { limit: 48, .. })
+    ));
+    let result = api.settings().set_client(cursor(), "x".repeat(127)).await;
+    assert!(matches!(
+        result,
+        Err(Error::SettingTooLarge {
+            length: 129,
+            limit: 128,
+            ..
+        })
+    ));
+
+    api.settings().delete_client(cursor()).await.unwrap();
+    let client = api.settings().list(Some(SettingScope::Client)).await;
+    assert!(!client.unwrap().client.contains_key("client.sync.cursor"));
+
+    api.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn reset() {
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    let settings = Settings::new().password_strength(4).session_lifetime(1200);
+    api.settings().set(settings).await.unwrap();
+
+    let reset = [
+        SettingVariant::PasswordStrength,
+        SettingVariant::SessionLifetime,
+    ];
+    let values = api.settings().reset_multiple(&reset).await.unwrap();
+    assert!(matches!(values[0], SettingValue::PasswordStrength(1)));
+    assert!(matches!(values[1], SettingValue::SessionLifetime(600)));
+
+    let client = api
+        .settings()
+        .reset_multiple(&[SettingVariant::Client])
+        .await;
+    assert!(matches!(client, Err(Error::InvalidSetting)));
+    let server = api
+        .settings()
+        .reset_multiple(&[SettingVariant::Version])
+        .await;
+    assert!(matches!(server, Err(Error::InvalidSetting)));
+
+    api.settings()
+        .set(Settings::new().password_strength(4))
+        .await
+        .unwrap();
+    let values = api.settings().reset_all_user().await.unwrap();
+    assert_eq!(values.len(), 14);
+    assert_eq!(api.settings().get().password_strength().await.unwrap(), 1);
+
+    api.disconnect().await.unwrap();
+}
+
+#[test]
+fn versions() {
+    assert_eq!(
+        "2019.5.0".parse::<Version>().unwrap(),
+        Version::new(2019, 5, 0)
+    );
+    assert_eq!("20".parse::<Version>().unwrap(), Version::new(20, 0, 0));
+    assert_eq!(
+        "2020.1.0-beta".parse::<Version>().unwrap(),
+        Version::new(2020, 1, 0)
+    );
+    assert!("beta".parse::<Version>().is_err());
+    assert!(Version::new(2019, 12, 0) < Version::new(2020, 1, 0));
+}
+
+#[tokio::test]
+async fn capabilities() {
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    let capabilities = api.capabilities().await.unwrap();
+    assert_eq!(capabilities.server_version, Version::new(20, 0, 0));
+    assert_eq!(capabilities.app_version, Some(Version::new(2020, 1, 0)));
+    assert!(capabilities.can_share);
+}
+
+#[tokio::test]
+async fn missing_setting() {
+    let old = common::MockServer::start_without_setting("server.theme.label");
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details_for(old))
+        .await
+        .unwrap();
+    let missing =
+        |result| matches!(result, Err(Error::MissingSetting(name)) if name == "server.theme.label");
+    assert!(missing(api.capabilities().await.map(|_| ())));
+    assert!(missing(api.settings().get().label().await.map(|_| ())));
+    assert!(missing(
+        api.settings()
+            .get()
+            .from_variant(SettingVariant::Label)
+            .await
+            .map(|_| ())
+    ));
+    assert_eq!(api.settings().get().session_lifetime().await.unwrap(), 600);
+}
+
+#[tokio::test]
+async fn unsupported_criteria() {
+    let by_parent = || {
+        FolderSearch::new()
+            .and_parent(SearchQuery::new(
+                ParentInfo::new(uuid::Uuid::nil()),
+                QueryKind::Exact,
+            ))
+            .unwrap()
+    };
+
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    assert_eq!(api.folder().find(by_parent(), None).await.unwrap().len(), 1);
+
+    let old = common::MockServer::start_with_app_version("2019.3.1");
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details_for(old))
+        .await
+        .unwrap();
+    let result = api.folder().find(by_parent(), None).await;
+    match result {
+        Err(Error::UnsupportedServer {
+            required, actual, ..
+        }) => {
+            assert_eq!(required, Version::new(2019, 5, 0));
+            assert_eq!(actual, Version::new(2019, 3, 1));
+        }
+        _ => panic!("the search by parent was sent"),
+    }
+    let trashed = FolderSearch::new()
+        .and_trashed(SearchQuery::new(false, QueryKind::Exact))
+        .unwrap();
+    api.folder().find(trashed, None).await.unwrap();
+}
@@ -0,0 +1,43 @@
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use nextcloud_passwords_client::{share::MAX_PARTNERS_WAIT, AuthenticatedApi, Error};
+use std::time::Duration;
+
+#[tokio::test]
+async fn partners_rate_limit() {
+    let server = common::MockServer::start_with_rate_limit(2);
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details_for(server))
+        .await
+        .unwrap();
+
+    let result = api.share().partners(None, None).await;
+    match result {
+        Err(Error::RateLimited { retry_after }) => assert_eq!(retry_after, Duration::from_secs(1)),
+        _ => panic!("the rate limit was not reported"),
+    }
+
+    let partners = api.share().partners_throttled(None, None).await.unwrap();
+    assert_eq!(partners.len(), 1);
+    assert_eq!(partners[0].display_name, "Bob");
+}
+
+#[tokio::test]
+async fn partners_long_rate_limit() {
+    let server = common::MockServer::start_with_retry_after(1, 3600);
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details_for(server))
+        .await
+        .unwrap();
+
+    // Waiting an hour is not an option while the user types
+    let started = std::time::Instant::now();
+    let result = api.share().partners_throttled(None, None).await;
+    match result {
+        Err(Error::RateLimited { retry_after }) => {
+            assert_eq!(retry_after, Duration::from_secs(3600))
+        }
+        _ => panic!("the rate limit was not reported"),
+    }
+    assert!(started.elapsed() < MAX_PARTNERS_WAIT);
+    assert_eq!(api.share().partners(None, None).await.unwrap().len(), 1);
+}
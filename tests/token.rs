@@ -0,0 +1,117 @@
+#[path = "../examples/common/mod.rs"]
+mod common;
+
+use nextcloud_passwords_client::{
+    token::{TokenProvider, TokenRequest},
+    AuthenticatedApi, Error,
+};
+
+#[tokio::test]
+async fn request() {
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+
+    let email = api.token().request(&TokenProvider::Email).await.unwrap();
+    assert_eq!(email, TokenRequest::Requested);
+    let notification = api.token().request(&TokenProvider::Notification).await;
+    assert_eq!(
+        notification.unwrap(),
+        TokenRequest::Notification { id: "42".into() }
+    );
+    let custom = TokenProvider::Custom("twofactor_u2f".into());
+    let custom = api.token().request(&custom).await.unwrap();
+    assert_eq!(custom, TokenRequest::Custom(serde_json::json!({})));
+
+    api.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn providers() {
+    let server = common::MockServer::start_with_tokens(vec![serde_json::json!({
+        "type": "user-token",
+        "id": "totp",
+        "label": "TOTP (Authenticator app)",
+        "description": "Authenticate with a TOTP app",
+        "request": false,
+    })]);
+    let login = common::mock_login_details_for(server);
+    let providers = match AuthenticatedApi::new_session(login.clone()).await {
+        Err(Error::TokenRequired(providers)) => providers,
+        r => panic!("expected the token to be required, got {:?}", r.map(|_| ())),
+    };
+    assert_eq!(providers.len(), 1);
+    let provider = &providers[0].id;
+    assert!(matches!(
+        AuthenticatedApi::new_session_with_token(login.clone(), provider, "000000").await,
+        Err(Error::TokenRejected)
+    ));
+    let (api, _) = AuthenticatedApi::new_session_with_token(login, provider, common::MOCK_TOKEN)
+        .await
+        .unwrap();
+
+    let providers = api.token().providers().await.unwrap();
+    assert_eq!(providers.len(), 1);
+    assert_eq!(providers[0].id, TokenProvider::Totp);
+    assert!(!providers[0].needs_request);
+
+    let (api, _) = AuthenticatedApi::new_session(common::mock_login_details())
+        .await
+        .unwrap();
+    assert!(api.token().providers().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn request_before_session() {
+    let server = common::MockServer::start_with_tokens(vec![serde_json::json!({
+        "type": "user-token",
+        "id": "email",
+        "label": "Email",
+        "description": "Send a code by email",
+        "request": true,
+    })]);
+    let login = common::mock_login_details_for(server);
+    let providers = match AuthenticatedApi::new_session(login.clone()).await {
+        Err(Error::TokenRequired(providers)) => providers,
+        r => panic!("expected the token to be required, got {:?}", r.map(|_| ())),
+    };
+    let provider = &providers[0].id;
+    assert!(providers[0].needs_request);
+    // The code was not sent yet
+    assert!(matches!(
+        AuthenticatedApi::new_session_with_token(login.clone(), provider, common::MOCK_TOKEN).await,
+        Err(Error::TokenRejected)
+    ));
+
+    let mut wrong = login.clone();
+    wrong.app_password = "wrong".into();
+    assert!(AuthenticatedApi::request_token(&wrong, provider)
+        .await
+        .is_err());
+    let requested = AuthenticatedApi::request_token(&login, provider).await;
+    assert_eq!(requested.unwrap(), TokenRequest::Requested);
+    let client = reqwest::Client::new();
+    let (api, _) = AuthenticatedApi::new_session_with_token_and_client(
+        login,
+        provider,
+        common::MOCK_TOKEN,
+        client,
+    )
+    .await
+    .unwrap();
+    api.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn request_before_session_typed() {
+    let login = common::mock_login_details();
+    let notification = AuthenticatedApi::request_token(&login, &TokenProvider::Notification).await;
+    assert_eq!(
+        notification.unwrap(),
+        TokenRequest::Notification { id: "42".into() }
+    );
+    let custom = TokenProvider::Custom("twofactor_u2f".into());
+    let client = reqwest::Client::new();
+    let custom = AuthenticatedApi::request_token_with_client(&login, &custom, client).await;
+    assert_eq!(custom.unwrap(), TokenRequest::Custom(serde_json::json!({})));
+}